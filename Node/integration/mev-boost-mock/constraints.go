@@ -243,6 +243,64 @@ func (m *ConstraintsMessage) UnmarshalSSZ(buf []byte) (err error) {
 	return
 }
 
+// HashTreeRoot is the root signed by the proposer in the builder domain
+func (m *ConstraintsMessage) HashTreeRoot() ([32]byte, error) {
+	return ssz.HashWithDefaultHasher(m)
+}
+
+func (m *ConstraintsMessage) HashTreeRootWith(hh ssz.HashWalker) (err error) {
+	indx := hh.Index()
+
+	// Field (0) `ValidatorIndex`
+	hh.PutUint64(m.ValidatorIndex)
+
+	// Field (1) `Slot`
+	hh.PutUint64(m.Slot)
+
+	// Field (2) `Constraints`
+	{
+		subIndx := hh.Index()
+		num := uint64(len(m.Constraints))
+		if num > MAX_CONSTRAINTS_PER_SLOT {
+			err = ssz.ErrIncorrectListSize
+			return
+		}
+		for _, constraint := range m.Constraints {
+			if err = constraint.HashTreeRootWith(hh); err != nil {
+				return
+			}
+		}
+		hh.MerkleizeWithMixin(subIndx, num, MAX_CONSTRAINTS_PER_SLOT)
+	}
+
+	hh.Merkleize(indx)
+	return
+}
+
+func (c *Constraint) HashTreeRootWith(hh ssz.HashWalker) (err error) {
+	indx := hh.Index()
+
+	// Field (0) `Tx`
+	if err = c.Tx.HashTreeRootWith(hh); err != nil {
+		return
+	}
+
+	// Field (1) `Index`, the Union[None, uint64] root is mixed in with its selector
+	{
+		subIndx := hh.Index()
+		if c.Index == nil {
+			hh.PutUint64(0)
+			hh.MerkleizeWithMixin(subIndx, 0, 1)
+		} else {
+			hh.PutUint64(uint64(*c.Index))
+			hh.MerkleizeWithMixin(subIndx, 1, 1)
+		}
+	}
+
+	hh.Merkleize(indx)
+	return
+}
+
 func (c *Constraint) MarshalSSZ() ([]byte, error) {
 	return ssz.MarshalSSZ(c)
 }
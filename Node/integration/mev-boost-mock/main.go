@@ -10,7 +10,8 @@ import (
 	"github.com/gorilla/mux"
 	"github.com/flashbots/go-boost-utils/utils"
 	"github.com/flashbots/go-boost-utils/bls"
-	//"github.com/flashbots/go-boost-utils/ssz"
+	"github.com/flashbots/go-boost-utils/ssz"
+	"github.com/attestantio/go-eth2-client/spec/phase0"
 )
 
 // Define the structure of the incoming data
@@ -89,14 +90,16 @@ func handleSubmitConstraint(w http.ResponseWriter, req *http.Request) {
 
 		message := signedConstraints.Message
 
-		// NOTE: even if payload is sent with JSON, the signature digest is the SSZ encoding of the message
-		messageSSZ, err := message.MarshalSSZ()
-		fmt.Println("messageSSZ: ", messageSSZ)
+		// NOTE: even if payload is sent with JSON, the signature digest is the signing root of the
+		// message in the builder domain. The genesis fork version is hardcoded here
+		domain := ssz.ComputeDomain(ssz.DomainTypeAppBuilder, phase0.Version{0, 0, 0, 0}, phase0.Root{})
+		signingRoot, err := ssz.ComputeSigningRoot(message, domain)
+		fmt.Println("signingRoot: ", signingRoot)
 		if err != nil {
-			fmt.Println("could not marshal constraint message to json: ", err)
+			fmt.Println("could not compute the signing root of the constraint message: ", err)
 			return
 		}
-		sigRes, err := bls.VerifySignature(signature, blsPublicKey, messageSSZ)
+		sigRes, err := bls.VerifySignature(signature, blsPublicKey, signingRoot[:])
 		if err != nil {
 			fmt.Println("error while veryfing signature: ", err)
 			return
@@ -31,16 +31,14 @@ func (tx *Transaction) HashTreeRootWith(hh ssz.HashWalker) error {
 		return err
 	}
 
+	// The transaction can be nested in another type, so only the part of the buffer from the
+	// current index is merkleized
+	indx := hh.Index()
 	// Load the bytes of the transaction into the hasher
 	hh.AppendBytes32(*tx)
 	// Perform `mix_in_length(merkleize(pack(value), limit=chunk_count(type)), len(value))`
 	// Reference: https://github.com/ethereum/consensus-specs/blob/dev/ssz/simple-serialize.md#merkleization
-	//
-	// The `indx` parameters is set to `0` as we need to consider the whole hh.buf buffer for this.
-	// In an implementation of more complex types, this parameter would be used to indicate the starting
-	// index of the buffer to be merkleized. It is used a single buffer to do everything for
-	// optimization purposes.
-	hh.MerkleizeWithMixin(0, byteLen, (1073741824+31)/32)
+	hh.MerkleizeWithMixin(indx, byteLen, (1073741824+31)/32)
 
 	return nil
 }
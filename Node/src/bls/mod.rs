@@ -1,11 +1,12 @@
 use alloy::primitives::U256;
 use anyhow::Error;
-use bls::types::{G1AffinePoint, G2AffinePoint, PublicKey, SecretKey, Signature};
+use bls::types::{PublicKey, SecretKey, Signature};
 use bls_on_arkworks as bls;
 use num_bigint::BigUint;
 #[cfg(test)]
 #[cfg(not(feature = "use_mock"))]
 use rand_core::{OsRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
 
 pub struct BLSService {
     pk: PublicKey,
@@ -14,9 +15,15 @@ pub struct BLSService {
 
 impl BLSService {
     pub fn new(private_key: &str) -> Result<Self, Error> {
-        let pk_bytes = alloy::hex::decode(private_key)
-            .map_err(|e| anyhow::anyhow!("BLSService: failed to decode private key: {}", e))?;
-        let sk = bls::os2ip(&pk_bytes);
+        let sk_bytes = Zeroizing::new(
+            alloy::hex::decode(private_key)
+                .map_err(|e| anyhow::anyhow!("BLSService: failed to decode private key: {}", e))?,
+        );
+        Ok(Self::from_secret_key_bytes(&sk_bytes))
+    }
+
+    pub fn from_secret_key_bytes(sk_bytes: &[u8]) -> Self {
+        let sk = bls::os2ip(&Zeroizing::new(sk_bytes.to_vec()));
         let public_key = bls::sk_to_pk(sk);
 
         tracing::info!(
@@ -24,7 +31,7 @@ impl BLSService {
             hex::encode(public_key.clone())
         );
 
-        Ok(Self { pk: public_key, sk })
+        Self { pk: public_key, sk }
     }
 
     #[cfg(test)]
@@ -43,11 +50,6 @@ impl BLSService {
         bls::sign(self.sk, message, dst).unwrap()
     }
 
-    pub fn sign_as_point(&self, message: &Vec<u8>, dst: &Vec<u8>) -> G2AffinePoint {
-        let sign = self.sign(message, dst);
-        bls::signature_to_point(&sign).unwrap()
-    }
-
     pub fn biguint_to_u256_array(biguint: BigUint) -> [U256; 2] {
         let s = format!("{:0>96x}", biguint);
         let res1 = U256::from_str_radix(&s[0..32], 16).unwrap();
@@ -56,13 +58,13 @@ impl BLSService {
         [res1, res2]
     }
 
-    #[cfg(test)]
-    #[cfg(not(feature = "use_mock"))]
     pub fn get_public_key_compressed(&self) -> PublicKey {
         self.pk.clone()
     }
+}
 
-    pub fn get_public_key(&self) -> G1AffinePoint {
-        bls::pubkey_to_point(&self.pk).unwrap()
+impl Drop for BLSService {
+    fn drop(&mut self) {
+        self.sk.zeroize();
     }
 }
@@ -3,8 +3,16 @@ use alloy::transports::TransportError;
 
 pub trait AVSContractError {
     fn to_avs_contract_error(&self) -> String;
+    /// Selector of the custom error the contract reverted with, if any.
+    fn avs_contract_error_code(&self) -> Option<u32>;
 }
 
+pub const MISSED_DISPUTE_WINDOW: u32 = 0x005b3ac2;
+pub const PRECONFIRMATION_IS_CORRECT: u32 = 0x7855bfd4;
+pub const POSTER_ALREADY_SLASHED_OR_LOOKAHEAD_IS_EMPTY: u32 = 0xf23592f9;
+pub const LOOKAHEAD_ENTRY_IS_CORRECT: u32 = 0xdd52015e;
+pub const OPERATOR_ALREADY_SLASHED: u32 = 0x54357847;
+
 impl AVSContractError for Error {
     fn to_avs_contract_error(&self) -> String {
         let convert_error_code_to_message = |error_code: u32| -> String {
@@ -47,28 +55,27 @@ impl AVSContractError for Error {
             }
         };
 
+        match self.avs_contract_error_code() {
+            Some(error_code) => convert_error_code_to_message(error_code),
+            None => format!("Unknown error {:#?}", self),
+        }
+    }
+
+    fn avs_contract_error_code(&self) -> Option<u32> {
         if let Error::TransportError(TransportError::ErrorResp(e)) = self {
             if let Some(data) = e.data.as_ref().map(|d| d.get()) {
-                if let Ok(error_code) = u32::from_str_radix(&data[3..data.len() - 1], 16) {
-                    return convert_error_code_to_message(error_code);
-                }
-            } else {
-                if e.message.starts_with("Reverted ") {
-                    if e.message.len() == 13 {
-                        let code = &e.message[9..13];
-                        let error_code = code.as_bytes();
-                        let error_code =
-                            u32::from_be_bytes(error_code.try_into().unwrap_or([0; 4]));
-                        return convert_error_code_to_message(error_code);
-                    } else if e.message.len() == 19 {
-                        if let Ok(error_code) = u32::from_str_radix(&e.message[11..], 16) {
-                            return convert_error_code_to_message(error_code);
-                        }
-                    }
+                return u32::from_str_radix(&data[3..data.len() - 1], 16).ok();
+            } else if e.message.starts_with("Reverted ") {
+                if e.message.len() == 13 {
+                    let code = &e.message[9..13];
+                    let error_code = code.as_bytes();
+                    return Some(u32::from_be_bytes(error_code.try_into().unwrap_or([0; 4])));
+                } else if e.message.len() == 19 {
+                    return u32::from_str_radix(&e.message[11..], 16).ok();
                 }
             }
         }
-        format!("Unknown error {:#?}", self)
+        None
     }
 }
 
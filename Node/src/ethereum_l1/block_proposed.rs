@@ -1,4 +1,5 @@
-use alloy::{contract::EventSubscription, sol};
+use super::resilient_subscription::ResilientSubscription;
+use alloy::sol;
 use anyhow::Error;
 
 sol!(
@@ -8,6 +9,7 @@ sol!(
     "src/ethereum_l1/abi/TaikoEvents.json"
 );
 
+#[derive(Clone)]
 pub struct BlockProposed {
     event_data: TaikoEvents::BlockProposed,
     block_id: u64,
@@ -30,4 +32,4 @@ impl BlockProposed {
     }
 }
 
-pub struct EventSubscriptionBlockProposed(pub EventSubscription<TaikoEvents::BlockProposed>);
+pub struct EventSubscriptionBlockProposed(pub ResilientSubscription<TaikoEvents::BlockProposed>);
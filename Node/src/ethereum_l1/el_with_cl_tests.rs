@@ -30,7 +30,7 @@ mod tests {
         //     .unwrap();
         let lookahead_params = Vec::<PreconfTaskManager::LookaheadSetParam>::new();
 
-        el.propose_new_block(0, vec![0; 32], 0, lookahead_params, true)
+        el.propose_new_block(1, 0, vec![0; 32], 0, lookahead_params, true)
             .await
             .unwrap();
     }
@@ -1,66 +1,78 @@
 use super::{
-    avs_contract_error::AVSContractError,
-    block_proposed::{BlockProposed, EventSubscriptionBlockProposed, TaikoEvents},
+    avs_contract_error::{self, AVSContractError},
+    block_proposed::{BlockProposed, EventSubscriptionBlockProposed},
+    gas_oracle::{GasOracle, GasOracleConfig},
+    resilient_subscription::ResilientSubscription,
     slot_clock::SlotClock,
+    tx_tracker::{sign_and_encode, Replacement, TxKind, TxOutcome, TxTracker},
 };
 use crate::{
     bls::BLSService,
     ethereum_l1::ws_provider::WsProvider,
-    utils::{config, types::*},
+    metrics,
+    signer::{self, BlsSigner, EcdsaSigner},
+    utils::{config::ContractAddresses, types::*},
 };
 use alloy::{
     consensus::TypedTransaction,
-    contract::EventSubscription,
     network::{Ethereum, EthereumWallet, NetworkWallet},
     primitives::{Address, Bytes, FixedBytes, B256, U256},
     providers::{Provider, ProviderBuilder, WsConnect},
-    signers::{
-        local::{LocalSigner, PrivateKeySigner},
-        Signature, SignerSync,
-    },
+    signers::Signature,
     sol,
     sol_types::SolValue,
 };
 use anyhow::Error;
 use beacon_api_client::ProposerDuty;
-use ecdsa::SigningKey;
-use futures_util::StreamExt;
-use k256::Secp256k1;
 #[cfg(test)]
 use mockall::automock;
 use num_bigint::BigUint;
 use rand_core::{OsRng, RngCore};
-use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+// Used when the node can't simulate the proposal, e.g. when it is only built for MEV-Boost
+const FALLBACK_PROPOSE_BLOCK_GAS_LIMIT: u128 = 500_000;
+
+/// Result of a dispute simulated with `eth_call` before it is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisputeOutcome {
+    /// The simulation succeeded and the tx was sent.
+    Sent,
+    /// The preconfirmation or the lookahead entry is correct, or the preconfer is already slashed.
+    Rejected,
+    /// The contract rejects the proof with `MissedDisputeWindow()`.
+    MissedWindow,
+}
+
+impl DisputeOutcome {
+    // Reverts which don't change by retrying, the others are treated as transient
+    fn from_revert(err: &alloy::contract::Error) -> Option<Self> {
+        match err.avs_contract_error_code()? {
+            avs_contract_error::MISSED_DISPUTE_WINDOW => Some(Self::MissedWindow),
+            avs_contract_error::PRECONFIRMATION_IS_CORRECT
+            | avs_contract_error::LOOKAHEAD_ENTRY_IS_CORRECT
+            | avs_contract_error::POSTER_ALREADY_SLASHED_OR_LOOKAHEAD_IS_EMPTY
+            | avs_contract_error::OPERATOR_ALREADY_SLASHED => Some(Self::Rejected),
+            _ => None,
+        }
+    }
+}
 
 pub struct ExecutionLayer {
+    ws_rpc_url: String,
     provider_ws: WsProvider,
-    signer: LocalSigner<SigningKey<Secp256k1>>,
+    ecdsa_signer: Arc<dyn EcdsaSigner>,
     wallet: EthereumWallet,
     preconfer_address: Address,
     contract_addresses: ContractAddresses,
     slot_clock: Arc<SlotClock>,
     msg_expiry_sec: u64,
     l1_chain_id: u64,
-    bls_service: Arc<BLSService>,
-}
-
-pub struct ContractAddresses {
-    pub taiko_l1: Address,
-    pub eigen_layer: EigenLayerContractAddresses,
-    pub avs: AvsContractAddresses,
-}
-
-pub struct EigenLayerContractAddresses {
-    pub strategy_manager: Address,
-    pub slasher: Address,
-}
-
-pub struct AvsContractAddresses {
-    pub preconf_task_manager: Address,
-    pub directory: Address,
-    pub service_manager: Address,
-    pub preconf_registry: Address,
+    bls_signer: Arc<dyn BlsSigner>,
+    gas_oracle: GasOracle,
+    tx_tracker: TxTracker,
 }
 
 sol!(
@@ -122,6 +134,32 @@ sol!(
     "src/ethereum_l1/abi/PreconfRegistry.json"
 );
 
+// The part of the TaikoL1 state read by the node
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface TaikoL1 {
+        struct SlotA {
+            uint64 genesisHeight;
+            uint64 genesisTimestamp;
+            uint64 lastSyncedBlockId;
+            uint64 lastSyncedAt;
+        }
+
+        struct SlotB {
+            uint64 numBlocks;
+            uint64 lastVerifiedBlockId;
+            bool provingPaused;
+            uint8 reservedB1;
+            uint16 reservedB2;
+            uint32 reservedB3;
+            uint64 lastUnpausedAt;
+        }
+
+        function getStateVariables() external view returns (SlotA memory, SlotB memory);
+    }
+);
+
 sol! (
     struct MessageData {
         uint256 chainId;
@@ -132,31 +170,29 @@ sol! (
 );
 
 pub struct EventSubscriptionLookaheadUpdated(
-    pub EventSubscription<PreconfTaskManager::LookaheadUpdated>,
+    pub ResilientSubscription<PreconfTaskManager::LookaheadUpdated>,
 );
 
 #[cfg_attr(test, allow(dead_code))]
 #[cfg_attr(test, automock)]
 impl ExecutionLayer {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         ws_rpc_url: &str,
-        avs_node_ecdsa_private_key: &str,
-        contract_addresses: &config::ContractAddresses,
+        ecdsa_signer: Arc<dyn EcdsaSigner>,
+        contract_addresses: &ContractAddresses,
         slot_clock: Arc<SlotClock>,
         msg_expiry_sec: u64,
-        bls_service: Arc<BLSService>,
+        bls_signer: Arc<dyn BlsSigner>,
         l1_chain_id: u64,
+        gas_oracle_config: GasOracleConfig,
     ) -> Result<Self, Error> {
         tracing::debug!("Creating ExecutionLayer with WS URL: {}", ws_rpc_url);
 
-        let signer = PrivateKeySigner::from_str(avs_node_ecdsa_private_key)?;
-        let preconfer_address: Address = signer.address();
+        let preconfer_address: Address = ecdsa_signer.address();
         tracing::info!("AVS node address: {}", preconfer_address);
 
-        let wallet = EthereumWallet::from(signer.clone());
-
-        let contract_addresses = Self::parse_contract_addresses(contract_addresses)
-            .map_err(|e| Error::msg(format!("Failed to parse contract addresses: {}", e)))?;
+        let wallet = signer::create_wallet(ecdsa_signer.clone());
 
         let ws = WsConnect::new(ws_rpc_url.to_string());
 
@@ -167,49 +203,42 @@ impl ExecutionLayer {
             .await
             .unwrap();
 
+        let gas_oracle = GasOracle::new(gas_oracle_config)?;
+        let tx_tracker = TxTracker::new(
+            provider_ws.clone(),
+            wallet.clone(),
+            preconfer_address,
+            gas_oracle.clone(),
+            slot_clock.get_slot_duration(),
+        );
+
         Ok(Self {
+            ws_rpc_url: ws_rpc_url.to_string(),
             provider_ws,
-            signer,
+            ecdsa_signer,
             wallet,
             preconfer_address,
-            contract_addresses,
+            contract_addresses: contract_addresses.clone(),
             slot_clock,
             msg_expiry_sec,
             l1_chain_id,
-            bls_service,
+            bls_signer,
+            gas_oracle,
+            tx_tracker,
         })
     }
 
-    pub fn get_preconfer_address(&self) -> PreconferAddress {
-        self.preconfer_address.into_array()
+    pub fn subscribe_to_tx_outcomes(&self) -> broadcast::Receiver<TxOutcome> {
+        self.tx_tracker.subscribe()
     }
 
-    fn parse_contract_addresses(
-        contract_addresses: &config::ContractAddresses,
-    ) -> Result<ContractAddresses, Error> {
-        let eigen_layer = EigenLayerContractAddresses {
-            strategy_manager: contract_addresses.eigen_layer.strategy_manager.parse()?,
-            slasher: contract_addresses.eigen_layer.slasher.parse()?,
-        };
-
-        let avs = AvsContractAddresses {
-            preconf_task_manager: contract_addresses.avs.preconf_task_manager.parse()?,
-            directory: contract_addresses.avs.directory.parse()?,
-            service_manager: contract_addresses.avs.service_manager.parse()?,
-            preconf_registry: contract_addresses.avs.preconf_registry.parse()?,
-        };
-
-        let taiko_l1 = contract_addresses.taiko_l1.parse()?;
-
-        Ok(ContractAddresses {
-            taiko_l1,
-            eigen_layer,
-            avs,
-        })
+    pub fn get_preconfer_address(&self) -> PreconferAddress {
+        self.preconfer_address.into_array()
     }
 
     pub async fn propose_new_block(
         &self,
+        block_id: u64,
         nonce: u64,
         tx_list: Vec<u8>,
         lookahead_pointer: u64,
@@ -239,9 +268,12 @@ impl ExecutionLayer {
 
         let encoded_block_params = Bytes::from(BlockParams::abi_encode_sequence(&block_params));
 
+        let tx_kind = TxKind::ProposeBlock {
+            block_id,
+            tx_list: tx_list.clone(),
+        };
         let tx_list = Bytes::from(tx_list);
 
-        // TODO check gas parameters
         let builder = contract
             .newBlockProposal(
                 encoded_block_params,
@@ -249,11 +281,26 @@ impl ExecutionLayer {
                 U256::from(lookahead_pointer),
                 lookahead_set_params,
             )
+            .from(self.preconfer_address)
             .chain_id(self.l1_chain_id)
-            .nonce(nonce)
-            .gas(500_000)
-            .max_fee_per_gas(20_000_000_000)
-            .max_priority_fee_per_gas(1_000_000_000);
+            .nonce(nonce);
+
+        let gas_limit = match builder.estimate_gas().await {
+            Ok(estimate) => self.gas_oracle.gas_limit_from_estimate(estimate),
+            Err(err) => {
+                tracing::warn!(
+                    "Failed to estimate gas for block proposal, using {}: {}",
+                    FALLBACK_PROPOSE_BLOCK_GAS_LIMIT,
+                    err.to_avs_contract_error()
+                );
+                FALLBACK_PROPOSE_BLOCK_GAS_LIMIT
+            }
+        };
+        let fees = self.gas_oracle.get_fees(&self.provider_ws).await?;
+        let builder = builder
+            .gas(gas_limit)
+            .max_fee_per_gas(fees.max_fee_per_gas)
+            .max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
 
         // Build transaction
         let tx = builder.as_ref().clone().build_typed_tx();
@@ -261,27 +308,42 @@ impl ExecutionLayer {
             return Err(anyhow::anyhow!("Not EIP1559 transaction"));
         };
 
-        // Sign transaction
-        let signature = self
-            .wallet
-            .default_signer()
-            .sign_transaction(&mut tx)
-            .await?;
-
-        // Encode transaction
-        let mut buf = vec![];
-        tx.encode_with_signature(&signature, &mut buf, false);
+        // Sign and encode transaction
+        let buf = sign_and_encode(&self.wallet, &mut tx).await?;
 
         // Send transaction
         if send_to_contract {
+            // Before the tx is broadcast, so a failure can't leave it untracked
+            let deadline = self.get_lookahead_entry_deadline(lookahead_pointer).await?;
             let pending = self.provider_ws.send_raw_transaction(&buf).await?;
 
             tracing::debug!("Proposed new block, with hash {}", pending.tx_hash());
+
+            self.tx_tracker.track_with_replacement(
+                tx_kind,
+                *pending.tx_hash(),
+                Replacement { tx, fees, deadline },
+            );
         }
 
         Ok(buf)
     }
 
+    // The proposal is only accepted up to the timestamp of the preconfer's lookahead entry.
+    async fn get_lookahead_entry_deadline(
+        &self,
+        lookahead_pointer: u64,
+    ) -> Result<Duration, Error> {
+        let lookahead_buffer = self.get_lookahead_preconfer_buffer().await?;
+        let entry = lookahead_buffer
+            .get(lookahead_pointer as usize)
+            .ok_or(anyhow::anyhow!(
+                "get_lookahead_entry_deadline: lookahead pointer {} out of range",
+                lookahead_pointer
+            ))?;
+        Ok(Duration::from_secs(entry.timestamp))
+    }
+
     pub async fn register_preconfer(&self) -> Result<(), Error> {
         tracing::debug!("Registering preconfer");
         let strategy_manager = StrategyManager::new(
@@ -334,7 +396,7 @@ impl ExecutionLayer {
         let digest_hash_bytes = vec![0u8; 32]; // Dummy value for tests
 
         // sign the digest hash with private key
-        let signature = self.signer.sign_message_sync(&digest_hash_bytes)?;
+        let signature = self.ecdsa_signer.sign_message(&digest_hash_bytes).await?;
 
         let signature_with_salt_and_expiry = PreconfRegistry::SignatureWithSaltAndExpiry {
             signature: Bytes::from(signature.as_bytes()),
@@ -346,12 +408,17 @@ impl ExecutionLayer {
             self.contract_addresses.avs.preconf_registry,
             &self.provider_ws,
         );
-        let tx = preconf_registry.registerPreconfer(signature_with_salt_and_expiry);
+        let nonce = self.get_pending_nonce().await?;
+        let tx = preconf_registry
+            .registerPreconfer(signature_with_salt_and_expiry)
+            .nonce(nonce);
 
         match tx.send().await {
             Ok(pending_tx) => {
                 let tx_hash = pending_tx.tx_hash();
                 tracing::info!("Preconfer registered: {:?}", tx_hash);
+                self.tx_tracker
+                    .track(TxKind::RegisterPreconfer, *tx_hash, nonce);
             }
             Err(err) => {
                 return Err(anyhow::anyhow!(err.to_avs_contract_error()));
@@ -389,8 +456,8 @@ impl ExecutionLayer {
         FixedBytes::from(&salt)
     }
 
-    pub fn sign_message_with_private_ecdsa_key(&self, msg: &[u8]) -> Result<[u8; 65], Error> {
-        let signature = self.signer.sign_message_sync(msg)?;
+    pub async fn sign_message_with_ecdsa_key(&self, msg: &[u8]) -> Result<[u8; 65], Error> {
+        let signature = self.ecdsa_signer.sign_message(msg).await?;
         Ok(signature.as_bytes())
     }
 
@@ -408,13 +475,23 @@ impl ExecutionLayer {
         Ok(nonce)
     }
 
+    // Counts the transactions still in the mempool, so a new one doesn't replace them
+    async fn get_pending_nonce(&self) -> Result<u64, Error> {
+        let nonce = self
+            .provider_ws
+            .get_transaction_count(self.preconfer_address)
+            .pending()
+            .await?;
+        Ok(nonce)
+    }
+
     pub async fn check_and_prove_incorrect_preconfirmation(
         &self,
         chain_id: u64,
         preconf_tx_list_hash: [u8; 32],
         preconf_signature: [u8; 65],
         block_proposed: &BlockProposed,
-    ) -> Result<(), Error> {
+    ) -> Result<DisputeOutcome, Error> {
         let contract = PreconfTaskManager::new(
             self.contract_addresses.avs.preconf_task_manager,
             &self.provider_ws,
@@ -446,30 +523,52 @@ impl ExecutionLayer {
             blobTxListOffset: proposed_meta.blobTxListOffset,
             blobTxListLength: proposed_meta.blobTxListLength,
         };
-        let result = contract
+        if let Err(err) = contract
             .proveIncorrectPreconfirmation(meta.clone(), header.clone(), signature.clone())
             .call()
-            .await;
-        if let Ok(_) = result {
-            tracing::debug!("Proved incorrect preconfirmation using eth_call, sending tx");
-            let tx = contract.proveIncorrectPreconfirmation(meta, header, signature);
-            match tx.send().await {
-                Ok(pending_tx) => {
-                    let tx_hash = pending_tx.tx_hash();
-                    tracing::debug!("Proved incorrect preconfirmation, tx sent: {tx_hash}");
-                }
-                Err(err) => {
-                    tracing::error!("Failed to prove incorrect preconfirmation: {}", err);
+            .await
+        {
+            return match DisputeOutcome::from_revert(&err) {
+                Some(outcome) => {
+                    tracing::debug!(
+                        "Preconfirmation for the block {} not disputable: {}",
+                        block_proposed.block_id(),
+                        err.to_avs_contract_error()
+                    );
+                    Ok(outcome)
                 }
-            }
-        } else {
-            tracing::debug!(
-                "Preconfirmation correct for the block {}",
-                block_proposed.block_id()
-            );
+                None => Err(anyhow::anyhow!(
+                    "Failed to simulate the incorrect preconfirmation proof: {}",
+                    err.to_avs_contract_error()
+                )),
+            };
         }
 
-        Ok(())
+        tracing::debug!("Proved incorrect preconfirmation using eth_call, sending tx");
+        let nonce = self.get_pending_nonce().await?;
+        let tx = contract
+            .proveIncorrectPreconfirmation(meta, header, signature)
+            .nonce(nonce);
+        let kind = TxKind::ProveIncorrectPreconfirmation {
+            block_id: block_proposed.block_id(),
+        };
+        match tx.send().await {
+            Ok(pending_tx) => {
+                let tx_hash = pending_tx.tx_hash();
+                tracing::debug!("Proved incorrect preconfirmation, tx sent: {tx_hash}");
+                self.tx_tracker.track(kind, *tx_hash, nonce);
+                Ok(DisputeOutcome::Sent)
+            }
+            Err(err) => {
+                metrics::L1_TX_SEND_FAILURES
+                    .with_label_values(&[kind.label()])
+                    .inc();
+                Err(anyhow::anyhow!(
+                    "Failed to prove incorrect preconfirmation: {}",
+                    err.to_avs_contract_error()
+                ))
+            }
+        }
     }
 
     pub async fn prove_incorrect_lookahead(
@@ -485,7 +584,7 @@ impl ExecutionLayer {
         beacon_state_root: [u8; 32],
         beacon_block_proof_for_state: Vec<[u8; 32]>,
         beacon_block_proof_for_proposer_index: Vec<[u8; 32]>,
-    ) -> Result<(), Error> {
+    ) -> Result<DisputeOutcome, Error> {
         let contract = PreconfTaskManager::new(
             self.contract_addresses.avs.preconf_task_manager,
             &self.provider_ws,
@@ -517,17 +616,41 @@ impl ExecutionLayer {
             Bytes::from(validator_bls_pub_key),
             validator_inclusion_proof,
         );
-        match tx.send().await {
+        if let Err(err) = tx.call().await {
+            return match DisputeOutcome::from_revert(&err) {
+                Some(outcome) => {
+                    tracing::debug!(
+                        "Lookahead entry for the slot timestamp {slot_timestamp} not disputable: {}",
+                        err.to_avs_contract_error()
+                    );
+                    Ok(outcome)
+                }
+                None => Err(anyhow::anyhow!(
+                    "Failed to simulate the incorrect lookahead proof: {}",
+                    err.to_avs_contract_error()
+                )),
+            };
+        }
+
+        let nonce = self.get_pending_nonce().await?;
+        match tx.nonce(nonce).send().await {
             Ok(pending_tx) => {
                 let tx_hash = pending_tx.tx_hash();
                 tracing::debug!("Proved incorrect lookahead: {tx_hash}");
+                self.tx_tracker
+                    .track(TxKind::ProveIncorrectLookahead, *tx_hash, nonce);
+                Ok(DisputeOutcome::Sent)
             }
             Err(err) => {
-                tracing::error!("Failed to prove incorrect lookahead: {}", err);
+                metrics::L1_TX_SEND_FAILURES
+                    .with_label_values(&[TxKind::ProveIncorrectLookahead.label()])
+                    .inc();
+                Err(anyhow::anyhow!(
+                    "Failed to prove incorrect lookahead: {}",
+                    err.to_avs_contract_error()
+                ))
             }
         }
-
-        Ok(())
     }
 
     fn convert_proof_to_fixed_bytes(proof: Vec<[u8; 32]>) -> Vec<FixedBytes<32>> {
@@ -536,35 +659,24 @@ impl ExecutionLayer {
 
     pub async fn subscribe_to_registered_event(
         &self,
-    ) -> Result<EventSubscription<PreconfRegistry::PreconferRegistered>, Error> {
-        let registry = PreconfRegistry::new(
+    ) -> Result<ResilientSubscription<PreconfRegistry::PreconferRegistered>, Error> {
+        ResilientSubscription::subscribe(
+            &self.ws_rpc_url,
             self.contract_addresses.avs.preconf_registry,
-            &self.provider_ws,
-        );
-
-        let registered_filter = registry.PreconferRegistered_filter().subscribe().await?;
-        tracing::debug!("Subscribed to registered event");
-
-        Ok(registered_filter)
+        )
+        .await
     }
 
     pub async fn wait_for_the_registered_event(
         &self,
-        registered_filter: EventSubscription<PreconfRegistry::PreconferRegistered>,
+        registered_filter: ResilientSubscription<PreconfRegistry::PreconferRegistered>,
     ) -> Result<(), Error> {
-        let mut stream = registered_filter.into_stream();
-        while let Some(log) = stream.next().await {
-            match log {
-                Ok(log) => {
-                    tracing::info!("Received PreconferRegistered for: {}", log.0.preconfer);
-                    if log.0.preconfer == self.preconfer_address {
-                        tracing::info!("Preconfer registered!");
-                        break;
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Error receiving log: {:?}", e);
-                }
+        let mut registered_filter = registered_filter;
+        while let Some(event) = registered_filter.next().await {
+            tracing::info!("Received PreconferRegistered for: {}", event.preconfer);
+            if event.preconfer == self.preconfer_address {
+                tracing::info!("Preconfer registered!");
+                break;
             }
         }
 
@@ -580,6 +692,23 @@ impl ExecutionLayer {
         Ok(tail.is_zero())
     }
 
+    /// Id of the last L2 block proposed to TaikoL1, the genesis block counts as the first one.
+    pub async fn get_last_proposed_block_id(&self) -> Result<u64, Error> {
+        let contract = TaikoL1::new(self.contract_addresses.taiko_l1, &self.provider_ws);
+        let num_blocks = contract.getStateVariables().call().await?._1.numBlocks;
+        Ok(num_blocks.saturating_sub(1))
+    }
+
+    pub async fn is_preconfer_registered(&self, preconfer: Address) -> Result<bool, Error> {
+        let contract = PreconfRegistry::new(
+            self.contract_addresses.avs.preconf_registry,
+            &self.provider_ws,
+        );
+        // index 0 means not registered, the registry starts counting from 1
+        let index = contract.getPreconferIndex(preconfer).call().await?._0;
+        Ok(!index.is_zero())
+    }
+
     pub async fn force_push_lookahead(
         &self,
         lookahead_set_params: Vec<PreconfTaskManager::LookaheadSetParam>,
@@ -594,15 +723,31 @@ impl ExecutionLayer {
             &self.provider_ws,
         );
 
+        let nonce = self.get_preconfer_nonce().await?;
         let tx = contract
             .forcePushLookahead(lookahead_set_params)
-            .nonce(self.get_preconfer_nonce().await?)
-            .gas(1_000_000);
+            .from(self.preconfer_address)
+            .nonce(nonce);
+        let gas_limit = self.gas_oracle.gas_limit_from_estimate(
+            tx.estimate_gas()
+                .await
+                .map_err(|err| anyhow::anyhow!(err.to_avs_contract_error()))?,
+        );
+        let fees = self.gas_oracle.get_fees(&self.provider_ws).await?;
+        let tx = tx
+            .gas(gas_limit)
+            .max_fee_per_gas(fees.max_fee_per_gas)
+            .max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
         match tx.send().await {
-            Ok(receipt) => {
-                tracing::debug!("Force push lookahead sent: {}", receipt.tx_hash());
+            Ok(pending_tx) => {
+                tracing::debug!("Force push lookahead sent: {}", pending_tx.tx_hash());
+                self.tx_tracker
+                    .track(TxKind::ForcePushLookahead, *pending_tx.tx_hash(), nonce);
             }
             Err(err) => {
+                metrics::L1_TX_SEND_FAILURES
+                    .with_label_values(&[TxKind::ForcePushLookahead.label()])
+                    .inc();
                 return Err(anyhow::anyhow!(err.to_avs_contract_error()));
             }
         }
@@ -626,14 +771,14 @@ impl ExecutionLayer {
         let message = data.abi_encode_packed();
 
         // Convert bls public key to G1Point
-        let pk_point = self.bls_service.get_public_key();
+        let pk_point = self.bls_signer.public_key_point()?;
         let pubkey = PreconfRegistry::G1Point {
             x: BLSService::biguint_to_u256_array(BigUint::from(pk_point.x)),
             y: BLSService::biguint_to_u256_array(BigUint::from(pk_point.y)),
         };
 
         // Sign message and convert to G2Point
-        let signature_point = self.bls_service.sign_as_point(&message, &vec![]);
+        let signature_point = self.bls_signer.sign_as_point(&message, &[]).await?;
 
         let signature = PreconfRegistry::G2Point {
             x: BLSService::biguint_to_u256_array(BigUint::from(signature_point.x.c0)),
@@ -653,12 +798,14 @@ impl ExecutionLayer {
             self.contract_addresses.avs.preconf_registry,
             &self.provider_ws,
         );
-        let tx = preconf_registry.addValidators(params);
+        let nonce = self.get_pending_nonce().await?;
+        let tx = preconf_registry.addValidators(params).nonce(nonce);
 
         match tx.send().await {
             Ok(pending_tx) => {
                 let tx_hash = pending_tx.tx_hash();
                 tracing::info!("Add validator to preconfer successful: {:?}", tx_hash);
+                self.tx_tracker.track(TxKind::AddValidator, *tx_hash, nonce);
             }
             Err(err) => {
                 return Err(anyhow::anyhow!(err.to_avs_contract_error()));
@@ -684,14 +831,14 @@ impl ExecutionLayer {
         let message = data.abi_encode_packed();
 
         // Convert bls public key to G1Point
-        let pk_point = self.bls_service.get_public_key();
+        let pk_point = self.bls_signer.public_key_point()?;
         let pubkey = PreconfRegistry::G1Point {
             x: BLSService::biguint_to_u256_array(BigUint::from(pk_point.x)),
             y: BLSService::biguint_to_u256_array(BigUint::from(pk_point.y)),
         };
 
         // Sign message and convert to G2Point
-        let signature_point = self.bls_service.sign_as_point(&message, &vec![]);
+        let signature_point = self.bls_signer.sign_as_point(&message, &[]).await?;
 
         let signature = PreconfRegistry::G2Point {
             x: BLSService::biguint_to_u256_array(BigUint::from(signature_point.x.c0)),
@@ -711,12 +858,15 @@ impl ExecutionLayer {
             self.contract_addresses.avs.preconf_registry,
             &self.provider_ws,
         );
-        let tx = preconf_registry.removeValidators(params);
+        let nonce = self.get_pending_nonce().await?;
+        let tx = preconf_registry.removeValidators(params).nonce(nonce);
 
         match tx.send().await {
             Ok(pending_tx) => {
                 let tx_hash = pending_tx.tx_hash();
                 tracing::info!("Validator removed successfully: {:?}", tx_hash);
+                self.tx_tracker
+                    .track(TxKind::RemoveValidator, *tx_hash, nonce);
             }
             Err(err) => {
                 return Err(anyhow::anyhow!(err.to_avs_contract_error()));
@@ -728,39 +878,28 @@ impl ExecutionLayer {
 
     pub async fn subscribe_to_validator_added_event(
         &self,
-    ) -> Result<EventSubscription<PreconfRegistry::ValidatorAdded>, Error> {
-        let registry = PreconfRegistry::new(
+    ) -> Result<ResilientSubscription<PreconfRegistry::ValidatorAdded>, Error> {
+        ResilientSubscription::subscribe(
+            &self.ws_rpc_url,
             self.contract_addresses.avs.preconf_registry,
-            &self.provider_ws,
-        );
-
-        let validator_added_filter = registry.ValidatorAdded_filter().subscribe().await?;
-        tracing::debug!("Subscribed to ValidatorAdded event");
-
-        Ok(validator_added_filter)
+        )
+        .await
     }
 
     pub async fn wait_for_the_validator_added_event(
         &self,
-        validator_added_filter: EventSubscription<PreconfRegistry::ValidatorAdded>,
+        validator_added_filter: ResilientSubscription<PreconfRegistry::ValidatorAdded>,
     ) -> Result<(), Error> {
-        let mut stream = validator_added_filter.into_stream();
-        while let Some(log) = stream.next().await {
-            match log {
-                Ok(log) => {
-                    tracing::info!(
-                        "Received ValidatorAdded for:\npubkey hash: {}\npreconfer: {}",
-                        log.0.pubKeyHash,
-                        log.0.preconfer
-                    );
-                    if log.0.preconfer == self.preconfer_address {
-                        tracing::info!("Validator added!");
-                        break;
-                    }
-                }
-                Err(e) => {
-                    tracing::error!("Error receiving log: {}", e);
-                }
+        let mut validator_added_filter = validator_added_filter;
+        while let Some(event) = validator_added_filter.next().await {
+            tracing::info!(
+                "Received ValidatorAdded for:\npubkey hash: {}\npreconfer: {}",
+                event.pubKeyHash,
+                event.preconfer
+            );
+            if event.preconfer == self.preconfer_address {
+                tracing::info!("Validator added!");
+                break;
             }
         }
         Ok(())
@@ -769,13 +908,11 @@ impl ExecutionLayer {
     pub async fn subscribe_to_lookahead_updated_event(
         &self,
     ) -> Result<EventSubscriptionLookaheadUpdated, Error> {
-        let task_manager = PreconfTaskManager::new(
+        let lookahead_updated_filter = ResilientSubscription::subscribe(
+            &self.ws_rpc_url,
             self.contract_addresses.avs.preconf_task_manager,
-            &self.provider_ws,
-        );
-
-        let lookahead_updated_filter = task_manager.LookaheadUpdated_filter().subscribe().await?;
-        tracing::debug!("Subscribed to lookahead updated event");
+        )
+        .await?;
 
         Ok(EventSubscriptionLookaheadUpdated(lookahead_updated_filter))
     }
@@ -783,10 +920,9 @@ impl ExecutionLayer {
     pub async fn subscribe_to_block_proposed_event(
         &self,
     ) -> Result<EventSubscriptionBlockProposed, Error> {
-        let taiko_events = TaikoEvents::new(self.contract_addresses.taiko_l1, &self.provider_ws);
-
-        let block_proposed_filter = taiko_events.BlockProposed_filter().subscribe().await?;
-        tracing::debug!("Subscribed to block proposed event");
+        let block_proposed_filter =
+            ResilientSubscription::subscribe(&self.ws_rpc_url, self.contract_addresses.taiko_l1)
+                .await?;
 
         Ok(EventSubscriptionBlockProposed(block_proposed_filter))
     }
@@ -888,6 +1024,32 @@ impl ExecutionLayer {
             .collect::<Vec<PreconferAddress>>())
     }
 
+    /// Preconfer randomly selected by the contract for the slots of `epoch` without an entry in
+    /// the lookahead.
+    pub async fn get_fallback_preconfer(&self, epoch: u64) -> Result<PreconferAddress, Error> {
+        let epoch_begin_timestamp = self
+            .slot_clock
+            .get_real_epoch_begin_timestamp_for_contract(epoch)?;
+
+        let contract = PreconfTaskManager::new(
+            self.contract_addresses.avs.preconf_task_manager,
+            &self.provider_ws,
+        );
+
+        let fallback_preconfer = contract
+            .getFallbackPreconfer(U256::from(epoch_begin_timestamp))
+            .call()
+            .await?
+            ._0;
+
+        tracing::debug!(
+            "getFallbackPreconfer({}) result: {}",
+            epoch_begin_timestamp,
+            fallback_preconfer
+        );
+        Ok(fallback_preconfer.into_array())
+    }
+
     pub async fn get_lookahead_preconfer_buffer(
         &self,
     ) -> Result<[PreconfTaskManager::LookaheadBufferEntry; 64], Error> {
@@ -924,14 +1086,16 @@ impl ExecutionLayer {
         rpc_url: reqwest::Url,
         private_key: elliptic_curve::SecretKey<k256::Secp256k1>,
     ) -> Result<Self, Error> {
-        let signer = PrivateKeySigner::from_signing_key(private_key.into());
-        let wallet = EthereumWallet::from(signer.clone());
+        let ecdsa_signer: Arc<dyn EcdsaSigner> = Arc::new(signer::LocalEcdsaSigner::from_signer(
+            alloy::signers::local::PrivateKeySigner::from_signing_key(private_key.into()),
+        ));
+        let wallet = signer::create_wallet(ecdsa_signer.clone());
         let clock = SlotClock::new(0u64, 12u64, 12u64, 32u64, 3u64);
 
         let provider = ProviderBuilder::new().on_http(rpc_url.clone());
         let l1_chain_id = provider.get_chain_id().await?;
 
-        let bls_service = Arc::new(
+        let bls_signer = Arc::new(
             crate::bls::BLSService::new(
                 "0x14d50ac943d01069c206543a0bed3836f6062b35270607ebf1d1f238ceda26f1",
             )
@@ -947,29 +1111,30 @@ impl ExecutionLayer {
             .await
             .unwrap();
 
+        let preconfer_address: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2" // some random address for test
+            .parse()?;
+        let gas_oracle = GasOracle::new(GasOracleConfig::default())?;
+        let tx_tracker = TxTracker::new(
+            provider_ws.clone(),
+            wallet.clone(),
+            preconfer_address,
+            gas_oracle.clone(),
+            clock.get_slot_duration(),
+        );
+
         Ok(Self {
+            ws_rpc_url: ws_rpc_url.to_string(),
             provider_ws,
-            signer,
+            ecdsa_signer,
             wallet,
-            preconfer_address: "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2" // some random address for test
-                .parse()?,
+            preconfer_address,
             slot_clock: Arc::new(clock),
-            contract_addresses: ContractAddresses {
-                taiko_l1: Address::ZERO,
-                eigen_layer: EigenLayerContractAddresses {
-                    strategy_manager: Address::ZERO,
-                    slasher: Address::ZERO,
-                },
-                avs: AvsContractAddresses {
-                    preconf_task_manager: Address::ZERO,
-                    directory: Address::ZERO,
-                    service_manager: Address::ZERO,
-                    preconf_registry: Address::ZERO,
-                },
-            },
+            contract_addresses: ContractAddresses::default(),
             msg_expiry_sec: 120,
-            bls_service,
+            bls_signer,
             l1_chain_id,
+            gas_oracle,
+            tx_tracker,
         })
     }
 
@@ -1038,7 +1203,7 @@ mod tests {
             .await
             .unwrap();
 
-        el.propose_new_block(0, vec![0; 32], 0, vec![], true)
+        el.propose_new_block(1, 0, vec![0; 32], 0, vec![], true)
             .await
             .unwrap();
     }
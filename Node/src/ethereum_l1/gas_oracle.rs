@@ -0,0 +1,212 @@
+// Derives gas limits and EIP-1559 fees for the transactions sent by the node from the current
+// state of the chain, within the caps set by the operator.
+use super::ws_provider::WsProvider;
+use alloy::{eips::BlockNumberOrTag, providers::Provider};
+use anyhow::Error;
+
+const FEE_HISTORY_BLOCKS: u64 = 10;
+const PRIORITY_FEE_PERCENTILE: f64 = 50.0;
+const DEFAULT_PRIORITY_FEE_PER_GAS: u128 = 1_000_000_000;
+// Execution clients reject a replacement which doesn't raise both fees by at least 10%
+const MIN_FEE_BUMP_PERCENT: u64 = 10;
+const GWEI: u128 = 1_000_000_000;
+
+#[derive(Debug, Clone)]
+pub struct GasOracleConfig {
+    pub max_fee_per_gas_cap: u128,
+    pub max_priority_fee_per_gas_cap: u128,
+    pub gas_limit_margin_percent: u64,
+    pub fee_bump_percent: u64,
+}
+
+impl Default for GasOracleConfig {
+    fn default() -> Self {
+        Self {
+            max_fee_per_gas_cap: 200 * GWEI,
+            max_priority_fee_per_gas_cap: 10 * GWEI,
+            gas_limit_margin_percent: 20,
+            fee_bump_percent: 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Eip1559Fees {
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+#[derive(Clone)]
+pub struct GasOracle {
+    config: GasOracleConfig,
+}
+
+impl GasOracle {
+    pub fn new(config: GasOracleConfig) -> Result<Self, Error> {
+        if config.fee_bump_percent < MIN_FEE_BUMP_PERCENT {
+            return Err(anyhow::anyhow!(
+                "Fee bump must be at least {}%, {}% given",
+                MIN_FEE_BUMP_PERCENT,
+                config.fee_bump_percent
+            ));
+        }
+        Ok(Self { config })
+    }
+
+    /// Adds the configured safety margin to a gas estimate.
+    pub fn gas_limit_from_estimate(&self, estimate: u128) -> u128 {
+        estimate + estimate * self.config.gas_limit_margin_percent as u128 / 100
+    }
+
+    pub async fn get_fees(&self, provider: &WsProvider) -> Result<Eip1559Fees, Error> {
+        let fee_history = provider
+            .get_fee_history(
+                FEE_HISTORY_BLOCKS,
+                BlockNumberOrTag::Latest,
+                &[PRIORITY_FEE_PERCENTILE],
+            )
+            .await?;
+        let next_base_fee = fee_history
+            .next_block_base_fee()
+            .ok_or(anyhow::anyhow!("get_fees: fee history has no base fee"))?;
+        let rewards = fee_history
+            .reward
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|block_rewards| block_rewards.first().copied())
+            .collect();
+
+        Ok(self.fees_from_history(next_base_fee, rewards))
+    }
+
+    fn fees_from_history(&self, next_base_fee: u128, mut rewards: Vec<u128>) -> Eip1559Fees {
+        rewards.sort_unstable();
+        let priority_fee = rewards
+            .get(rewards.len() / 2)
+            .copied()
+            .unwrap_or(DEFAULT_PRIORITY_FEE_PER_GAS)
+            .min(self.config.max_priority_fee_per_gas_cap);
+
+        // Twice the base fee keeps the transaction valid through several full blocks
+        let max_fee = (2 * next_base_fee + priority_fee).min(self.config.max_fee_per_gas_cap);
+        if max_fee < next_base_fee + priority_fee {
+            tracing::warn!(
+                "Max fee per gas capped at {}, below the next block base fee {} plus tip",
+                max_fee,
+                next_base_fee
+            );
+        }
+
+        Eip1559Fees {
+            max_fee_per_gas: max_fee,
+            max_priority_fee_per_gas: priority_fee.min(max_fee),
+        }
+    }
+
+    /// Returns the fees for a replacement of a transaction sent with `previous` fees. Both fees
+    /// are bumped enough for the replacement to be accepted and follow the market if it moved
+    /// further.
+    pub fn replacement_fees(
+        &self,
+        previous: Eip1559Fees,
+        market: Eip1559Fees,
+    ) -> Result<Eip1559Fees, Error> {
+        let fees = Eip1559Fees {
+            max_fee_per_gas: self
+                .bump(previous.max_fee_per_gas)
+                .max(market.max_fee_per_gas),
+            max_priority_fee_per_gas: self
+                .bump(previous.max_priority_fee_per_gas)
+                .max(market.max_priority_fee_per_gas),
+        };
+
+        if fees.max_fee_per_gas > self.config.max_fee_per_gas_cap
+            || fees.max_priority_fee_per_gas > self.config.max_priority_fee_per_gas_cap
+        {
+            return Err(anyhow::anyhow!(
+                "replacement_fees: replacing the transaction would exceed the fee caps"
+            ));
+        }
+        Ok(fees)
+    }
+
+    fn bump(&self, fee: u128) -> u128 {
+        fee + (fee * self.config.fee_bump_percent as u128 / 100).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_gas_oracle() -> GasOracle {
+        GasOracle::new(GasOracleConfig::default()).unwrap()
+    }
+
+    #[test]
+    fn test_fees_from_history() {
+        let gas_oracle = create_gas_oracle();
+
+        let fees = gas_oracle.fees_from_history(10 * GWEI, vec![3 * GWEI, GWEI, 2 * GWEI]);
+        assert_eq!(
+            fees,
+            Eip1559Fees {
+                max_fee_per_gas: 22 * GWEI,
+                max_priority_fee_per_gas: 2 * GWEI,
+            }
+        );
+
+        let fees = gas_oracle.fees_from_history(150 * GWEI, vec![]);
+        assert_eq!(
+            fees,
+            Eip1559Fees {
+                max_fee_per_gas: 200 * GWEI,
+                max_priority_fee_per_gas: DEFAULT_PRIORITY_FEE_PER_GAS,
+            }
+        );
+
+        let fees = gas_oracle.fees_from_history(GWEI, vec![50 * GWEI]);
+        assert_eq!(fees.max_priority_fee_per_gas, 10 * GWEI);
+    }
+
+    #[test]
+    fn test_replacement_fees() {
+        let gas_oracle = create_gas_oracle();
+        let previous = Eip1559Fees {
+            max_fee_per_gas: 20 * GWEI,
+            max_priority_fee_per_gas: 2 * GWEI,
+        };
+
+        let fees = gas_oracle.replacement_fees(previous, previous).unwrap();
+        assert_eq!(fees.max_fee_per_gas, 23 * GWEI);
+        assert_eq!(fees.max_priority_fee_per_gas, 2_300_000_000);
+
+        let market = Eip1559Fees {
+            max_fee_per_gas: 40 * GWEI,
+            max_priority_fee_per_gas: GWEI,
+        };
+        let fees = gas_oracle.replacement_fees(previous, market).unwrap();
+        assert_eq!(fees.max_fee_per_gas, 40 * GWEI);
+        assert_eq!(fees.max_priority_fee_per_gas, 2_300_000_000);
+
+        let at_cap = Eip1559Fees {
+            max_fee_per_gas: 190 * GWEI,
+            max_priority_fee_per_gas: 2 * GWEI,
+        };
+        assert!(gas_oracle.replacement_fees(at_cap, at_cap).is_err());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = GasOracleConfig {
+            fee_bump_percent: 5,
+            ..Default::default()
+        };
+        assert!(GasOracle::new(config).is_err());
+
+        assert_eq!(
+            create_gas_oracle().gas_limit_from_estimate(100_000),
+            120_000
+        );
+    }
+}
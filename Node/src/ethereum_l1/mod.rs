@@ -3,11 +3,17 @@ pub mod block_proposed;
 pub mod consensus_layer;
 mod el_with_cl_tests;
 pub mod execution_layer;
+pub mod gas_oracle;
 pub mod merkle_proofs;
+pub mod resilient_subscription;
 pub mod slot_clock;
+pub mod tx_tracker;
 mod ws_provider;
 
-use crate::{bls::BLSService, utils::config::ContractAddresses};
+use crate::{
+    signer::{BlsSigner, EcdsaSigner},
+    utils::config::ContractAddresses,
+};
 use anyhow::Error;
 use consensus_layer::ConsensusLayer;
 #[cfg(not(test))]
@@ -15,6 +21,7 @@ use execution_layer::ExecutionLayer;
 #[cfg(test)]
 #[cfg_attr(feature = "use_mock", double)]
 use execution_layer::ExecutionLayer;
+use gas_oracle::GasOracleConfig;
 #[cfg(test)]
 #[cfg(feature = "use_mock")]
 use mockall_double::double;
@@ -30,15 +37,16 @@ pub struct EthereumL1 {
 impl EthereumL1 {
     pub async fn new(
         execution_ws_rpc_url: &str,
-        avs_node_ecdsa_private_key: &str,
+        ecdsa_signer: Arc<dyn EcdsaSigner>,
         contract_addresses: &ContractAddresses,
         consensus_rpc_url: &str,
         slot_duration_sec: u64,
         slots_per_epoch: u64,
         msg_expiry_sec: u64,
-        bls_service: Arc<BLSService>,
+        bls_signer: Arc<dyn BlsSigner>,
         l1_chain_id: u64,
         l2_slot_duration_sec: u64,
+        gas_oracle_config: GasOracleConfig,
     ) -> Result<Self, Error> {
         let consensus_layer = ConsensusLayer::new(consensus_rpc_url)?;
         let genesis_details = consensus_layer.get_genesis_details().await?;
@@ -52,12 +60,13 @@ impl EthereumL1 {
 
         let execution_layer = ExecutionLayer::new(
             execution_ws_rpc_url,
-            avs_node_ecdsa_private_key,
+            ecdsa_signer,
             contract_addresses,
             slot_clock.clone(),
             msg_expiry_sec,
-            bls_service,
+            bls_signer,
             l1_chain_id,
+            gas_oracle_config,
         )
         .await?;
 
@@ -0,0 +1,255 @@
+// Keeps an L1 event subscription alive across websocket disconnects. Every time the connection
+// is re-established the logs emitted while it was down are fetched with eth_getLogs, so the
+// consumer receives each event once and in chain order.
+use alloy::{
+    primitives::Address,
+    providers::{Provider, ProviderBuilder, RootProvider, WsConnect},
+    pubsub::{PubSubFrontend, Subscription},
+    rpc::types::{Filter, Log},
+    sol_types::SolEvent,
+};
+use anyhow::Error;
+use std::{marker::PhantomData, time::Duration};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tracing::{debug, error, info, warn};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const EVENTS_CHANNEL_CAPACITY: usize = 100;
+
+pub struct ResilientSubscription<E> {
+    events_rx: mpsc::Receiver<E>,
+}
+
+impl<E: SolEvent + Send + 'static> ResilientSubscription<E> {
+    /// Subscribes to `E` emitted by the contract at `address`. The first connection is made
+    /// before returning, so an unreachable endpoint is reported to the caller.
+    pub async fn subscribe(ws_rpc_url: &str, address: Address) -> Result<Self, Error> {
+        let (events_tx, events_rx) = mpsc::channel(EVENTS_CHANNEL_CAPACITY);
+        let mut worker = SubscriptionWorker::<E> {
+            ws_rpc_url: ws_rpc_url.to_string(),
+            filter: Filter::new()
+                .address(address)
+                .event_signature(E::SIGNATURE_HASH),
+            cursor: LogCursor::default(),
+            events_tx,
+            _event: PhantomData,
+        };
+        let connection = worker.connect().await?;
+        debug!("Subscribed to {} event", E::SIGNATURE);
+        tokio::spawn(worker.run(connection));
+
+        Ok(Self { events_rx })
+    }
+
+    /// Waits for the next event. Reconnections happen in the background, so this only
+    /// returns `None` if the subscription task has stopped.
+    pub async fn next(&mut self) -> Option<E> {
+        self.events_rx.recv().await
+    }
+}
+
+struct Connection {
+    // the subscription is only served as long as its provider is alive
+    _provider: RootProvider<PubSubFrontend>,
+    subscription: Subscription<Log>,
+}
+
+struct SubscriptionWorker<E> {
+    ws_rpc_url: String,
+    filter: Filter,
+    cursor: LogCursor,
+    events_tx: mpsc::Sender<E>,
+    _event: PhantomData<E>,
+}
+
+impl<E: SolEvent + Send + 'static> SubscriptionWorker<E> {
+    async fn run(mut self, mut connection: Connection) {
+        loop {
+            self.forward(&mut connection.subscription).await;
+            if self.events_tx.is_closed() {
+                debug!("{} subscription dropped by the receiver", E::SIGNATURE);
+                return;
+            }
+
+            warn!("{} subscription lost, reconnecting", E::SIGNATURE);
+            let mut backoff = INITIAL_BACKOFF;
+            connection = loop {
+                tokio::time::sleep(backoff).await;
+                if self.events_tx.is_closed() {
+                    return;
+                }
+                match self.connect().await {
+                    Ok(connection) => break connection,
+                    Err(e) => {
+                        error!(
+                            "Failed to resubscribe to {}: {}, retrying in {:?}",
+                            E::SIGNATURE,
+                            e,
+                            backoff
+                        );
+                        backoff = (backoff * 2).min(MAX_BACKOFF);
+                    }
+                }
+            };
+            info!("{} subscription restored", E::SIGNATURE);
+        }
+    }
+
+    // The log subscription is created before the head is read, so everything up to the head
+    // is covered by the backfill and everything after it by the subscription.
+    async fn connect(&mut self) -> Result<Connection, Error> {
+        let provider = ProviderBuilder::new()
+            .on_ws(WsConnect::new(self.ws_rpc_url.clone()))
+            .await?;
+        let subscription = provider.subscribe_logs(&self.filter).await?;
+        let head = provider.get_block_number().await?;
+
+        if let Some(from_block) = self.cursor.backfill_from() {
+            if from_block <= head {
+                let logs = provider
+                    .get_logs(&self.filter.clone().from_block(from_block).to_block(head))
+                    .await?;
+                debug!(
+                    "Backfilled {} {} logs from blocks {}..={}",
+                    logs.len(),
+                    E::SIGNATURE,
+                    from_block,
+                    head
+                );
+                for log in logs {
+                    self.deliver(log).await;
+                }
+            }
+        }
+        self.cursor.mark_synced(head);
+
+        Ok(Connection {
+            _provider: provider,
+            subscription,
+        })
+    }
+
+    async fn forward(&mut self, subscription: &mut Subscription<Log>) {
+        loop {
+            match subscription.recv().await {
+                Ok(log) => self.deliver(log).await,
+                Err(RecvError::Lagged(skipped)) => {
+                    // the skipped logs are recovered by the backfill after reconnecting
+                    warn!("{} subscription lagged by {} logs", E::SIGNATURE, skipped);
+                    return;
+                }
+                Err(RecvError::Closed) => return,
+            }
+            if self.events_tx.is_closed() {
+                return;
+            }
+        }
+    }
+
+    async fn deliver(&mut self, log: Log) {
+        let (Some(block_number), Some(log_index)) = (log.block_number, log.log_index) else {
+            warn!("Ignoring pending {} log", E::SIGNATURE);
+            return;
+        };
+        if log.removed {
+            warn!(
+                "{} log in block {} was removed by a reorg",
+                E::SIGNATURE,
+                block_number
+            );
+            self.cursor.rewind_to(block_number);
+            return;
+        }
+        if !self.cursor.advance((block_number, log_index)) {
+            return;
+        }
+
+        match log.log_decode::<E>() {
+            Ok(decoded) => {
+                if let Err(e) = self.events_tx.send(decoded.inner.data).await {
+                    debug!("Failed to forward {} event: {}", E::SIGNATURE, e);
+                }
+            }
+            Err(e) => error!("Failed to decode {} log: {}", E::SIGNATURE, e),
+        }
+    }
+}
+
+type LogPosition = (u64, u64); // (block number, log index)
+
+// Tracks how far the log stream has been consumed, so a reconnect knows where to backfill
+// from and logs seen twice (backfill and subscription overlap) are delivered only once.
+#[derive(Default)]
+struct LogCursor {
+    last_delivered: Option<LogPosition>,
+    synced_block: Option<u64>,
+}
+
+impl LogCursor {
+    // The synced block itself is fetched again, its duplicates are filtered by `advance`.
+    fn backfill_from(&self) -> Option<u64> {
+        self.synced_block
+    }
+
+    fn mark_synced(&mut self, block: u64) {
+        self.synced_block = Some(self.synced_block.map_or(block, |synced| synced.max(block)));
+    }
+
+    /// Returns false if the log at `position` was already delivered.
+    fn advance(&mut self, position: LogPosition) -> bool {
+        if self.last_delivered.is_some_and(|last| position <= last) {
+            return false;
+        }
+        self.last_delivered = Some(position);
+        self.mark_synced(position.0);
+        true
+    }
+
+    // Logs from `block` onwards may be re-emitted on the new canonical chain.
+    fn rewind_to(&mut self, block: u64) {
+        let before = block.saturating_sub(1);
+        if self
+            .last_delivered
+            .is_some_and(|(last_block, _)| last_block >= block)
+        {
+            self.last_delivered = Some((before, u64::MAX));
+        }
+        if self.synced_block.is_some_and(|synced| synced >= block) {
+            self.synced_block = Some(before);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_skips_already_delivered_logs() {
+        let mut cursor = LogCursor::default();
+        assert_eq!(cursor.backfill_from(), None);
+
+        cursor.mark_synced(100);
+        assert!(cursor.advance((101, 0)));
+        assert!(cursor.advance((101, 3)));
+        assert!(cursor.advance((102, 1)));
+        // the backfill overlaps with logs received through the subscription
+        assert_eq!(cursor.backfill_from(), Some(102));
+        assert!(!cursor.advance((101, 3)));
+        assert!(!cursor.advance((102, 1)));
+        assert!(cursor.advance((102, 2)));
+    }
+
+    #[test]
+    fn test_cursor_rewinds_on_reorg() {
+        let mut cursor = LogCursor::default();
+        cursor.mark_synced(10);
+        assert!(cursor.advance((11, 0)));
+        assert!(cursor.advance((12, 0)));
+
+        cursor.rewind_to(12);
+        assert_eq!(cursor.backfill_from(), Some(11));
+        assert!(cursor.advance((12, 0)));
+    }
+}
@@ -42,6 +42,10 @@ impl SlotClock {
         self.slots_per_epoch
     }
 
+    pub fn get_slot_duration(&self) -> Duration {
+        self.slot_duration
+    }
+
     pub fn duration_to_next_slot(&self) -> Result<Duration, Error> {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
         self.duration_to_next_slot_from(now)
@@ -66,11 +70,6 @@ impl SlotClock {
         }
     }
 
-    pub fn duration_to_slot_from_now(&self, slot: Slot) -> Result<Duration, Error> {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
-        self.duration_to_slot(slot, now)
-    }
-
     pub fn slot_of(&self, now: Duration) -> Result<Slot, Error> {
         let genesis: Duration = self.genesis_duration;
 
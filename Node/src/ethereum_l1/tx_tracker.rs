@@ -0,0 +1,756 @@
+// Follows every transaction sent by the node until it is finalized, reverted or given up on,
+// and publishes the outcomes so the node can react to failed calls.
+use super::{
+    avs_contract_error::AVSContractError,
+    gas_oracle::{Eip1559Fees, GasOracle},
+    ws_provider::WsProvider,
+};
+use crate::metrics;
+use alloy::{
+    consensus::TxEip1559,
+    eips::{BlockId, BlockNumberOrTag},
+    network::EthereumWallet,
+    primitives::{Address, B256},
+    providers::Provider,
+    rpc::types::TransactionRequest,
+};
+use anyhow::Error;
+use async_trait::async_trait;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast;
+use tracing::{debug, error, info, warn};
+
+const OUTCOMES_CHANNEL_CAPACITY: usize = 100;
+// Number of poll intervals a transaction may stay pending before it is reported as timed out
+const PENDING_TIMEOUT_POLLS: u32 = 32;
+// Number of poll intervals before a transaction unknown to the L1 node is taken as evicted, it
+// may not be visible right after it is sent
+const EVICTION_POLLS: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxKind {
+    ProposeBlock { block_id: u64, tx_list: Vec<u8> },
+    ForcePushLookahead,
+    RegisterPreconfer,
+    AddValidator,
+    RemoveValidator,
+    ProveIncorrectLookahead,
+    ProveIncorrectPreconfirmation { block_id: u64 },
+}
+
+impl TxKind {
+    /// Name of the kind in the metrics.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TxKind::ProposeBlock { .. } => "propose_block",
+            TxKind::ForcePushLookahead => "force_push_lookahead",
+            TxKind::RegisterPreconfer => "register_preconfer",
+            TxKind::AddValidator => "add_validator",
+            TxKind::RemoveValidator => "remove_validator",
+            TxKind::ProveIncorrectLookahead => "prove_incorrect_lookahead",
+            TxKind::ProveIncorrectPreconfirmation { .. } => "prove_incorrect_preconfirmation",
+        }
+    }
+}
+
+impl fmt::Display for TxKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TxKind::ProposeBlock { block_id, .. } => write!(f, "ProposeBlock({})", block_id),
+            TxKind::ForcePushLookahead => write!(f, "ForcePushLookahead"),
+            TxKind::RegisterPreconfer => write!(f, "RegisterPreconfer"),
+            TxKind::AddValidator => write!(f, "AddValidator"),
+            TxKind::RemoveValidator => write!(f, "RemoveValidator"),
+            TxKind::ProveIncorrectLookahead => write!(f, "ProveIncorrectLookahead"),
+            TxKind::ProveIncorrectPreconfirmation { block_id } => {
+                write!(f, "ProveIncorrectPreconfirmation({})", block_id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxStatus {
+    Included {
+        block_number: u64,
+    },
+    Finalized {
+        block_number: u64,
+    },
+    Reverted {
+        block_number: u64,
+        reason: String,
+    },
+    /// The nonce was used by another transaction, or the L1 node evicted the transaction and
+    /// its nonce is still free.
+    Dropped,
+    /// Still not included after the pending timeout, the signed transaction may still land.
+    TimedOut,
+}
+
+impl TxStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            TxStatus::Included { .. } => "included",
+            TxStatus::Finalized { .. } => "finalized",
+            TxStatus::Reverted { .. } => "reverted",
+            TxStatus::Dropped => "dropped",
+            TxStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TxOutcome {
+    pub kind: TxKind,
+    pub tx_hash: B256,
+    pub nonce: u64,
+    pub status: TxStatus,
+}
+
+/// Re-sends a pending transaction with bumped fees until `deadline`.
+pub struct Replacement {
+    pub tx: TxEip1559,
+    pub fees: Eip1559Fees,
+    pub deadline: Duration,
+}
+
+/// Where a transaction was included.
+#[derive(Debug, Clone, PartialEq)]
+struct Inclusion {
+    tx_hash: B256,
+    block_number: u64,
+    block_hash: B256,
+    success: bool,
+}
+
+// The L1 calls made while following a transaction
+#[async_trait]
+trait L1Client: Send + Sync {
+    /// Returns the inclusion of the first of `tx_hashes` which has a receipt.
+    async fn find_inclusion(&self, tx_hashes: &[B256]) -> Result<Option<Inclusion>, Error>;
+    /// Whether the L1 node still knows any of `tx_hashes`.
+    async fn is_known(&self, tx_hashes: &[B256]) -> Result<bool, Error>;
+    async fn get_account_nonce(&self) -> Result<u64, Error>;
+    async fn get_finalized_block_number(&self) -> Result<u64, Error>;
+    /// Sends the transaction again with bumped fees, returns the new hash.
+    async fn replace(&self, replacement: &mut Replacement) -> Result<B256, Error>;
+    async fn get_revert_reason(&self, tx_hash: B256, block_number: u64) -> String;
+}
+
+struct ProviderClient {
+    provider_ws: WsProvider,
+    wallet: EthereumWallet,
+    sender: Address,
+    gas_oracle: GasOracle,
+}
+
+#[derive(Clone)]
+pub struct TxTracker {
+    client: Arc<dyn L1Client>,
+    poll_interval: Duration,
+    outcomes_tx: broadcast::Sender<TxOutcome>,
+}
+
+// What to do with a transaction which has no receipt yet
+#[derive(Debug, PartialEq)]
+enum PendingAction {
+    Wait,
+    Replace,
+    /// The account nonce moved past the transaction's one.
+    NonceUsed,
+    /// The L1 node doesn't know the transaction anymore.
+    Evicted,
+    TimedOut,
+}
+
+impl PendingAction {
+    fn decide(
+        nonce: u64,
+        account_nonce: u64,
+        known: bool,
+        pending_polls: u32,
+        replacement_deadline: Option<Duration>,
+        now: Duration,
+        poll_interval: Duration,
+    ) -> Self {
+        if account_nonce > nonce {
+            return PendingAction::NonceUsed;
+        }
+        if !known && pending_polls >= EVICTION_POLLS {
+            return PendingAction::Evicted;
+        }
+        if pending_polls >= PENDING_TIMEOUT_POLLS {
+            return PendingAction::TimedOut;
+        }
+        // a replacement is only worth it if it can be included before the deadline
+        match replacement_deadline {
+            Some(deadline) if now + poll_interval <= deadline => PendingAction::Replace,
+            _ => PendingAction::Wait,
+        }
+    }
+}
+
+struct TrackedTx {
+    kind: TxKind,
+    nonce: u64,
+    // every hash sent for the nonce, any of them may get included
+    tx_hashes: Vec<B256>,
+    replacement: Option<Replacement>,
+}
+
+impl TxTracker {
+    pub fn new(
+        provider_ws: WsProvider,
+        wallet: EthereumWallet,
+        sender: Address,
+        gas_oracle: GasOracle,
+        poll_interval: Duration,
+    ) -> Self {
+        Self::with_client(
+            Arc::new(ProviderClient {
+                provider_ws,
+                wallet,
+                sender,
+                gas_oracle,
+            }),
+            poll_interval,
+        )
+    }
+
+    fn with_client(client: Arc<dyn L1Client>, poll_interval: Duration) -> Self {
+        let (outcomes_tx, _) = broadcast::channel(OUTCOMES_CHANNEL_CAPACITY);
+        Self {
+            client,
+            poll_interval,
+            outcomes_tx,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TxOutcome> {
+        self.outcomes_tx.subscribe()
+    }
+
+    pub fn track(&self, kind: TxKind, tx_hash: B256, nonce: u64) {
+        self.spawn(TrackedTx {
+            kind,
+            nonce,
+            tx_hashes: vec![tx_hash],
+            replacement: None,
+        });
+    }
+
+    pub fn track_with_replacement(&self, kind: TxKind, tx_hash: B256, replacement: Replacement) {
+        self.spawn(TrackedTx {
+            kind,
+            nonce: replacement.tx.nonce,
+            tx_hashes: vec![tx_hash],
+            replacement: Some(replacement),
+        });
+    }
+
+    fn spawn(&self, tracked: TrackedTx) {
+        debug!(
+            "Tracking {} transaction {}",
+            tracked.kind, tracked.tx_hashes[0]
+        );
+        metrics::L1_TXS_SENT
+            .with_label_values(&[tracked.kind.label()])
+            .inc();
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            tracker.follow(tracked).await;
+        });
+    }
+
+    async fn follow(&self, mut tracked: TrackedTx) {
+        // (block number, block hash) of the block the transaction was last seen in
+        let mut included: Option<(u64, B256)> = None;
+        let mut pending_polls = 0;
+
+        loop {
+            tokio::time::sleep(self.poll_interval).await;
+
+            let inclusion = match self.client.find_inclusion(&tracked.tx_hashes).await {
+                Ok(inclusion) => inclusion,
+                Err(e) => {
+                    warn!("Failed to get receipt for {}: {}", tracked.kind, e);
+                    continue;
+                }
+            };
+
+            let Some(Inclusion {
+                tx_hash,
+                block_number,
+                block_hash,
+                success,
+            }) = inclusion
+            else {
+                if let Some((block_number, _)) = included.take() {
+                    warn!(
+                        "{} transaction removed from block {} by a reorg, pending again",
+                        tracked.kind, block_number
+                    );
+                }
+                pending_polls += 1;
+                match self.check_pending(&mut tracked, pending_polls).await {
+                    Ok(Some(status)) => {
+                        self.publish(&tracked, *tracked.tx_hashes.last().unwrap(), status);
+                        return;
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to check pending {}: {}", tracked.kind, e),
+                }
+                continue;
+            };
+
+            if included != Some((block_number, block_hash)) {
+                if included.is_some() {
+                    warn!(
+                        "{} transaction {} moved to block {} by a reorg",
+                        tracked.kind, tx_hash, block_number
+                    );
+                }
+                included = Some((block_number, block_hash));
+
+                if !success {
+                    let reason = self.client.get_revert_reason(tx_hash, block_number).await;
+                    self.publish(
+                        &tracked,
+                        tx_hash,
+                        TxStatus::Reverted {
+                            block_number,
+                            reason,
+                        },
+                    );
+                    return;
+                }
+                self.publish(&tracked, tx_hash, TxStatus::Included { block_number });
+            }
+
+            match self.client.get_finalized_block_number().await {
+                Ok(finalized) if finalized >= block_number => {
+                    self.publish(&tracked, tx_hash, TxStatus::Finalized { block_number });
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to get the finalized block: {}", e),
+            }
+        }
+    }
+
+    // Returns the terminal status of a transaction that is not included yet, if any.
+    async fn check_pending(
+        &self,
+        tracked: &mut TrackedTx,
+        pending_polls: u32,
+    ) -> Result<Option<TxStatus>, Error> {
+        let action = PendingAction::decide(
+            tracked.nonce,
+            self.client.get_account_nonce().await?,
+            self.client.is_known(&tracked.tx_hashes).await?,
+            pending_polls,
+            tracked
+                .replacement
+                .as_ref()
+                .map(|replacement| replacement.deadline),
+            SystemTime::now().duration_since(UNIX_EPOCH)?,
+            self.poll_interval,
+        );
+        match action {
+            PendingAction::Wait => {}
+            PendingAction::NonceUsed => {
+                // Our nonce is used but none of our hashes has a receipt. Look once more in
+                // case the transaction got included between the two calls.
+                if self
+                    .client
+                    .find_inclusion(&tracked.tx_hashes)
+                    .await?
+                    .is_none()
+                {
+                    return Ok(Some(TxStatus::Dropped));
+                }
+            }
+            PendingAction::Evicted => return Ok(Some(TxStatus::Dropped)),
+            PendingAction::TimedOut => return Ok(Some(TxStatus::TimedOut)),
+            PendingAction::Replace => {
+                let Some(replacement) = tracked.replacement.as_mut() else {
+                    return Ok(None);
+                };
+                let tx_hash = self.client.replace(replacement).await?;
+                info!(
+                    "Replaced {} transaction with {}, max fee per gas: {}, max priority fee per gas: {}",
+                    tracked.kind,
+                    tx_hash,
+                    replacement.fees.max_fee_per_gas,
+                    replacement.fees.max_priority_fee_per_gas
+                );
+                tracked.tx_hashes.push(tx_hash);
+            }
+        }
+        Ok(None)
+    }
+
+    fn publish(&self, tracked: &TrackedTx, tx_hash: B256, status: TxStatus) {
+        let kind = &tracked.kind;
+        match &status {
+            TxStatus::Included { .. } | TxStatus::Finalized { .. } => {
+                debug!("{} transaction {}: {:?}", kind, tx_hash, status)
+            }
+            _ => error!("{} transaction {} failed: {:?}", kind, tx_hash, status),
+        }
+        metrics::L1_TX_OUTCOMES
+            .with_label_values(&[kind.label(), status.label()])
+            .inc();
+        // nobody listening is fine, e.g. in the registration mode
+        let _ = self.outcomes_tx.send(TxOutcome {
+            kind: kind.clone(),
+            tx_hash,
+            nonce: tracked.nonce,
+            status,
+        });
+    }
+}
+
+#[async_trait]
+impl L1Client for ProviderClient {
+    async fn find_inclusion(&self, tx_hashes: &[B256]) -> Result<Option<Inclusion>, Error> {
+        for tx_hash in tx_hashes {
+            let Some(receipt) = self.provider_ws.get_transaction_receipt(*tx_hash).await? else {
+                continue;
+            };
+            if let (Some(block_number), Some(block_hash)) =
+                (receipt.block_number, receipt.block_hash)
+            {
+                return Ok(Some(Inclusion {
+                    tx_hash: receipt.transaction_hash,
+                    block_number,
+                    block_hash,
+                    success: receipt.status(),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn is_known(&self, tx_hashes: &[B256]) -> Result<bool, Error> {
+        for tx_hash in tx_hashes {
+            if self
+                .provider_ws
+                .get_transaction_by_hash(*tx_hash)
+                .await?
+                .is_some()
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn get_account_nonce(&self) -> Result<u64, Error> {
+        Ok(self.provider_ws.get_transaction_count(self.sender).await?)
+    }
+
+    async fn get_finalized_block_number(&self) -> Result<u64, Error> {
+        self.provider_ws
+            .get_block_by_number(BlockNumberOrTag::Finalized, false)
+            .await?
+            .and_then(|block| block.header.number)
+            .ok_or(anyhow::anyhow!(
+                "get_finalized_block_number: no finalized block"
+            ))
+    }
+
+    async fn replace(&self, replacement: &mut Replacement) -> Result<B256, Error> {
+        let market_fees = self.gas_oracle.get_fees(&self.provider_ws).await?;
+        replacement.fees = self
+            .gas_oracle
+            .replacement_fees(replacement.fees, market_fees)?;
+        replacement.tx.max_fee_per_gas = replacement.fees.max_fee_per_gas;
+        replacement.tx.max_priority_fee_per_gas = replacement.fees.max_priority_fee_per_gas;
+
+        let buf = sign_and_encode(&self.wallet, &mut replacement.tx).await?;
+        let pending = self.provider_ws.send_raw_transaction(&buf).await?;
+        Ok(*pending.tx_hash())
+    }
+
+    // Replays the transaction on top of the parent block to recover the revert data.
+    async fn get_revert_reason(&self, tx_hash: B256, block_number: u64) -> String {
+        let tx = match self.provider_ws.get_transaction_by_hash(tx_hash).await {
+            Ok(Some(tx)) => tx,
+            Ok(None) => return "unknown, transaction not found".to_string(),
+            Err(e) => return format!("unknown, failed to get transaction: {}", e),
+        };
+        let request: TransactionRequest = tx.into();
+        match self
+            .provider_ws
+            .call(&request)
+            .block(BlockId::number(block_number.saturating_sub(1)))
+            .await
+        {
+            Ok(_) => "unknown, the replayed call succeeds".to_string(),
+            Err(e) => alloy::contract::Error::TransportError(e).to_avs_contract_error(),
+        }
+    }
+}
+
+pub async fn sign_and_encode(
+    wallet: &EthereumWallet,
+    tx: &mut TxEip1559,
+) -> Result<Vec<u8>, Error> {
+    let signature = wallet.default_signer().sign_transaction(tx).await?;
+    let mut buf = vec![];
+    tx.encode_with_signature(&signature, &mut buf, false);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::VecDeque, sync::Mutex};
+
+    const POLL_INTERVAL: Duration = Duration::from_secs(12);
+    const NOW: Duration = Duration::from_secs(1000);
+
+    fn decide(nonce: u64, account_nonce: u64, deadline: Option<u64>) -> PendingAction {
+        PendingAction::decide(
+            nonce,
+            account_nonce,
+            true,
+            1,
+            deadline.map(Duration::from_secs),
+            NOW,
+            POLL_INTERVAL,
+        )
+    }
+
+    #[test]
+    fn test_nonce_used_by_another_tx() {
+        assert_eq!(decide(5, 6, Some(2000)), PendingAction::NonceUsed);
+        assert_eq!(decide(5, 5, None), PendingAction::Wait);
+    }
+
+    #[test]
+    fn test_replace_until_deadline() {
+        assert_eq!(decide(5, 5, Some(1012)), PendingAction::Replace);
+        // a replacement would not be included before the deadline
+        assert_eq!(decide(5, 5, Some(1011)), PendingAction::Wait);
+    }
+
+    #[test]
+    fn test_timed_out() {
+        let action = PendingAction::decide(
+            5,
+            5,
+            true,
+            PENDING_TIMEOUT_POLLS,
+            Some(Duration::from_secs(2000)),
+            NOW,
+            POLL_INTERVAL,
+        );
+        assert_eq!(action, PendingAction::TimedOut);
+        let action =
+            PendingAction::decide(5, 6, true, PENDING_TIMEOUT_POLLS, None, NOW, POLL_INTERVAL);
+        assert_eq!(action, PendingAction::NonceUsed);
+    }
+
+    #[test]
+    fn test_evicted() {
+        let decide = |known, pending_polls| {
+            PendingAction::decide(5, 5, known, pending_polls, None, NOW, POLL_INTERVAL)
+        };
+        // a transaction just sent may not be visible yet
+        assert_eq!(decide(false, 1), PendingAction::Wait);
+        assert_eq!(decide(false, EVICTION_POLLS), PendingAction::Evicted);
+        assert_eq!(decide(true, EVICTION_POLLS), PendingAction::Wait);
+    }
+
+    #[test]
+    fn test_labels() {
+        let kind = TxKind::ProposeBlock {
+            block_id: 7,
+            tx_list: vec![1],
+        };
+        assert_eq!(kind.label(), "propose_block");
+        assert_eq!(kind.to_string(), "ProposeBlock(7)");
+        assert_eq!(TxStatus::Dropped.label(), "dropped");
+        assert_eq!(
+            TxStatus::Reverted {
+                block_number: 1,
+                reason: String::new()
+            }
+            .label(),
+            "reverted"
+        );
+    }
+
+    // Answers each call with the next scripted value, the last one is repeated
+    struct Script<T>(VecDeque<T>);
+
+    impl<T: Clone> Script<T> {
+        fn new(values: Vec<T>) -> Self {
+            Self(values.into())
+        }
+
+        fn next(&mut self) -> T {
+            if self.0.len() > 1 {
+                self.0.pop_front().unwrap()
+            } else {
+                self.0[0].clone()
+            }
+        }
+    }
+
+    struct FakeL1 {
+        inclusions: Mutex<Script<Option<Inclusion>>>,
+        account_nonces: Mutex<Script<u64>>,
+        finalized_blocks: Mutex<Script<u64>>,
+        replacement_hash: B256,
+        known: bool,
+    }
+
+    impl FakeL1 {
+        fn new(
+            inclusions: Vec<Option<Inclusion>>,
+            account_nonce: u64,
+            finalized: Vec<u64>,
+        ) -> Self {
+            Self {
+                inclusions: Mutex::new(Script::new(inclusions)),
+                account_nonces: Mutex::new(Script::new(vec![account_nonce])),
+                finalized_blocks: Mutex::new(Script::new(finalized)),
+                replacement_hash: B256::repeat_byte(2),
+                known: true,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl L1Client for FakeL1 {
+        async fn find_inclusion(&self, _tx_hashes: &[B256]) -> Result<Option<Inclusion>, Error> {
+            Ok(self.inclusions.lock().unwrap().next())
+        }
+
+        async fn is_known(&self, _tx_hashes: &[B256]) -> Result<bool, Error> {
+            Ok(self.known)
+        }
+
+        async fn get_account_nonce(&self) -> Result<u64, Error> {
+            Ok(self.account_nonces.lock().unwrap().next())
+        }
+
+        async fn get_finalized_block_number(&self) -> Result<u64, Error> {
+            Ok(self.finalized_blocks.lock().unwrap().next())
+        }
+
+        async fn replace(&self, replacement: &mut Replacement) -> Result<B256, Error> {
+            replacement.fees.max_fee_per_gas *= 2;
+            Ok(self.replacement_hash)
+        }
+
+        async fn get_revert_reason(&self, _tx_hash: B256, _block_number: u64) -> String {
+            "reverted".to_string()
+        }
+    }
+
+    fn included(tx_hash: B256, block_number: u64, block_hash: u8) -> Option<Inclusion> {
+        Some(Inclusion {
+            tx_hash,
+            block_number,
+            block_hash: B256::repeat_byte(block_hash),
+            success: true,
+        })
+    }
+
+    // Follows the transaction until its terminal status and returns every published status
+    async fn follow(fake: FakeL1, replacement: Option<Replacement>) -> Vec<(B256, TxStatus)> {
+        let tracker = TxTracker::with_client(Arc::new(fake), Duration::from_millis(1));
+        let mut outcomes_rx = tracker.subscribe();
+        tracker
+            .follow(TrackedTx {
+                kind: TxKind::ForcePushLookahead,
+                nonce: 5,
+                tx_hashes: vec![B256::repeat_byte(1)],
+                replacement,
+            })
+            .await;
+        let mut statuses = vec![];
+        while let Ok(outcome) = outcomes_rx.try_recv() {
+            statuses.push((outcome.tx_hash, outcome.status));
+        }
+        statuses
+    }
+
+    #[tokio::test]
+    async fn test_follow_through_a_reorg() {
+        let tx_hash = B256::repeat_byte(1);
+        let fake = FakeL1::new(
+            vec![
+                included(tx_hash, 10, 1),
+                // reorged out, then included in another block
+                None,
+                included(tx_hash, 11, 2),
+            ],
+            5,
+            vec![9, 11],
+        );
+
+        assert_eq!(
+            follow(fake, None).await,
+            vec![
+                (tx_hash, TxStatus::Included { block_number: 10 }),
+                (tx_hash, TxStatus::Included { block_number: 11 }),
+                (tx_hash, TxStatus::Finalized { block_number: 11 }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follow_a_replacement() {
+        let replacement_hash = B256::repeat_byte(2);
+        let fake = FakeL1::new(vec![None, included(replacement_hash, 10, 1)], 5, vec![10]);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let replacement = Replacement {
+            tx: TxEip1559::default(),
+            fees: Eip1559Fees {
+                max_fee_per_gas: 10,
+                max_priority_fee_per_gas: 1,
+            },
+            deadline: now + Duration::from_secs(60),
+        };
+
+        assert_eq!(
+            follow(fake, Some(replacement)).await,
+            vec![
+                (replacement_hash, TxStatus::Included { block_number: 10 }),
+                (replacement_hash, TxStatus::Finalized { block_number: 10 }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follow_a_dropped_tx() {
+        // the nonce of the transaction is used by another one
+        let fake = FakeL1::new(vec![None], 6, vec![0]);
+
+        assert_eq!(
+            follow(fake, None).await,
+            vec![(B256::repeat_byte(1), TxStatus::Dropped)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follow_an_evicted_tx() {
+        let mut fake = FakeL1::new(vec![None], 5, vec![0]);
+        fake.known = false;
+
+        assert_eq!(
+            follow(fake, None).await,
+            vec![(B256::repeat_byte(1), TxStatus::Dropped)]
+        );
+    }
+}
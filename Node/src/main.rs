@@ -1,9 +1,11 @@
 mod bls;
 mod ethereum_l1;
+mod metrics;
 mod mev_boost;
 mod node;
 mod p2p_network;
 mod registration;
+mod signer;
 mod taiko;
 mod utils;
 
@@ -28,6 +30,30 @@ struct Cli {
     remove_validator: bool,
     #[clap(long, help = "Force Push lookahead to the PreconfTaskManager contract")]
     force_push_lookahead: bool,
+    #[clap(long, help = "Config file (TOML or YAML)")]
+    config: Option<String>,
+    #[clap(long, help = "Network preset: devnet or helder")]
+    network: Option<String>,
+    #[clap(
+        long = "set",
+        value_name = "NAME=VALUE",
+        help = "Override a config setting, e.g. --set VALIDATOR_INDEX=1"
+    )]
+    overrides: Vec<String>,
+    #[clap(long, help = "Validate the configuration and exit")]
+    check_config: bool,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Import the signed commitments of a slashing protection interchange file and exit"
+    )]
+    import_slashing_protection: Option<String>,
+    #[clap(
+        long,
+        value_name = "FILE",
+        help = "Export the signed commitments to a slashing protection interchange file and exit"
+    )]
+    export_slashing_protection: Option<String>,
 }
 
 #[tokio::main]
@@ -37,21 +63,63 @@ async fn main() -> Result<(), Error> {
     tracing::info!("🚀 Starting AVS Node");
 
     let args = Cli::parse();
-    let config = utils::config::Config::read_env_variables();
+    let mut config = utils::config::Config::load(
+        args.config.as_deref(),
+        args.network.as_deref(),
+        &args.overrides,
+    )?;
 
-    let bls_service = Arc::new(bls::BLSService::new(&config.validator_bls_privkey)?);
+    if args.check_config {
+        tracing::info!("Configuration is valid");
+        return Ok(());
+    }
+
+    if let Some(path) = &args.import_slashing_protection {
+        let mut slashing_protection =
+            node::slashing_protection::SlashingProtection::open(&config.data_dir)?;
+        slashing_protection.import_from_file(path)?;
+        return Ok(());
+    }
+
+    if let Some(path) = &args.export_slashing_protection {
+        let slashing_protection =
+            node::slashing_protection::SlashingProtection::open(&config.data_dir)?;
+        slashing_protection.export_to_file(path)?;
+        return Ok(());
+    }
+
+    // The registry verifies the BLS signature of the raw validator message, not of a signing
+    // root, so Web3Signer can't sign it
+    if (args.add_validator || args.remove_validator)
+        && config
+            .web3signer
+            .as_ref()
+            .is_some_and(|web3signer| web3signer.bls_public_key.is_some())
+    {
+        return Err(anyhow::anyhow!(
+            "Adding or removing a validator requires the BLS key locally, unset WEB3SIGNER_BLS_PUBLIC_KEY and set VALIDATOR_BLS_PRIVATEKEY or VALIDATOR_BLS_KEYSTORE_PATH"
+        ));
+    }
+
+    let (ecdsa_signer, bls_signer) = signer::create_signers(&config).await?;
+
+    if config.p2p_bind_preconfer_identity {
+        let mut secret = signer::keystore::load_ecdsa_secret(&config.avs_node_ecdsa_key)?;
+        config.p2p_network_config.local_key = ::p2p_network::secp256k1_from_bytes(&mut secret)?;
+    }
 
     let ethereum_l1 = ethereum_l1::EthereumL1::new(
         &config.l1_ws_rpc_url,
-        &config.avs_node_ecdsa_private_key,
+        ecdsa_signer,
         &config.contract_addresses,
         &config.l1_beacon_url,
         config.l1_slot_duration_sec,
         config.l1_slots_per_epoch,
         config.msg_expiry_sec,
-        bls_service.clone(),
+        bls_signer.clone(),
         config.l1_chain_id,
         config.l2_slot_duration_sec,
+        config.gas_oracle.clone(),
     )
     .await?;
 
@@ -78,11 +146,24 @@ async fn main() -> Result<(), Error> {
         return Ok(());
     }
 
+    if config.enable_metrics {
+        metrics::serve(([0, 0, 0, 0], config.metrics_port).into()).await?;
+    }
+
     let (node_to_p2p_tx, node_to_p2p_rx) = mpsc::channel(MESSAGE_QUEUE_SIZE);
     let (p2p_to_node_tx, p2p_to_node_rx) = mpsc::channel(MESSAGE_QUEUE_SIZE);
+    let (p2p_validation_tx, p2p_validation_rx) = mpsc::channel(MESSAGE_QUEUE_SIZE);
+    let (p2p_report_tx, p2p_report_rx) = mpsc::channel(MESSAGE_QUEUE_SIZE);
     let (block_proposed_tx, block_proposed_rx) = mpsc::channel(MESSAGE_QUEUE_SIZE);
+    let (p2p_sync_channels, p2p_sync) = ::p2p_network::sync::channels(MESSAGE_QUEUE_SIZE);
     if config.enable_p2p {
-        let p2p = p2p_network::AVSp2p::new(p2p_to_node_tx.clone(), node_to_p2p_rx);
+        let p2p = p2p_network::AVSp2p::new(
+            p2p_to_node_tx.clone(),
+            node_to_p2p_rx,
+            p2p_validation_rx,
+            p2p_report_rx,
+            p2p_sync_channels,
+        );
         p2p.start(config.p2p_network_config).await;
     }
     let taiko = Arc::new(taiko::Taiko::new(
@@ -91,26 +172,61 @@ async fn main() -> Result<(), Error> {
         config.taiko_chain_id,
     ));
 
-    let mev_boost = mev_boost::MevBoost::new(&config.mev_boost_url, config.validator_index);
+    let genesis_fork_version = ethereum_l1
+        .consensus_layer
+        .get_genesis_details()
+        .await?
+        .genesis_fork_version;
+    let mev_boost = mev_boost::MevBoost::new(
+        &config.mev_boost_url,
+        config.validator_index,
+        genesis_fork_version,
+    );
     let ethereum_l1 = Arc::new(ethereum_l1);
 
     let block_proposed_event_checker =
         BlockProposedEventReceiver::new(ethereum_l1.clone(), block_proposed_tx);
     BlockProposedEventReceiver::start(block_proposed_event_checker);
 
-    let lookahead_updated_event_checker = LookaheadUpdatedEventReceiver::new(ethereum_l1.clone());
+    let dispute_scheduler = node::dispute_scheduler::DisputeScheduler::start(ethereum_l1.clone());
+    let lookahead_updated_event_checker =
+        LookaheadUpdatedEventReceiver::new(ethereum_l1.clone(), dispute_scheduler.clone());
     lookahead_updated_event_checker.start();
 
-    if config.enable_preconfirmation {
-        let node = node::Node::new(
+    if config.watchtower {
+        let watchtower = node::watchtower::Watchtower::new(
+            ethereum_l1.clone(),
+            config.taiko_chain_id,
+            config.p2p_require_registered_author,
+            p2p_validation_tx,
+            dispute_scheduler,
+        );
+        watchtower.start(block_proposed_rx, p2p_to_node_rx).await;
+    } else if config.enable_preconfirmation {
+        let channels = node::NodeChannels {
             block_proposed_rx,
             node_to_p2p_tx,
             p2p_to_node_rx,
+            p2p_validation_tx,
+            p2p_report_tx,
+            p2p_sync,
+        };
+        let settings = node::NodeSettings {
+            l2_slot_duration_sec: config.l2_slot_duration_sec,
+            data_dir: config.data_dir.clone(),
+            require_registered_author: config.p2p_require_registered_author,
+            preconf_api_addr: config
+                .enable_preconf_api
+                .then(|| (config.preconf_api_address, config.preconf_api_port).into()),
+        };
+        let node = node::Node::new(
+            channels,
             taiko.clone(),
             ethereum_l1.clone(),
             mev_boost,
-            config.l2_slot_duration_sec,
-            bls_service,
+            bls_signer,
+            settings,
+            dispute_scheduler,
         )
         .await?;
         node.entrypoint().await?;
@@ -0,0 +1,214 @@
+// Prometheus metrics of the node, served as text on the /metrics HTTP endpoint.
+use crate::node::operator::Status as OperatorStatus;
+use anyhow::Error;
+use http_body_util::Full;
+use hyper::{body::Bytes, server::conn::http1, service::service_fn, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Encoder, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, TextEncoder,
+};
+use std::{convert::Infallible, net::SocketAddr};
+use tokio::net::TcpListener;
+use tracing::{debug, error, info};
+
+lazy_static! {
+    pub static ref PRECONFIRMATIONS_PRODUCED: IntCounter = register_int_counter!(
+        "avs_preconfirmations_produced_total",
+        "Preconfirmations signed and gossiped by this node"
+    )
+    .unwrap();
+    pub static ref PRECONFIRMATIONS_RECEIVED: IntCounter = register_int_counter!(
+        "avs_preconfirmations_received_total",
+        "Preconfirmations received from the p2p network"
+    )
+    .unwrap();
+    pub static ref PRECONFIRMATIONS_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "avs_preconfirmations_rejected_total",
+        "Preconfirmations received from the p2p network and rejected",
+        &["reason"]
+    )
+    .unwrap();
+    pub static ref EQUIVOCATIONS_DETECTED: IntCounter = register_int_counter!(
+        "avs_equivocations_detected_total",
+        "Conflicting preconfirmations of the same block signed by the same preconfer"
+    )
+    .unwrap();
+    pub static ref DISPUTES: IntCounterVec = register_int_counter_vec!(
+        "avs_disputes_total",
+        "Disputes of incorrect preconfirmations and lookahead entries, by final outcome",
+        &["kind", "outcome"]
+    )
+    .unwrap();
+    pub static ref PENDING_DISPUTES: IntGauge = register_int_gauge!(
+        "avs_pending_disputes",
+        "Disputes queued until they are sent or their dispute window closes"
+    )
+    .unwrap();
+    pub static ref ADVANCE_HEAD_DURATION: HistogramVec = register_histogram_vec!(
+        "avs_advance_head_duration_seconds",
+        "Latency of advancing the L2 head to a new preconfirmed block",
+        &["source", "result"]
+    )
+    .unwrap();
+    pub static ref L1_TXS_SENT: IntCounterVec = register_int_counter_vec!(
+        "avs_l1_txs_sent_total",
+        "Transactions sent to the L1 AVS contracts",
+        &["kind"]
+    )
+    .unwrap();
+    pub static ref L1_TX_SEND_FAILURES: IntCounterVec = register_int_counter_vec!(
+        "avs_l1_tx_send_failures_total",
+        "Transactions to the L1 AVS contracts which could not be sent",
+        &["kind"]
+    )
+    .unwrap();
+    pub static ref L1_TX_OUTCOMES: IntCounterVec = register_int_counter_vec!(
+        "avs_l1_tx_outcomes_total",
+        "Outcomes of the transactions sent to the L1 AVS contracts",
+        &["kind", "outcome"]
+    )
+    .unwrap();
+    pub static ref MEV_BOOST_CONSTRAINTS: IntCounterVec = register_int_counter_vec!(
+        "avs_mev_boost_constraints_total",
+        "Constraints posted to MEV-Boost",
+        &["result"]
+    )
+    .unwrap();
+    pub static ref OPERATOR_STATUS: IntGaugeVec = register_int_gauge_vec!(
+        "avs_operator_status",
+        "Current operator status, 1 for the active one",
+        &["status"]
+    )
+    .unwrap();
+    pub static ref L1_EPOCH: IntGauge =
+        register_int_gauge!("avs_l1_epoch", "Current L1 epoch").unwrap();
+    pub static ref L1_SLOT: IntGauge =
+        register_int_gauge!("avs_l1_slot", "Current L1 slot").unwrap();
+    pub static ref GOSSIPSUB_PEERS: IntGauge =
+        register_int_gauge!("avs_gossipsub_peers", "Connected gossipsub peers").unwrap();
+}
+
+/// Marks `status` as the current operator status.
+pub fn set_operator_status(status: &OperatorStatus) {
+    for known in [
+        OperatorStatus::PreconferAndProposer,
+        OperatorStatus::Preconfer,
+        OperatorStatus::FallbackPreconfer,
+        OperatorStatus::None,
+    ] {
+        OPERATOR_STATUS
+            .with_label_values(&[operator_status_label(&known)])
+            .set((known == *status) as i64);
+    }
+}
+
+fn operator_status_label(status: &OperatorStatus) -> &'static str {
+    match status {
+        OperatorStatus::PreconferAndProposer => "PreconferAndProposer",
+        OperatorStatus::Preconfer => "Preconfer",
+        OperatorStatus::FallbackPreconfer => "FallbackPreconfer",
+        OperatorStatus::None => "None",
+    }
+}
+
+pub fn result_label<T, E>(result: &Result<T, E>) -> &'static str {
+    if result.is_ok() {
+        "success"
+    } else {
+        "failure"
+    }
+}
+
+fn encode() -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    Ok(buffer)
+}
+
+async fn handle(
+    request: Request<hyper::body::Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if request.uri().path() != "/metrics" {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Full::new(Bytes::new()))
+            .unwrap());
+    }
+
+    let response = match encode() {
+        Ok(body) => Response::builder()
+            .header(
+                hyper::header::CONTENT_TYPE,
+                TextEncoder::new().format_type(),
+            )
+            .body(Full::new(Bytes::from(body))),
+        Err(e) => {
+            error!("Failed to encode metrics: {}", e);
+            Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Full::new(Bytes::new()))
+        }
+    };
+    Ok(response.unwrap())
+}
+
+/// Serves the metrics on `http://addr/metrics` until the process exits.
+pub async fn serve(addr: SocketAddr) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving metrics on http://{}/metrics", addr);
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    error!("Failed to accept metrics connection: {}", e);
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                if let Err(e) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service_fn(handle))
+                    .await
+                {
+                    debug!("Metrics connection error: {}", e);
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        PRECONFIRMATIONS_PRODUCED.inc();
+        set_operator_status(&OperatorStatus::Preconfer);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        serve(addr).await.unwrap();
+
+        let body = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("avs_preconfirmations_produced_total"));
+        assert!(body.contains("avs_operator_status{status=\"Preconfer\"} 1"));
+        assert!(body.contains("avs_operator_status{status=\"None\"} 0"));
+
+        let response = reqwest::get(format!("http://{}/other", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+}
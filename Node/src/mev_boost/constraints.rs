@@ -1,11 +1,18 @@
-use crate::bls::BLSService;
+use crate::signer::{BlsSigner, BLS_POP_DST};
 use alloy::hex::encode;
+use anyhow::Error;
 use serde::ser::Serializer;
 use serde::Serialize;
-use ssz::Encode;
+use sha2::{Digest, Sha256};
 use ssz_derive::{Decode, Encode};
 use std::sync::Arc;
 
+// The constraints are signed in the builder domain, like the validator registrations
+const DOMAIN_APPLICATION_BUILDER: [u8; 4] = [0, 0, 0, 1];
+// List limits of the constraints API types, they are part of the hash tree root
+const MAX_CONSTRAINTS_PER_SLOT: u64 = 1 << 20;
+const MAX_BYTES_PER_TRANSACTION: u64 = 1 << 30;
+
 #[derive(PartialEq, Debug, Encode, Decode, Serialize)]
 pub struct Constraint {
     #[serde(serialize_with = "serialize_vec_as_hex")]
@@ -20,6 +27,30 @@ pub struct ConstraintsMessage {
     constraints: Vec<Constraint>,
 }
 
+impl Constraint {
+    // The index is an SSZ Union[None, uint64]
+    fn hash_tree_root(&self) -> [u8; 32] {
+        let chunks: Vec<[u8; 32]> = self
+            .tx
+            .chunks(32)
+            .map(|bytes| {
+                let mut chunk = [0u8; 32];
+                chunk[..bytes.len()].copy_from_slice(bytes);
+                chunk
+            })
+            .collect();
+        let tx_root = mix_in_length(
+            merkleize(&chunks, MAX_BYTES_PER_TRANSACTION.div_ceil(32)),
+            self.tx.len() as u64,
+        );
+        let index_root = match self.index {
+            Some(index) => mix_in_length(uint64_root(index), 1),
+            None => mix_in_length([0; 32], 0),
+        };
+        merkleize(&[tx_root, index_root], 2)
+    }
+}
+
 impl ConstraintsMessage {
     pub fn new(validator_index: u64, slot: u64, messages: Vec<Vec<u8>>) -> Self {
         let constraints = messages
@@ -35,6 +66,81 @@ impl ConstraintsMessage {
             constraints,
         }
     }
+
+    /// Root signed by the proposer, computed as in the builder API from the hash tree root of
+    /// the message and the builder domain of the chain.
+    pub fn signing_root(&self, genesis_fork_version: [u8; 4]) -> [u8; 32] {
+        hash_pair(
+            &self.hash_tree_root(),
+            &builder_domain(genesis_fork_version),
+        )
+    }
+
+    fn hash_tree_root(&self) -> [u8; 32] {
+        let constraints: Vec<[u8; 32]> = self
+            .constraints
+            .iter()
+            .map(Constraint::hash_tree_root)
+            .collect();
+        let constraints_root = mix_in_length(
+            merkleize(&constraints, MAX_CONSTRAINTS_PER_SLOT),
+            constraints.len() as u64,
+        );
+        merkleize(
+            &[
+                uint64_root(self.validator_index),
+                uint64_root(self.slot),
+                constraints_root,
+            ],
+            3,
+        )
+    }
+}
+
+fn builder_domain(genesis_fork_version: [u8; 4]) -> [u8; 32] {
+    let mut fork_version = [0u8; 32];
+    fork_version[..4].copy_from_slice(&genesis_fork_version);
+    // the genesis validators root is left empty in the builder domain
+    let fork_data_root = hash_pair(&fork_version, &[0; 32]);
+    let mut domain = [0u8; 32];
+    domain[..4].copy_from_slice(&DOMAIN_APPLICATION_BUILDER);
+    domain[4..].copy_from_slice(&fork_data_root[..28]);
+    domain
+}
+
+fn hash_pair(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn uint64_root(value: u64) -> [u8; 32] {
+    let mut chunk = [0u8; 32];
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+fn mix_in_length(root: [u8; 32], length: u64) -> [u8; 32] {
+    hash_pair(&root, &uint64_root(length))
+}
+
+// Merkle root of `chunks` padded with zero chunks up to the next power of two of `limit`
+fn merkleize(chunks: &[[u8; 32]], limit: u64) -> [u8; 32] {
+    let depth = limit.next_power_of_two().trailing_zeros();
+    let mut layer = chunks.to_vec();
+    let mut zero = [0u8; 32];
+    for _ in 0..depth {
+        if layer.len() % 2 == 1 {
+            layer.push(zero);
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+        zero = hash_pair(&zero, &zero);
+    }
+    layer.first().copied().unwrap_or(zero)
 }
 
 #[derive(Serialize)]
@@ -61,18 +167,59 @@ where
 }
 
 impl SignedConstraints {
-    pub fn new(message: ConstraintsMessage, bls: Arc<BLSService>) -> Self {
-        // Encode message;
-        let data = message.as_ssz_bytes();
-        // Use propper DST
-        let dst = "BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_"
-            .as_bytes()
-            .to_vec();
-        // Sign message
+    pub async fn new(
+        message: ConstraintsMessage,
+        bls: Arc<dyn BlsSigner>,
+        genesis_fork_version: [u8; 4],
+    ) -> Result<Self, Error> {
+        // A signing root can be signed by Web3Signer as well as by the local key
         let signature: [u8; 96] = bls
-            .sign(&data, &dst)
+            .sign(&message.signing_root(genesis_fork_version), BLS_POP_DST)
+            .await?
             .try_into()
-            .expect("Vec should have exactly 96 elements");
-        Self { message, signature }
+            .map_err(|_| anyhow::anyhow!("BLS signature should have exactly 96 bytes"))?;
+        Ok(Self { message, signature })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_domain() {
+        // the builder domain of mainnet, as used by MEV-Boost
+        assert_eq!(
+            alloy::hex::encode(builder_domain([0; 4])),
+            "00000001f5a5fd42d16a20302798ef6ed309979b43003d2320d9f0e8ea9831a9"
+        );
+    }
+
+    #[test]
+    fn test_merkleize() {
+        let chunk = [1u8; 32];
+        assert_eq!(merkleize(&[chunk], 1), chunk);
+        assert_eq!(merkleize(&[chunk], 2), hash_pair(&chunk, &[0; 32]));
+        let zero_pair = hash_pair(&[0; 32], &[0; 32]);
+        assert_eq!(
+            merkleize(&[chunk, chunk, chunk], 3),
+            hash_pair(&hash_pair(&chunk, &chunk), &hash_pair(&chunk, &[0; 32]))
+        );
+        assert_eq!(merkleize(&[], 4), hash_pair(&zero_pair, &zero_pair));
+    }
+
+    #[test]
+    fn test_signing_root_covers_the_message() {
+        let message = ConstraintsMessage::new(1, 100, vec![vec![1, 2, 3]]);
+        let root = message.signing_root([0; 4]);
+        assert_ne!(
+            root,
+            ConstraintsMessage::new(1, 100, vec![vec![1, 2, 4]]).signing_root([0; 4])
+        );
+        assert_ne!(
+            root,
+            ConstraintsMessage::new(1, 101, vec![vec![1, 2, 3]]).signing_root([0; 4])
+        );
+        assert_ne!(root, message.signing_root([1, 0, 0, 0]));
     }
 }
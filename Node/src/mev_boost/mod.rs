@@ -1,4 +1,4 @@
-use crate::bls::BLSService;
+use crate::signer::BlsSigner;
 use anyhow::Error;
 use reqwest::Client;
 use serde_json::Value;
@@ -12,13 +12,15 @@ mod tests;
 pub struct MevBoost {
     url: String,
     validator_index: u64,
+    genesis_fork_version: [u8; 4],
 }
 
 impl MevBoost {
-    pub fn new(url: &str, validator_index: u64) -> Self {
+    pub fn new(url: &str, validator_index: u64, genesis_fork_version: [u8; 4]) -> Self {
         Self {
             url: url.to_string(),
             validator_index,
+            genesis_fork_version,
         }
     }
 
@@ -39,13 +41,13 @@ impl MevBoost {
         &self,
         constraints: Vec<Vec<u8>>,
         slot_id: u64,
-        bls_service: Arc<BLSService>,
+        bls_signer: Arc<dyn BlsSigner>,
     ) -> Result<(), Error> {
         // Prepare the message
 
         let message = ConstraintsMessage::new(self.validator_index, slot_id, constraints);
 
-        let signed = SignedConstraints::new(message, bls_service);
+        let signed = SignedConstraints::new(message, bls_signer, self.genesis_fork_version).await?;
 
         let json_data = serde_json::to_value([&signed])?;
 
@@ -1,5 +1,4 @@
 use crate::ethereum_l1::{block_proposed::BlockProposed, EthereumL1};
-use futures_util::StreamExt;
 use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 use tracing::{error, info};
@@ -25,54 +24,35 @@ impl BlockProposedEventReceiver {
     }
 
     async fn check_for_events(self) {
-        let event_subscription = match self
+        let mut event_subscription = match self
             .ethereum_l1
             .execution_layer
             .subscribe_to_block_proposed_event()
             .await
         {
-            Ok(event_stream) => event_stream,
+            Ok(event_subscription) => event_subscription,
             Err(e) => {
                 error!("Error subscribing to block proposed event: {:?}", e);
                 return;
             }
         };
 
-        let mut stream = event_subscription.0.into_stream();
-        loop {
-            match stream.next().await {
-                Some(log) => match log {
-                    Ok(log) => {
-                        let block_proposed = log.0;
-                        info!(
-                            "Received block proposed event for block: {}",
-                            block_proposed.blockId
-                        );
-                        match BlockProposed::new(block_proposed) {
-                            Ok(block_proposed) => {
-                                if let Err(e) = self.node_tx.send(block_proposed).await {
-                                    error!(
-                                        "Error sending block proposed event by channel: {:?}",
-                                        e
-                                    );
-                                }
-                            }
-                            Err(e) => {
-                                error!("Error creating block proposed event: {:?}", e);
-                            }
-                        }
+        while let Some(block_proposed) = event_subscription.0.next().await {
+            info!(
+                "Received block proposed event for block: {}",
+                block_proposed.blockId
+            );
+            match BlockProposed::new(block_proposed) {
+                Ok(block_proposed) => {
+                    if let Err(e) = self.node_tx.send(block_proposed).await {
+                        error!("Error sending block proposed event by channel: {:?}", e);
                     }
-                    Err(e) => {
-                        error!("Error receiving block proposed event: {:?}", e);
-                        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-                    }
-                },
-                None => {
-                    error!("No block proposed event received, stream closed");
-                    // TODO: recreate a stream in this case?
-                    return;
+                }
+                Err(e) => {
+                    error!("Error creating block proposed event: {:?}", e);
                 }
             }
         }
+        error!("Block proposed event subscription stopped");
     }
 }
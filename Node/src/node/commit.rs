@@ -1,5 +1,5 @@
+use crate::utils::bytes_tools::hash_bytes_with_keccak;
 use crate::utils::types::*;
-use crate::{taiko::l2_tx_lists::RPCReplyL2TxLists, utils::bytes_tools::hash_bytes_with_keccak};
 use alloy_rlp::{Encodable, RlpDecodable, RlpEncodable};
 use anyhow::Error;
 use secp256k1::{ecdsa::Signature, Message, Secp256k1, SecretKey};
@@ -13,7 +13,7 @@ pub struct L2TxListsCommit {
 }
 
 impl L2TxListsCommit {
-    pub fn new(reply: &RPCReplyL2TxLists, block_height: u64, chain_id: u64) -> Self {
+    pub fn new(tx_list_bytes: &[u8], block_height: u64, chain_id: u64) -> Self {
         let block_height_bytes = block_height.to_le_bytes(); // Convert u64 to a [u8; 8] array
         let mut block_height = [0u8; 32];
         block_height[24..].copy_from_slice(&block_height_bytes);
@@ -23,7 +23,7 @@ impl L2TxListsCommit {
         L2TxListsCommit {
             block_height,
             chain_id,
-            tx_list_hash: hash_bytes_with_keccak(reply.tx_list_bytes[0].as_slice()),
+            tx_list_hash: hash_bytes_with_keccak(tx_list_bytes),
         }
     }
 
@@ -77,6 +77,15 @@ mod tests {
         assert_eq!(hash.len(), 32);
     }
 
+    #[test]
+    fn test_new_matches_from_preconf() {
+        let tx_list_bytes = [1u8, 2, 3, 4];
+        let commit = L2TxListsCommit::new(&tx_list_bytes, 5, 167);
+        let from_preconf =
+            L2TxListsCommit::from_preconf(5, hash_bytes_with_keccak(&tx_list_bytes), 167);
+        assert_eq!(commit, from_preconf);
+    }
+
     #[test]
     fn test_sign() {
         let mut block_height = [0u8; 32];
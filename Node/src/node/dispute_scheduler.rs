@@ -0,0 +1,371 @@
+// Disputes of incorrect preconfirmations and lookahead entries. The contract only accepts a proof
+// within `PreconfConstants.DISPUTE_PERIOD`, so every candidate is queued with its deadline,
+// simulated with `eth_call` before it is sent and retried on transient failures until then.
+// Disputes still unresolved at their deadline are reported.
+use super::lookahead_updated_receiver::LookaheadUpdatedEventHandler;
+use crate::{
+    ethereum_l1::{
+        block_proposed::BlockProposed, execution_layer::DisputeOutcome, slot_clock::SlotClock,
+        EthereumL1,
+    },
+    metrics,
+    utils::types::*,
+};
+use anyhow::Error;
+use beacon_api_client::ProposerDuty;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error, info, warn};
+
+// PreconfConstants.DISPUTE_PERIOD
+const DISPUTE_PERIOD_EPOCHS: u64 = 2;
+// Upper bound of the backoff between the attempts of a dispute
+const MAX_RETRY_DELAY_SLOTS: u32 = 4;
+
+pub enum Dispute {
+    /// A preconfirmation of `block_proposed` which may commit to another tx list.
+    Preconfirmation {
+        chain_id: u64,
+        tx_list_hash: [u8; 32],
+        signature: [u8; 65],
+        block_proposed: Box<BlockProposed>,
+    },
+    /// A lookahead entry of `slot` which doesn't match the duty of its proposer.
+    Lookahead {
+        slot: Slot,
+        slot_timestamp: u64,
+        epoch_duty: ProposerDuty,
+    },
+}
+
+impl Dispute {
+    fn kind(&self) -> &'static str {
+        match self {
+            Dispute::Preconfirmation { .. } => "preconfirmation",
+            Dispute::Lookahead { .. } => "lookahead",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Dispute::Preconfirmation {
+                tx_list_hash,
+                block_proposed,
+                ..
+            } => format!(
+                "preconfirmation of the block {} with tx list 0x{}",
+                block_proposed.block_id(),
+                hex::encode(tx_list_hash)
+            ),
+            Dispute::Lookahead { slot, .. } => format!("lookahead entry of the slot {slot}"),
+        }
+    }
+
+    /// Returns the time of the first attempt and the deadline, since the UNIX epoch.
+    fn window(&self, slot_clock: &SlotClock) -> Result<(Duration, Duration), Error> {
+        match self {
+            // The metadata timestamp is not later than the `proposedAt` the contract counts from
+            Dispute::Preconfirmation { block_proposed, .. } => Ok((
+                Duration::ZERO,
+                deadline(block_proposed.event_data().meta.timestamp, slot_clock),
+            )),
+            // The proof needs the beacon block of the next slot
+            Dispute::Lookahead {
+                slot,
+                slot_timestamp,
+                ..
+            } => Ok((
+                slot_clock.start_of(slot + 1)?,
+                deadline(*slot_timestamp, slot_clock),
+            )),
+        }
+    }
+
+    async fn attempt(&self, ethereum_l1: &EthereumL1) -> Result<DisputeOutcome, Error> {
+        match self {
+            Dispute::Preconfirmation {
+                chain_id,
+                tx_list_hash,
+                signature,
+                block_proposed,
+            } => {
+                ethereum_l1
+                    .execution_layer
+                    .check_and_prove_incorrect_preconfirmation(
+                        *chain_id,
+                        *tx_list_hash,
+                        *signature,
+                        block_proposed,
+                    )
+                    .await
+            }
+            Dispute::Lookahead {
+                slot,
+                slot_timestamp,
+                epoch_duty,
+            } => {
+                LookaheadUpdatedEventHandler::prove_incorrect_lookahead(
+                    ethereum_l1,
+                    *slot,
+                    *slot_timestamp,
+                    epoch_duty,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Time after an event during which it can be disputed.
+pub fn dispute_period(slot_clock: &SlotClock) -> Duration {
+    Duration::from_secs(
+        DISPUTE_PERIOD_EPOCHS
+            * slot_clock.get_slots_per_epoch()
+            * slot_clock.get_slot_duration().as_secs(),
+    )
+}
+
+/// Last time to send a dispute of an event at `timestamp`, leaving a slot for the inclusion of
+/// the tx before the dispute window closes.
+fn deadline(timestamp: u64, slot_clock: &SlotClock) -> Duration {
+    (Duration::from_secs(timestamp) + dispute_period(slot_clock))
+        .saturating_sub(slot_clock.get_slot_duration())
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+struct PendingDispute<D> {
+    dispute: D,
+    next_attempt: Duration,
+    deadline: Duration,
+    attempts: u32,
+}
+
+/// Disputes waiting for their next attempt, served by earliest deadline.
+struct DisputeQueue<D> {
+    pending: Vec<PendingDispute<D>>,
+    slot_duration: Duration,
+}
+
+impl<D> DisputeQueue<D> {
+    fn new(slot_duration: Duration) -> Self {
+        Self {
+            pending: Vec::new(),
+            slot_duration,
+        }
+    }
+
+    fn push(&mut self, dispute: D, not_before: Duration, deadline: Duration) {
+        self.pending.push(PendingDispute {
+            dispute,
+            next_attempt: not_before,
+            deadline,
+            attempts: 0,
+        });
+    }
+
+    fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Removes the disputes whose deadline has passed.
+    fn take_expired(&mut self, now: Duration) -> Vec<PendingDispute<D>> {
+        let (expired, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| pending.deadline <= now);
+        self.pending = pending;
+        expired
+    }
+
+    /// Removes the due dispute with the earliest deadline.
+    fn take_due(&mut self, now: Duration) -> Option<PendingDispute<D>> {
+        let index = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(_, pending)| pending.next_attempt <= now)
+            .min_by_key(|(_, pending)| pending.deadline)
+            .map(|(index, _)| index)?;
+        Some(self.pending.swap_remove(index))
+    }
+
+    /// Queues a dispute again after a failed attempt, backing off from one slot up to
+    /// `MAX_RETRY_DELAY_SLOTS`.
+    fn retry(&mut self, mut pending: PendingDispute<D>, now: Duration) {
+        pending.attempts += 1;
+        let slots = 1u32 << (pending.attempts - 1).min(MAX_RETRY_DELAY_SLOTS.ilog2());
+        pending.next_attempt = now + self.slot_duration * slots;
+        self.pending.push(pending);
+    }
+
+    /// Time of the next attempt or deadline, if any dispute is pending.
+    fn next_wakeup(&self) -> Option<Duration> {
+        self.pending
+            .iter()
+            .map(|pending| pending.next_attempt.min(pending.deadline))
+            .min()
+    }
+}
+
+/// Handle to queue disputes, shared by the node and the lookahead updated event handler.
+#[derive(Clone)]
+pub struct DisputeScheduler {
+    disputes_tx: UnboundedSender<Dispute>,
+}
+
+impl DisputeScheduler {
+    pub fn start(ethereum_l1: Arc<EthereumL1>) -> Self {
+        info!("Starting dispute scheduler");
+        let (disputes_tx, disputes_rx) = mpsc::unbounded_channel();
+        tokio::spawn(Self::run(ethereum_l1, disputes_rx));
+        Self { disputes_tx }
+    }
+
+    pub fn schedule(&self, dispute: Dispute) {
+        if let Err(e) = self.disputes_tx.send(dispute) {
+            error!(
+                "Dispute scheduler stopped, dropping the dispute of the {}",
+                e.0.describe()
+            );
+        }
+    }
+
+    async fn run(ethereum_l1: Arc<EthereumL1>, mut disputes_rx: UnboundedReceiver<Dispute>) {
+        let mut queue = DisputeQueue::new(ethereum_l1.slot_clock.get_slot_duration());
+        loop {
+            for expired in queue.take_expired(now()) {
+                Self::report_expired(&expired);
+            }
+            if let Some(pending) = queue.take_due(now()) {
+                Self::attempt(&ethereum_l1, &mut queue, pending).await;
+                continue;
+            }
+            metrics::PENDING_DISPUTES.set(queue.len() as i64);
+
+            let wakeup = queue.next_wakeup();
+            tokio::select! {
+                dispute = disputes_rx.recv() => {
+                    let Some(dispute) = dispute else {
+                        error!("Dispute scheduler channel closed");
+                        return;
+                    };
+                    match dispute.window(&ethereum_l1.slot_clock) {
+                        Ok((not_before, deadline)) => {
+                            debug!("Scheduled the dispute of the {}", dispute.describe());
+                            queue.push(dispute, not_before, deadline);
+                        }
+                        Err(e) => error!(
+                            "Failed to schedule the dispute of the {}: {}",
+                            dispute.describe(),
+                            e
+                        ),
+                    }
+                }
+                _ = tokio::time::sleep(wakeup.unwrap_or_default().saturating_sub(now())), if wakeup.is_some() => {}
+            }
+        }
+    }
+
+    async fn attempt(
+        ethereum_l1: &EthereumL1,
+        queue: &mut DisputeQueue<Dispute>,
+        pending: PendingDispute<Dispute>,
+    ) {
+        let dispute = &pending.dispute;
+        match dispute.attempt(ethereum_l1).await {
+            Ok(DisputeOutcome::Sent) => {
+                info!("Sent the dispute of the {}", dispute.describe());
+                Self::count(dispute, "sent");
+            }
+            Ok(DisputeOutcome::Rejected) => {
+                debug!("Nothing to dispute in the {}", dispute.describe());
+                Self::count(dispute, "rejected");
+            }
+            Ok(DisputeOutcome::MissedWindow) => Self::report_expired(&pending),
+            Err(e) => {
+                warn!(
+                    "Dispute of the {} failed after {} attempts, retrying: {}",
+                    dispute.describe(),
+                    pending.attempts + 1,
+                    e
+                );
+                queue.retry(pending, now());
+            }
+        }
+    }
+
+    fn report_expired(pending: &PendingDispute<Dispute>) {
+        error!(
+            "Dispute window of the {} closed unresolved after {} attempts",
+            pending.dispute.describe(),
+            pending.attempts
+        );
+        Self::count(&pending.dispute, "expired");
+    }
+
+    fn count(dispute: &Dispute, outcome: &str) {
+        metrics::DISPUTES
+            .with_label_values(&[dispute.kind(), outcome])
+            .inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLOT: Duration = Duration::from_secs(12);
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_deadline() {
+        let slot_clock = SlotClock::new(0, 12, 12, 32, 3);
+        // two epochs after the event, less a slot for the inclusion
+        assert_eq!(deadline(1000, &slot_clock), secs(1000 + 768 - 12));
+    }
+
+    #[test]
+    fn test_earliest_deadline_first() {
+        let mut queue = DisputeQueue::new(SLOT);
+        queue.push("late", secs(0), secs(500));
+        queue.push("early", secs(0), secs(100));
+        queue.push("not yet", secs(50), secs(60));
+
+        assert_eq!(queue.take_due(secs(10)).unwrap().dispute, "early");
+        assert_eq!(queue.take_due(secs(10)).unwrap().dispute, "late");
+        assert!(queue.take_due(secs(10)).is_none());
+        assert_eq!(queue.next_wakeup(), Some(secs(50)));
+        assert_eq!(queue.take_due(secs(50)).unwrap().dispute, "not yet");
+    }
+
+    #[test]
+    fn test_retry_backs_off_until_expired() {
+        let mut queue = DisputeQueue::new(SLOT);
+        queue.push("dispute", secs(0), secs(200));
+
+        let mut now = secs(0);
+        let mut delays = vec![];
+        while let Some(pending) = queue.take_due(now) {
+            queue.retry(pending, now);
+            let next = queue.next_wakeup().unwrap();
+            delays.push((next - now).as_secs());
+            now = next;
+            if !queue.take_expired(now).is_empty() {
+                break;
+            }
+        }
+        // 12 + 24 + 48 + 48 + 48 = 180, the next attempt would be after the deadline
+        assert_eq!(delays, vec![12, 24, 48, 48, 48, 20]);
+        assert_eq!(queue.len(), 0);
+    }
+}
@@ -0,0 +1,179 @@
+// Detection of the preconfers committing to two different tx lists for the same block. Both
+// signed commitments are kept as evidence: at most one of them matches the block proposed on L1,
+// the other one is slashed with `proveIncorrectPreconfirmation`.
+use super::preconfirmation_message::PreconfirmationMessage;
+use crate::metrics;
+use alloy::primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::Mutex;
+use tracing::error;
+
+/// Evidence of the equivocations, by block height.
+pub type Equivocations = Arc<Mutex<HashMap<u64, Vec<Equivocation>>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Equivocation {
+    pub preconfer: Address,
+    pub block_height: u64,
+    pub first: PreconfirmationMessage,
+    pub second: PreconfirmationMessage,
+}
+
+impl Equivocation {
+    /// Returns the equivocation if `msg` and the `known` preconfirmation of the same block commit
+    /// to different tx lists and are signed by the same preconfer. A conflict between two
+    /// preconfers is not slashable, e.g. at a handover.
+    pub fn detect(known: &PreconfirmationMessage, msg: &PreconfirmationMessage) -> Option<Self> {
+        if known.block_height != msg.block_height || known.tx_list_hash == msg.tx_list_hash {
+            return None;
+        }
+        let preconfer = known.signer()?;
+        if msg.signer()? != preconfer {
+            return None;
+        }
+        Some(Self {
+            preconfer,
+            block_height: msg.block_height,
+            first: known.clone(),
+            second: msg.clone(),
+        })
+    }
+
+    /// Both commitments, for the dispute once the block is proposed.
+    pub fn commitments(&self) -> [&PreconfirmationMessage; 2] {
+        [&self.first, &self.second]
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        let hashes = |e: &Self| [e.first.tx_list_hash, e.second.tx_list_hash];
+        let [a, b] = hashes(self);
+        self.preconfer == other.preconfer && (hashes(other) == [a, b] || hashes(other) == [b, a])
+    }
+}
+
+/// Follows the blocks proposed on L1 to tell up to which one the dispute window is over, the
+/// evidence of the equivocations on those blocks isn't needed anymore.
+#[derive(Default)]
+pub struct DisputeWindows {
+    // (proposal timestamp, block id) of the blocks still in their dispute window
+    open: VecDeque<(u64, u64)>,
+}
+
+impl DisputeWindows {
+    /// Adds `block_id` proposed at `timestamp`, returns the last block whose dispute window is
+    /// over by then if it changed.
+    pub fn on_block_proposed(
+        &mut self,
+        block_id: u64,
+        timestamp: u64,
+        dispute_period: Duration,
+    ) -> Option<u64> {
+        self.open.push_back((timestamp, block_id));
+        let mut closed = None;
+        while let Some(&(proposed_at, block_id)) = self.open.front() {
+            if proposed_at + dispute_period.as_secs() > timestamp {
+                break;
+            }
+            closed = Some(block_id);
+            self.open.pop_front();
+        }
+        closed
+    }
+}
+
+/// Adds `equivocation` to the evidence and reports it, returns false if it was known already.
+pub async fn record(equivocations: &Equivocations, equivocation: Equivocation) -> bool {
+    let mut equivocations = equivocations.lock().await;
+    let known = equivocations.entry(equivocation.block_height).or_default();
+    if known.iter().any(|e| e.is_same(&equivocation)) {
+        return false;
+    }
+    error!(
+        "Preconfer {} equivocated on block {}: tx lists 0x{} and 0x{}",
+        equivocation.preconfer,
+        equivocation.block_height,
+        hex::encode(equivocation.first.tx_list_hash),
+        hex::encode(equivocation.second.tx_list_hash)
+    );
+    metrics::EQUIVOCATIONS_DETECTED.inc();
+    known.push(equivocation);
+    true
+}
+
+/// Drops the evidence of the blocks up to `last_closed_block`, out of their dispute window.
+pub async fn prune(equivocations: &Equivocations, last_closed_block: u64) {
+    equivocations
+        .lock()
+        .await
+        .retain(|block_height, _| *block_height > last_closed_block);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::preconfirmation_message::signed_preconfirmation;
+    use alloy::signers::local::PrivateKeySigner;
+    use serde_json::json;
+
+    #[test]
+    fn test_detect() {
+        let signer = PrivateKeySigner::random();
+        let first = signed_preconfirmation(5, json!([]), &[1], &signer);
+
+        let equivocation =
+            Equivocation::detect(&first, &signed_preconfirmation(5, json!([]), &[2], &signer))
+                .unwrap();
+        assert_eq!(equivocation.preconfer, signer.address());
+        assert_eq!(equivocation.block_height, 5);
+        assert_eq!(equivocation.first.tx_list_hash, first.tx_list_hash);
+
+        // the same commitment again
+        assert!(
+            Equivocation::detect(&first, &signed_preconfirmation(5, json!([]), &[1], &signer))
+                .is_none()
+        );
+        // another block
+        assert!(
+            Equivocation::detect(&first, &signed_preconfirmation(6, json!([]), &[2], &signer))
+                .is_none()
+        );
+        // another preconfer
+        let other = PrivateKeySigner::random();
+        assert!(
+            Equivocation::detect(&first, &signed_preconfirmation(5, json!([]), &[2], &other))
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_once() {
+        let signer = PrivateKeySigner::random();
+        let first = signed_preconfirmation(5, json!([]), &[1], &signer);
+        let second = signed_preconfirmation(5, json!([]), &[2], &signer);
+        let equivocations = Equivocations::default();
+
+        let equivocation = Equivocation::detect(&first, &second).unwrap();
+        assert!(record(&equivocations, equivocation).await);
+        // received in the other order from another peer
+        let reversed = Equivocation::detect(&second, &first).unwrap();
+        assert!(!record(&equivocations, reversed).await);
+        assert_eq!(equivocations.lock().await[&5].len(), 1);
+    }
+
+    #[test]
+    fn test_dispute_windows() {
+        let period = Duration::from_secs(100);
+        let mut windows = DisputeWindows::default();
+        assert_eq!(windows.on_block_proposed(1, 1000, period), None);
+        assert_eq!(windows.on_block_proposed(2, 1050, period), None);
+        assert_eq!(windows.on_block_proposed(3, 1099, period), None);
+        assert_eq!(windows.on_block_proposed(4, 1100, period), Some(1));
+        assert_eq!(windows.on_block_proposed(5, 1200, period), Some(4));
+        assert_eq!(windows.on_block_proposed(6, 1250, period), None);
+    }
+}
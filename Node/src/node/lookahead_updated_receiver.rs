@@ -1,10 +1,14 @@
+use super::dispute_scheduler::{Dispute, DisputeScheduler};
 use crate::{
-    ethereum_l1::{execution_layer::PreconfTaskManager, merkle_proofs::*, EthereumL1},
+    ethereum_l1::{
+        execution_layer::{DisputeOutcome, PreconfTaskManager},
+        merkle_proofs::*,
+        EthereumL1,
+    },
     utils::types::*,
 };
 use anyhow::Error;
 use beacon_api_client::ProposerDuty;
-use futures_util::StreamExt;
 use std::{sync::Arc, time::Duration};
 use tracing::{debug, error, info};
 
@@ -13,11 +17,15 @@ type LookaheadUpdated = Vec<PreconfTaskManager::LookaheadSetParam>;
 #[derive(Clone)]
 pub struct LookaheadUpdatedEventReceiver {
     ethereum_l1: Arc<EthereumL1>,
+    dispute_scheduler: DisputeScheduler,
 }
 
 impl LookaheadUpdatedEventReceiver {
-    pub fn new(ethereum_l1: Arc<EthereumL1>) -> Self {
-        Self { ethereum_l1 }
+    pub fn new(ethereum_l1: Arc<EthereumL1>, dispute_scheduler: DisputeScheduler) -> Self {
+        Self {
+            ethereum_l1,
+            dispute_scheduler,
+        }
     }
 
     pub fn start(self) {
@@ -28,54 +36,46 @@ impl LookaheadUpdatedEventReceiver {
     }
 
     async fn check_for_events(self) {
-        let event_poller = match self
+        let mut event_subscription = match self
             .ethereum_l1
             .execution_layer
             .subscribe_to_lookahead_updated_event()
             .await
         {
-            Ok(event_stream) => event_stream,
+            Ok(event_subscription) => event_subscription,
             Err(e) => {
                 error!("Error subscribing to lookahead updated event: {:?}", e);
                 return;
             }
         };
 
-        let mut stream = event_poller.0.into_stream();
-        loop {
-            match stream.next().await {
-                Some(log) => match log {
-                    Ok(log) => {
-                        let lookahead_params = log.0._0;
-                        debug!(
-                            "Received lookahead updated event with {} params.",
-                            lookahead_params.len()
-                        );
-                        let handler = LookaheadUpdatedEventHandler::new(self.ethereum_l1.clone());
-                        handler.handle_lookahead_updated_event(lookahead_params);
-                    }
-                    Err(e) => {
-                        error!("Error receiving lookahead updated event: {:?}", e);
-                        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
-                    }
-                },
-                None => {
-                    error!("No lookahead updated event received, stream closed");
-                    // TODO: recreate a stream in this case?
-                    return;
-                }
-            }
+        while let Some(lookahead_updated) = event_subscription.0.next().await {
+            let lookahead_params = lookahead_updated._0;
+            debug!(
+                "Received lookahead updated event with {} params.",
+                lookahead_params.len()
+            );
+            let handler = LookaheadUpdatedEventHandler::new(
+                self.ethereum_l1.clone(),
+                self.dispute_scheduler.clone(),
+            );
+            handler.handle_lookahead_updated_event(lookahead_params);
         }
+        error!("Lookahead updated event subscription stopped");
     }
 }
 
 pub struct LookaheadUpdatedEventHandler {
     ethereum_l1: Arc<EthereumL1>,
+    dispute_scheduler: DisputeScheduler,
 }
 
 impl LookaheadUpdatedEventHandler {
-    pub fn new(ethereum_l1: Arc<EthereumL1>) -> Self {
-        Self { ethereum_l1 }
+    pub fn new(ethereum_l1: Arc<EthereumL1>, dispute_scheduler: DisputeScheduler) -> Self {
+        Self {
+            ethereum_l1,
+            dispute_scheduler,
+        }
     }
 
     pub fn handle_lookahead_updated_event(
@@ -126,16 +126,14 @@ impl LookaheadUpdatedEventHandler {
                 .ethereum_l1
                 .slot_clock
                 .slot_of(Duration::from_secs(slot_timestamp))?;
+            info!("Lookahead mismatch found for slot: {}", slot);
             let corresponding_epoch_slot_index =
                 (slot % self.ethereum_l1.slot_clock.get_slots_per_epoch()) as usize;
-            self.wait_for_the_slot_to_prove_incorrect_lookahead(slot + 1)
-                .await?;
-            self.prove_incorrect_lookahead(
+            self.dispute_scheduler.schedule(Dispute::Lookahead {
                 slot,
                 slot_timestamp,
-                &epoch_duties[corresponding_epoch_slot_index],
-            )
-            .await?;
+                epoch_duty: epoch_duties[corresponding_epoch_slot_index].clone(),
+            });
         }
 
         Ok(())
@@ -176,34 +174,20 @@ impl LookaheadUpdatedEventHandler {
         return Ok(None);
     }
 
-    async fn wait_for_the_slot_to_prove_incorrect_lookahead(
-        &self,
-        slot: Slot,
-    ) -> Result<(), Error> {
-        tokio::time::sleep(
-            self.ethereum_l1
-                .slot_clock
-                .duration_to_slot_from_now(slot)?,
-        )
-        .await;
-        Ok(())
-    }
-
-    async fn prove_incorrect_lookahead(
-        &self,
+    /// Builds the proofs of the proposer of `slot` from the beacon state and block of the next
+    /// slot, and disputes the lookahead entry with them.
+    pub async fn prove_incorrect_lookahead(
+        ethereum_l1: &EthereumL1,
         slot: Slot,
         slot_timestamp: u64,
         epoch_duty: &ProposerDuty,
-    ) -> Result<(), Error> {
-        info!("Lookahead mismatch found for slot: {}", slot);
-
+    ) -> Result<DisputeOutcome, Error> {
         let next_slot = slot + 1;
 
-        let lookahead_pointer = self.find_lookahead_pointer(slot_timestamp).await?;
+        let lookahead_pointer = Self::find_lookahead_pointer(ethereum_l1, slot_timestamp).await?;
 
         let pub_key = &epoch_duty.public_key;
-        let beacon_state = self
-            .ethereum_l1
+        let beacon_state = ethereum_l1
             .consensus_layer
             .get_beacon_state(next_slot)
             .await?;
@@ -226,8 +210,7 @@ impl LookaheadUpdatedEventHandler {
         let (beacon_state_proof, beacon_state_root) =
             create_merkle_proof_for_validator_list_being_part_of_beacon_state(&beacon_state)?;
 
-        let beacon_block = self
-            .ethereum_l1
+        let beacon_block = ethereum_l1
             .consensus_layer
             .get_beacon_block(next_slot)
             .await?;
@@ -236,7 +219,7 @@ impl LookaheadUpdatedEventHandler {
                 &beacon_block,
             )?;
 
-        self.ethereum_l1
+        ethereum_l1
             .execution_layer
             .prove_incorrect_lookahead(
                 lookahead_pointer,
@@ -254,9 +237,11 @@ impl LookaheadUpdatedEventHandler {
             .await
     }
 
-    async fn find_lookahead_pointer(&self, slot_timestamp: u64) -> Result<u64, Error> {
-        let lookahead_preconfer_buffer = self
-            .ethereum_l1
+    async fn find_lookahead_pointer(
+        ethereum_l1: &EthereumL1,
+        slot_timestamp: u64,
+    ) -> Result<u64, Error> {
+        let lookahead_preconfer_buffer = ethereum_l1
             .execution_layer
             .get_lookahead_preconfer_buffer()
             .await?;
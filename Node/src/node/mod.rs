@@ -1,160 +1,482 @@
 pub mod block_proposed_receiver;
 mod commit;
+pub mod dispute_scheduler;
+mod equivocation;
 pub mod lookahead_monitor;
 pub mod lookahead_updated_receiver;
-mod operator;
+pub mod operator;
+mod preconf_api;
 mod preconfirmation_helper;
 mod preconfirmation_message;
 mod preconfirmation_proof;
+pub mod preconfirmation_store;
+mod preconfirmation_sync;
+pub mod slashing_protection;
+pub mod watchtower;
+mod wire;
 
 use crate::{
-    bls::BLSService,
-    ethereum_l1::{block_proposed::BlockProposed, execution_layer::PreconfTaskManager, EthereumL1},
+    ethereum_l1::{
+        block_proposed::BlockProposed,
+        execution_layer::PreconfTaskManager,
+        tx_tracker::{TxKind, TxOutcome, TxStatus},
+        EthereumL1,
+    },
+    metrics,
     mev_boost::MevBoost,
-    taiko::{l2_tx_lists::RPCReplyL2TxLists, Taiko},
+    signer::BlsSigner,
+    taiko::Taiko,
     utils::types::*,
 };
 use anyhow::Error;
 use commit::L2TxListsCommit;
+use dispute_scheduler::{Dispute, DisputeScheduler};
+use equivocation::{DisputeWindows, Equivocation, Equivocations};
 use operator::{Operator, Status as OperatorStatus};
+use p2p_network::{
+    network::{GossipMessage, MessageAcceptance, PeerReport, ValidationResult},
+    sync::{SyncHandle, SyncResponse, SyncResult},
+    topics::Topic,
+};
+use preconf_api::{CurrentPreconfer, PreconfApi};
 use preconfirmation_helper::PreconfirmationHelper;
 use preconfirmation_message::PreconfirmationMessage;
 use preconfirmation_proof::PreconfirmationProof;
+use preconfirmation_store::PreconfirmationStore;
+use preconfirmation_sync::PreconfirmationSync;
+use serde_json::Value;
+use slashing_protection::SlashingProtection;
 use std::{
     collections::HashMap,
+    net::SocketAddr,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
 };
 use tokio::sync::{
+    broadcast,
     mpsc::{Receiver, Sender},
     Mutex,
 };
-use tokio::time::{sleep, Duration};
-use tracing::{debug, error, info};
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{debug, error, info, warn};
+use wire::{FixedBytes, LookaheadAnnouncement, PreconferHandover, Signed, WireError, WireMessage};
 
 const OLDEST_BLOCK_DISTANCE: u64 = 256;
+const MAX_PROPOSAL_RETRIES: u32 = 2;
 
 type PreconfirmedBlocks = Arc<Mutex<HashMap<u64, PreconfirmationMessage>>>;
+type PreconfirmationTxs = Arc<Mutex<HashMap<u64, Vec<u8>>>>; // block_id -> tx
+type SharedPreconfirmationStore = Arc<Mutex<PreconfirmationStore>>;
+type FailedProposals = Arc<Mutex<Vec<FailedProposal>>>;
+type ProposalRetries = Arc<Mutex<HashMap<u64, u32>>>; // block_id -> retries
+
+/// Channels between the node, the L1 event receivers and the p2p network.
+pub struct NodeChannels {
+    pub block_proposed_rx: Receiver<BlockProposed>,
+    pub node_to_p2p_tx: Sender<(Topic, Vec<u8>)>,
+    pub p2p_to_node_rx: Receiver<GossipMessage>,
+    pub p2p_validation_tx: Sender<ValidationResult>,
+    pub p2p_report_tx: Sender<PeerReport>,
+    pub p2p_sync: SyncHandle,
+}
+
+pub struct NodeSettings {
+    pub l2_slot_duration_sec: u64,
+    /// Directory of the preconfirmation store and of the slashing protection database.
+    pub data_dir: String,
+    pub require_registered_author: bool,
+    pub preconf_api_addr: Option<SocketAddr>,
+}
+
+// The preconfirmations known to the node, shared by its loops
+#[derive(Clone)]
+struct SharedStores {
+    preconfirmed_blocks: PreconfirmedBlocks,
+    equivocations: Equivocations,
+    preconfirmation_txs: PreconfirmationTxs,
+    preconfirmation_store: SharedPreconfirmationStore,
+    preconf_api: PreconfApi,
+}
+
+// A block proposal whose transaction reverted or lost its nonce, to be proposed again
+struct FailedProposal {
+    block_id: u64,
+    tx_list: Vec<u8>,
+    // nonce of a dropped transaction, reused if it is still free
+    nonce: Option<u64>,
+}
 
 pub struct Node {
     taiko: Arc<Taiko>,
-    node_block_proposed_rx: Option<Receiver<BlockProposed>>,
-    node_to_p2p_tx: Sender<Vec<u8>>,
-    p2p_to_node_rx: Option<Receiver<Vec<u8>>>,
+    channels: Option<NodeChannels>,
+    node_to_p2p_tx: Sender<(Topic, Vec<u8>)>,
     ethereum_l1: Arc<EthereumL1>,
     mev_boost: MevBoost,
     epoch: Epoch,
     l2_slot_duration_sec: u64,
     preconfirmed_blocks: PreconfirmedBlocks,
+    equivocations: Equivocations,
     is_preconfer_now: Arc<AtomicBool>,
-    preconfirmation_txs: Arc<Mutex<HashMap<u64, Vec<u8>>>>, // block_id -> tx
+    preconfirmation_txs: PreconfirmationTxs,
+    preconfirmation_store: SharedPreconfirmationStore,
+    slashing_protection: SlashingProtection,
+    tx_outcomes_rx: Option<broadcast::Receiver<TxOutcome>>,
+    failed_proposals: FailedProposals,
+    proposal_retries: ProposalRetries,
+    // set while the lookahead pushed to claim an empty slot is not finalized
+    fallback_push_pending: Arc<AtomicBool>,
     operator: Operator,
     preconfirmation_helper: PreconfirmationHelper,
-    bls_service: Arc<BLSService>,
+    bls_signer: Arc<dyn BlsSigner>,
+    preconf_api: PreconfApi,
+    preconf_api_addr: Option<SocketAddr>,
+    require_registered_author: bool,
+    dispute_scheduler: DisputeScheduler,
 }
 
 impl Node {
     pub async fn new(
-        node_rx: Receiver<BlockProposed>,
-        node_to_p2p_tx: Sender<Vec<u8>>,
-        p2p_to_node_rx: Receiver<Vec<u8>>,
+        channels: NodeChannels,
         taiko: Arc<Taiko>,
         ethereum_l1: Arc<EthereumL1>,
         mev_boost: MevBoost,
-        l2_slot_duration_sec: u64,
-        bls_service: Arc<BLSService>,
+        bls_signer: Arc<dyn BlsSigner>,
+        settings: NodeSettings,
+        dispute_scheduler: DisputeScheduler,
     ) -> Result<Self, Error> {
         let init_epoch = 0;
         let operator = Operator::new(ethereum_l1.clone(), init_epoch)?;
+        let preconfirmation_store = PreconfirmationStore::open(&settings.data_dir)?;
+        let slashing_protection = SlashingProtection::open(&settings.data_dir)?;
+        let stored = preconfirmation_store.load()?;
+        info!(
+            "Restored {} preconfirmed blocks and {} pending proposal txs from the store",
+            stored.preconfirmed_blocks.len(),
+            stored.preconfirmation_txs.len()
+        );
+        let tx_outcomes_rx = ethereum_l1.execution_layer.subscribe_to_tx_outcomes();
+        let preconfirmed_blocks = Arc::new(Mutex::new(stored.preconfirmed_blocks));
+        let mut equivocations: HashMap<u64, Vec<Equivocation>> = HashMap::new();
+        for equivocation in stored.equivocations {
+            equivocations
+                .entry(equivocation.block_height)
+                .or_default()
+                .push(equivocation);
+        }
+        let equivocations = Arc::new(Mutex::new(equivocations));
+        let preconf_api = PreconfApi::new(
+            preconfirmed_blocks.clone(),
+            equivocations.clone(),
+            taiko.chain_id,
+        );
+        preconf_api.index_preconfirmed_blocks().await;
         Ok(Self {
             taiko,
-            node_block_proposed_rx: Some(node_rx),
-            node_to_p2p_tx,
-            p2p_to_node_rx: Some(p2p_to_node_rx),
+            node_to_p2p_tx: channels.node_to_p2p_tx.clone(),
+            channels: Some(channels),
             ethereum_l1,
             mev_boost,
             epoch: init_epoch,
-            l2_slot_duration_sec,
-            preconfirmed_blocks: Arc::new(Mutex::new(HashMap::new())),
+            l2_slot_duration_sec: settings.l2_slot_duration_sec,
+            preconfirmed_blocks,
+            equivocations,
             is_preconfer_now: Arc::new(AtomicBool::new(false)),
-            preconfirmation_txs: Arc::new(Mutex::new(HashMap::new())),
+            preconfirmation_txs: Arc::new(Mutex::new(stored.preconfirmation_txs)),
+            preconfirmation_store: Arc::new(Mutex::new(preconfirmation_store)),
+            slashing_protection,
+            tx_outcomes_rx: Some(tx_outcomes_rx),
+            failed_proposals: Arc::new(Mutex::new(Vec::new())),
+            proposal_retries: Arc::new(Mutex::new(HashMap::new())),
+            fallback_push_pending: Arc::new(AtomicBool::new(false)),
             operator,
             preconfirmation_helper: PreconfirmationHelper::new(),
-            bls_service,
+            bls_signer,
+            preconf_api,
+            preconf_api_addr: settings.preconf_api_addr,
+            require_registered_author: settings.require_registered_author,
+            dispute_scheduler,
         })
     }
 
-    /// Consumes the Node and starts two loops:
-    /// one for handling incoming messages and one for the block preconfirmation
+    /// Consumes the Node and starts three loops: one for handling incoming messages,
+    /// one for the outcomes of the sent L1 transactions and one for the block preconfirmation
     pub async fn entrypoint(mut self) -> Result<(), Error> {
         info!("Starting node");
+        if let Some(addr) = self.preconf_api_addr {
+            // the server stops when its handle is dropped
+            let handle = self.preconf_api.clone().start(addr).await?;
+            tokio::spawn(handle.stopped());
+        }
         self.start_new_msg_receiver_thread();
+        self.start_tx_outcome_receiver_thread();
         self.preconfirmation_loop().await;
         Ok(())
     }
 
     fn start_new_msg_receiver_thread(&mut self) {
-        let preconfirmed_blocks = self.preconfirmed_blocks.clone();
+        let stores = SharedStores {
+            preconfirmed_blocks: self.preconfirmed_blocks.clone(),
+            equivocations: self.equivocations.clone(),
+            preconfirmation_txs: self.preconfirmation_txs.clone(),
+            preconfirmation_store: self.preconfirmation_store.clone(),
+            preconf_api: self.preconf_api.clone(),
+        };
         let ethereum_l1 = self.ethereum_l1.clone();
         let taiko = self.taiko.clone();
         let is_preconfer_now = self.is_preconfer_now.clone();
-        let preconfirmation_txs = self.preconfirmation_txs.clone();
-        if let (Some(node_rx), Some(p2p_to_node_rx)) = (
-            self.node_block_proposed_rx.take(),
-            self.p2p_to_node_rx.take(),
-        ) {
+        let require_registered_author = self.require_registered_author;
+        let dispute_scheduler = self.dispute_scheduler.clone();
+        if let Some(channels) = self.channels.take() {
             tokio::spawn(async move {
                 Self::handle_incoming_messages(
-                    node_rx,
-                    p2p_to_node_rx,
-                    preconfirmed_blocks,
+                    channels,
+                    stores,
                     ethereum_l1,
                     taiko,
                     is_preconfer_now,
+                    require_registered_author,
+                    dispute_scheduler,
+                )
+                .await;
+            });
+        } else {
+            error!("The node channels have already been moved");
+        }
+    }
+
+    fn start_tx_outcome_receiver_thread(&mut self) {
+        let preconfirmation_txs = self.preconfirmation_txs.clone();
+        let preconfirmation_store = self.preconfirmation_store.clone();
+        let failed_proposals = self.failed_proposals.clone();
+        let proposal_retries = self.proposal_retries.clone();
+        let fallback_push_pending = self.fallback_push_pending.clone();
+        if let Some(tx_outcomes_rx) = self.tx_outcomes_rx.take() {
+            tokio::spawn(async move {
+                Self::handle_tx_outcomes(
+                    tx_outcomes_rx,
                     preconfirmation_txs,
+                    preconfirmation_store,
+                    failed_proposals,
+                    proposal_retries,
+                    fallback_push_pending,
                 )
                 .await;
             });
         } else {
-            error!("Some of the node_rx, p2p_to_node_rx, or lookahead_updated_rx has already been moved");
+            error!("tx_outcomes_rx has already been moved");
+        }
+    }
+
+    async fn handle_tx_outcomes(
+        mut tx_outcomes_rx: broadcast::Receiver<TxOutcome>,
+        preconfirmation_txs: PreconfirmationTxs,
+        preconfirmation_store: SharedPreconfirmationStore,
+        failed_proposals: FailedProposals,
+        proposal_retries: ProposalRetries,
+        fallback_push_pending: Arc<AtomicBool>,
+    ) {
+        loop {
+            let outcome = match tx_outcomes_rx.recv().await {
+                Ok(outcome) => outcome,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Missed {} transaction outcomes", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => {
+                    error!("Transaction outcomes channel closed");
+                    return;
+                }
+            };
+
+            if outcome.kind == TxKind::ForcePushLookahead {
+                if !matches!(outcome.status, TxStatus::Included { .. }) {
+                    fallback_push_pending.store(false, Ordering::Release);
+                }
+                continue;
+            }
+            let TxKind::ProposeBlock { block_id, tx_list } = outcome.kind else {
+                continue;
+            };
+            match outcome.status {
+                TxStatus::Included { .. } => {}
+                TxStatus::Finalized { block_number } => {
+                    debug!(
+                        "Proposal of block {} finalized in L1 block {}",
+                        block_id, block_number
+                    );
+                    Self::remove_proposal_tx(
+                        &preconfirmation_txs,
+                        &preconfirmation_store,
+                        block_id,
+                    )
+                    .await;
+                    proposal_retries.lock().await.remove(&block_id);
+                }
+                TxStatus::Reverted { .. } | TxStatus::Dropped => {
+                    // the signed transaction can't be included anymore, propose it again
+                    let nonce = (outcome.status == TxStatus::Dropped).then_some(outcome.nonce);
+                    info!(
+                        "Proposal of block {} failed with {}, queued for retry",
+                        block_id, outcome.tx_hash
+                    );
+                    Self::remove_proposal_tx(
+                        &preconfirmation_txs,
+                        &preconfirmation_store,
+                        block_id,
+                    )
+                    .await;
+                    failed_proposals.lock().await.push(FailedProposal {
+                        block_id,
+                        tx_list,
+                        nonce,
+                    });
+                }
+                TxStatus::TimedOut => {
+                    // the signed transaction is still valid, it stays among the preconfirmation
+                    // txs which are force included through MEV-Boost in the proposer slot
+                    warn!(
+                        "Proposal of block {} ({}) not included, leaving it to MEV-Boost force inclusion",
+                        block_id, outcome.tx_hash
+                    );
+                    proposal_retries.lock().await.remove(&block_id);
+                }
+            }
+        }
+    }
+
+    async fn remove_proposal_tx(
+        preconfirmation_txs: &PreconfirmationTxs,
+        preconfirmation_store: &SharedPreconfirmationStore,
+        block_id: u64,
+    ) {
+        preconfirmation_txs.lock().await.remove(&block_id);
+        if let Err(e) = preconfirmation_store
+            .lock()
+            .await
+            .remove_proposal_tx(block_id)
+        {
+            error!("Failed to remove proposal tx from the store: {}", e);
         }
     }
 
     async fn handle_incoming_messages(
-        mut node_rx: Receiver<BlockProposed>,
-        mut p2p_to_node_rx: Receiver<Vec<u8>>,
-        preconfirmed_blocks: PreconfirmedBlocks,
+        channels: NodeChannels,
+        stores: SharedStores,
         ethereum_l1: Arc<EthereumL1>,
         taiko: Arc<Taiko>,
         is_preconfer_now: Arc<AtomicBool>,
-        preconfirmation_txs: Arc<Mutex<HashMap<u64, Vec<u8>>>>,
+        require_registered_author: bool,
+        dispute_scheduler: DisputeScheduler,
     ) {
+        let NodeChannels {
+            block_proposed_rx: mut node_rx,
+            mut p2p_to_node_rx,
+            p2p_validation_tx,
+            p2p_report_tx,
+            p2p_sync,
+            ..
+        } = channels;
+        let SyncHandle {
+            requests_tx: sync_requests_tx,
+            results_rx: mut sync_results_rx,
+            inbound_rx: mut sync_inbound_rx,
+        } = p2p_sync;
+        let SharedStores {
+            preconfirmed_blocks,
+            equivocations,
+            preconfirmation_txs,
+            preconfirmation_store,
+            preconf_api,
+        } = stores;
+        // The stored preconfirmations can be far behind after a downtime, the blocks proposed on
+        // L1 meanwhile are not synced from the peers
+        let last_proposed_block = match ethereum_l1
+            .execution_layer
+            .get_last_proposed_block_id()
+            .await
+        {
+            Ok(block_id) => Some(block_id),
+            Err(e) => {
+                error!("Failed to get the last proposed block: {}", e);
+                None
+            }
+        };
+        let mut sync =
+            PreconfirmationSync::new(sync_requests_tx, p2p_report_tx, last_proposed_block);
+        let mut dispute_windows = DisputeWindows::default();
         loop {
             tokio::select! {
                 Some(block_proposed) = node_rx.recv() => {
                     if !is_preconfer_now.load(Ordering::Acquire) {
                         debug!("Node received block proposed event: {:?}", block_proposed.block_id());
-                        if let Err(e) = Self::check_preconfirmed_blocks_correctness(&preconfirmed_blocks, taiko.chain_id, &block_proposed, ethereum_l1.clone()).await {
-                            error!("Failed to check preconfirmed blocks correctness: {}", e);
-                        }
-                        if let Err(e) = Self::clean_old_blocks(&preconfirmed_blocks, block_proposed.block_id()).await {
+                        Self::check_preconfirmed_blocks_correctness(&preconfirmed_blocks, &equivocations, taiko.chain_id, &block_proposed, &dispute_scheduler).await;
+                        if let Err(e) = Self::clean_old_blocks(&preconfirmed_blocks, &preconfirmation_store, &preconf_api, block_proposed.block_id()).await {
                             error!("Failed to clean old blocks: {}", e);
                         }
+                        Self::clean_old_equivocations(&mut dispute_windows, &block_proposed, &equivocations, &preconfirmation_store, &ethereum_l1).await;
+                        let last_block = Self::last_preconfirmed_block(&preconfirmed_blocks).await;
+                        let released = sync.on_block_proposed(block_proposed.block_id(), last_block);
+                        if let Some(new_last_block) = released.last().map(|msg| msg.block_height) {
+                            info!("Block {} proposed on L1, applying the held preconfirmations up to {}", block_proposed.block_id(), new_last_block);
+                            for msg in released {
+                                Self::advance_l2_head(msg, &preconfirmed_blocks, &equivocations, &preconfirmation_store, &preconf_api, taiko.clone()).await;
+                            }
+                            sync.request_missing(new_last_block);
+                        }
                     } else {
                         debug!("Node is Preconfer and received block proposed event: {:?}", block_proposed.block_id());
-                        preconfirmation_txs.lock().await.remove(&block_proposed.block_id());
+                        Self::remove_proposal_tx(&preconfirmation_txs, &preconfirmation_store, block_proposed.block_id()).await;
                     }
                 },
                 Some(p2p_message) = p2p_to_node_rx.recv() => {
-                    if !is_preconfer_now.load(Ordering::Acquire) {
-                        debug!("Received Message from p2p!");
-                        let msg: PreconfirmationMessage = p2p_message.into();
-                        Self::advance_l2_head(msg, &preconfirmed_blocks, ethereum_l1.clone(), taiko.clone()).await;
-                    } else {
-                        debug!("Node is Preconfer and received message from p2p: {:?}", p2p_message);
+                    if p2p_message.topic == Topic::Preconfirmations {
+                        metrics::PRECONFIRMATIONS_RECEIVED.inc();
                     }
+                    let acceptance = match Self::decode_p2p_message(&p2p_message) {
+                        Ok(WireMessage::Preconfirmation(preconfirmation)) => {
+                            if is_preconfer_now.load(Ordering::Acquire) {
+                                debug!("Node is Preconfer and received message {} from p2p", p2p_message.id);
+                                MessageAcceptance::Ignore
+                            } else {
+                                debug!("Received Message from p2p!");
+                                match Self::validate_preconfirmation(preconfirmation, &p2p_message, require_registered_author, ethereum_l1.clone(), taiko.chain_id).await {
+                                    Ok(msg) => {
+                                        Self::report_validation(&p2p_validation_tx, &p2p_message, MessageAcceptance::Accept);
+                                        let last_block = sync.last_block(Self::last_preconfirmed_block(&preconfirmed_blocks).await);
+                                        if let Some(msg) = sync.on_new_preconfirmation(msg, last_block) {
+                                            let block_height = msg.block_height;
+                                            Self::advance_l2_head(msg, &preconfirmed_blocks, &equivocations, &preconfirmation_store, &preconf_api, taiko.clone()).await;
+                                            for msg in sync.take_ready(block_height) {
+                                                Self::advance_l2_head(msg, &preconfirmed_blocks, &equivocations, &preconfirmation_store, &preconf_api, taiko.clone()).await;
+                                            }
+                                        }
+                                        continue;
+                                    }
+                                    Err(acceptance) => acceptance,
+                                }
+                            }
+                        }
+                        Ok(WireMessage::LookaheadAnnouncement(announcement)) => {
+                            Self::validate_lookahead_announcement(announcement, &ethereum_l1, taiko.chain_id).await
+                        }
+                        Ok(WireMessage::PreconferHandover(handover)) => {
+                            Self::validate_preconfer_handover(handover, &ethereum_l1, taiko.chain_id).await
+                        }
+                        Err(acceptance) => acceptance,
+                    };
+                    Self::report_validation(&p2p_validation_tx, &p2p_message, acceptance);
+                }
+                Some(result) = sync_results_rx.recv() => {
+                    sync.request_done();
+                    Self::apply_synced_preconfirmations(result, &mut sync, &preconfirmed_blocks, &equivocations, &preconfirmation_store, &preconf_api, &ethereum_l1, taiko.clone(), require_registered_author).await;
+                }
+                Some(inbound) = sync_inbound_rx.recv() => {
+                    debug!("Serving blocks {}..={} to {}", inbound.request.from_block, inbound.request.to_block, inbound.peer);
+                    let response = PreconfirmationSync::serve(&preconfirmed_blocks, inbound.request).await;
+                    // the peer may be gone already
+                    let _ = inbound.response.send(response);
                 }
             }
         }
@@ -163,7 +485,7 @@ impl Node {
     async fn is_valid_preconfer(
         ethereum_l1: Arc<EthereumL1>,
         preconfer: PreconferAddress,
-    ) -> Result<(), Error> {
+    ) -> Result<bool, Error> {
         // get current lookahead
         let epoch = ethereum_l1.slot_clock.get_current_epoch()?;
 
@@ -177,94 +499,546 @@ impl Node {
         debug!("slot_of_epoch: {}", slot_of_epoch);
 
         // get current preconfer
-        if current_lookahead[slot_of_epoch as usize] == preconfer {
-            Ok(())
-        } else {
-            Err(anyhow::anyhow!(
-                "is_valid_preconfer: P2P message Preconfer is not equal to current preconfer"
-            ))
+        Ok(current_lookahead.get(slot_of_epoch as usize) == Some(&preconfer))
+    }
+
+    fn reject_preconfirmation(reason: &str) -> MessageAcceptance {
+        metrics::PRECONFIRMATIONS_REJECTED
+            .with_label_values(&[reason])
+            .inc();
+        MessageAcceptance::Reject
+    }
+
+    // Messages of a newer protocol version are ignored so that they are neither handled nor held
+    // against the peers which forward them.
+    fn decode_p2p_message(message: &GossipMessage) -> Result<WireMessage, MessageAcceptance> {
+        let reject = || {
+            if message.topic == Topic::Preconfirmations {
+                Self::reject_preconfirmation("invalid_encoding")
+            } else {
+                MessageAcceptance::Reject
+            }
+        };
+        match WireMessage::from_bytes(&message.data) {
+            Ok(decoded) if decoded.topic() == message.topic => Ok(decoded),
+            Ok(decoded) => {
+                warn!(
+                    "Message {} of topic {} received on topic {}",
+                    message.id,
+                    decoded.topic().name(),
+                    message.topic.name()
+                );
+                Err(reject())
+            }
+            Err(e @ WireError::UnsupportedVersion(_)) => {
+                debug!("Ignoring message {}: {}", message.id, e);
+                Err(MessageAcceptance::Ignore)
+            }
+            Err(e) => {
+                warn!("Message {} from {}: {}", message.id, message.source, e);
+                Err(reject())
+            }
         }
     }
 
-    async fn advance_l2_head(
-        msg: PreconfirmationMessage,
-        preconfirmed_blocks: &PreconfirmedBlocks,
+    // Decides whether a gossip message is forwarded to the other peers. Invalid messages are
+    // rejected, lowering the score of the sender, while the ones that can't be checked because
+    // of a local failure are ignored. If `require_registered_author` is set, the message has to be
+    // signed by the p2p identity of the preconfer, which has to be registered.
+    async fn validate_preconfirmation(
+        preconfirmation: wire::Preconfirmation,
+        message: &GossipMessage,
+        require_registered_author: bool,
         ethereum_l1: Arc<EthereumL1>,
-        taiko: Arc<Taiko>,
-    ) {
+        chain_id: u64,
+    ) -> Result<PreconfirmationMessage, MessageAcceptance> {
+        let reject = Self::reject_preconfirmation;
+
+        let msg = PreconfirmationMessage::try_from(preconfirmation).map_err(|e| {
+            warn!("{}", e);
+            reject("invalid_encoding")
+        })?;
+
+        let preconfer = Self::verify_commitment(&msg, &ethereum_l1, chain_id).map_err(reject)?;
+
+        if require_registered_author {
+            let author = message
+                .author
+                .as_ref()
+                .and_then(crate::p2p_network::author_address);
+            if author != Some(preconfer) {
+                warn!(
+                    "Preconfirmation of block_id: {} signed by {} was published by {:?}",
+                    msg.block_height, preconfer, message.author
+                );
+                return Err(reject("invalid_author"));
+            }
+            match ethereum_l1
+                .execution_layer
+                .is_preconfer_registered(preconfer)
+                .await
+            {
+                Ok(true) => {}
+                // Our view of the registry may lag behind, so the peer is not penalized
+                Ok(false) => {
+                    warn!(
+                        "Preconfirmation of block_id: {} published by unregistered preconfer {}",
+                        msg.block_height, preconfer
+                    );
+                    return Err(MessageAcceptance::Ignore);
+                }
+                Err(e) => {
+                    error!("Failed to check the registration of {}: {}", preconfer, e);
+                    return Err(MessageAcceptance::Ignore);
+                }
+            }
+        }
+
+        // check valid preconfer address, the lookahead may differ around the slot boundaries and
+        // while it is being updated, so the peers forwarding the message are not penalized
+        match Self::is_valid_preconfer(ethereum_l1, preconfer.into()).await {
+            Ok(true) => Ok(msg),
+            Ok(false) => {
+                warn!(
+                    "Preconfirmation of block_id: {} signed by {} who is not the current preconfer",
+                    msg.block_height, preconfer
+                );
+                Err(MessageAcceptance::Ignore)
+            }
+            Err(e) => {
+                error!(
+                    "Failed to get the current preconfer: {} for block_id: {}",
+                    e, msg.block_height
+                );
+                Err(MessageAcceptance::Ignore)
+            }
+        }
+    }
+
+    // Checks the commit hash of the preconfirmation and returns the preconfer which signed it,
+    // or the reason why it is invalid.
+    fn verify_commitment(
+        msg: &PreconfirmationMessage,
+        ethereum_l1: &EthereumL1,
+        chain_id: u64,
+    ) -> Result<alloy::primitives::Address, &'static str> {
         // check hash
         let tx_list_commit =
-            L2TxListsCommit::from_preconf(msg.block_height, msg.tx_list_hash, taiko.chain_id);
+            L2TxListsCommit::from_preconf(msg.block_height, msg.tx_list_hash, chain_id);
         debug!("Match txListCommit");
-        match tx_list_commit.hash() {
-            Ok(hash) => {
-                if hash == msg.proof.commit_hash {
-                    // check signature
-                    match ethereum_l1
-                        .execution_layer
-                        .recover_address_from_msg(&msg.proof.commit_hash, &msg.proof.signature)
-                    {
-                        Ok(preconfer) => {
-                            // check valid preconfer address
-                            if let Err(e) =
-                                Self::is_valid_preconfer(ethereum_l1.clone(), preconfer.into())
-                                    .await
-                            {
-                                error!("Error: {} for block_id: {}", e, msg.block_height);
-                                return;
-                            }
-                            // Add to preconfirmation map
-                            preconfirmed_blocks
-                                .lock()
-                                .await
-                                .insert(msg.block_height, msg.clone());
-                            // Advance head
-                            if let Err(e) = taiko.advance_head_to_new_l2_block(msg.tx_lists).await {
-                                error!(
-                                    "Failed to advance head: {} for block_id: {}",
-                                    e, msg.block_height
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            error!(
-                                "Failed to check signature: {} for block_id: {}",
-                                e, msg.block_height
-                            );
-                        }
+        let hash = tx_list_commit.hash().map_err(|e| {
+            warn!("Failed to calculate hash: {}", e);
+            "invalid_commit"
+        })?;
+        if hash != msg.proof.commit_hash {
+            warn!(
+                "Preconfirmation hash is not correct for block_id: {}",
+                msg.block_height
+            );
+            return Err("invalid_commit_hash");
+        }
+
+        // check signature
+        ethereum_l1
+            .execution_layer
+            .recover_address_from_msg(&msg.proof.commit_hash, &msg.proof.signature)
+            .map_err(|e| {
+                warn!(
+                    "Failed to check signature: {} for block_id: {}",
+                    e, msg.block_height
+                );
+                "invalid_signature"
+            })
+    }
+
+    // A synced preconfirmation which fails these checks was forged or corrupted by the peer
+    fn decode_synced_preconfirmation(
+        bytes: &[u8],
+        ethereum_l1: &EthereumL1,
+        chain_id: u64,
+    ) -> Result<(PreconfirmationMessage, alloy::primitives::Address), Error> {
+        let WireMessage::Preconfirmation(preconfirmation) = WireMessage::from_bytes(bytes)? else {
+            return Err(anyhow::anyhow!("not a preconfirmation"));
+        };
+        let msg = PreconfirmationMessage::try_from(preconfirmation)?;
+        let preconfer = Self::verify_commitment(&msg, ethereum_l1, chain_id)
+            .map_err(|reason| anyhow::anyhow!("block {}: {}", msg.block_height, reason))?;
+        Ok((msg, preconfer))
+    }
+
+    // Synced preconfirmations may be from earlier slots, so their preconfer is looked for in the
+    // lookaheads of the current and previous epochs rather than in the current slot.
+    async fn is_recent_preconfer(
+        ethereum_l1: &EthereumL1,
+        preconfer: alloy::primitives::Address,
+    ) -> Result<bool, Error> {
+        let epoch = ethereum_l1.slot_clock.get_current_epoch()?;
+        for epoch in [epoch, epoch.saturating_sub(1)] {
+            let lookahead = ethereum_l1
+                .execution_layer
+                .get_lookahead_preconfer_addresses_for_epoch(epoch)
+                .await?;
+            if lookahead.contains(&preconfer.into_array()) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn last_preconfirmed_block(preconfirmed_blocks: &PreconfirmedBlocks) -> Option<u64> {
+        preconfirmed_blocks.lock().await.keys().max().copied()
+    }
+
+    // Applies the synced preconfirmations which fill the hole before the held ones. More blocks
+    // are requested as long as the responses make progress, otherwise the next gossiped
+    // preconfirmation triggers a new request, until the hole is skipped after too many failures.
+    #[allow(clippy::too_many_arguments)]
+    async fn apply_synced_preconfirmations(
+        result: SyncResult,
+        sync: &mut PreconfirmationSync,
+        preconfirmed_blocks: &PreconfirmedBlocks,
+        equivocations: &Equivocations,
+        preconfirmation_store: &SharedPreconfirmationStore,
+        preconf_api: &PreconfApi,
+        ethereum_l1: &EthereumL1,
+        taiko: Arc<Taiko>,
+        require_registered_author: bool,
+    ) {
+        let response = match result.response {
+            Ok(response) => response,
+            Err(e) => {
+                warn!(
+                    "Failed to sync blocks {}..={} from {:?}: {}",
+                    result.request.from_block, result.request.to_block, result.peer, e
+                );
+                SyncResponse::default()
+            }
+        };
+        let Some(last_block) =
+            sync.last_block(Self::last_preconfirmed_block(preconfirmed_blocks).await)
+        else {
+            return;
+        };
+        let mut invalid = false;
+        for bytes in response.messages {
+            let (msg, preconfer) =
+                match Self::decode_synced_preconfirmation(&bytes, ethereum_l1, taiko.chain_id) {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        warn!(
+                            "Invalid synced preconfirmation from {:?}: {}",
+                            result.peer, e
+                        );
+                        invalid = true;
+                        continue;
                     }
-                } else {
-                    tracing::warn!(
-                        "Preconfirmatoin hash is not correct for block_id: {}",
-                        msg.block_height
+                };
+            match Self::is_recent_preconfer(ethereum_l1, preconfer).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    warn!(
+                        "Synced preconfirmation of block {} signed by {}, not a recent preconfer",
+                        msg.block_height, preconfer
                     );
+                    continue;
+                }
+                Err(e) => {
+                    error!("Failed to check the preconfer of a synced block: {}", e);
+                    continue;
+                }
+            }
+            // A synced preconfirmation is sent by the peer which holds it rather than by its
+            // author, only the registration of the preconfer which signed it can be checked
+            if require_registered_author {
+                match ethereum_l1
+                    .execution_layer
+                    .is_preconfer_registered(preconfer)
+                    .await
+                {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        warn!(
+                            "Synced preconfirmation of block {} signed by unregistered preconfer {}",
+                            msg.block_height, preconfer
+                        );
+                        continue;
+                    }
+                    Err(e) => {
+                        error!("Failed to check the registration of {}: {}", preconfer, e);
+                        continue;
+                    }
                 }
             }
+            sync.hold(msg, last_block);
+        }
+        // a single report per response, the whole response comes from the same peer
+        if let (true, Some(peer)) = (invalid, result.peer) {
+            sync.report_invalid_response(peer);
+        }
+
+        let mut ready = sync.take_ready(last_block);
+        if let Some(msg) = ready.last() {
+            info!(
+                "Synced blocks {}..={} from {:?}",
+                last_block + 1,
+                msg.block_height,
+                result.peer
+            );
+        } else {
+            warn!(
+                "No preconfirmation of blocks {}..={} received from {:?}",
+                result.request.from_block, result.request.to_block, result.peer
+            );
+            ready = sync.request_failed();
+        }
+        let Some(new_last_block) = ready.last().map(|msg| msg.block_height) else {
+            return;
+        };
+        for msg in ready {
+            Self::advance_l2_head(
+                msg,
+                preconfirmed_blocks,
+                equivocations,
+                preconfirmation_store,
+                preconf_api,
+                taiko.clone(),
+            )
+            .await;
+        }
+        sync.request_missing(new_last_block);
+    }
+
+    fn recover_signer(
+        ethereum_l1: &EthereumL1,
+        message: &impl Signed,
+    ) -> Result<PreconferAddress, Error> {
+        let signer = ethereum_l1
+            .execution_layer
+            .recover_address_from_msg(&message.signed_data(), &message.signature().0)?;
+        Ok(signer.into_array())
+    }
+
+    // An announced lookahead is accepted if it was signed by one of its preconfers and matches
+    // the lookahead of the PreconfTaskManager contract. A lookahead that doesn't match is only
+    // ignored, the view of the contract can differ from one node to another around a reorg or
+    // a lookahead update, and the sender isn't penalized for it.
+    async fn validate_lookahead_announcement(
+        announcement: LookaheadAnnouncement,
+        ethereum_l1: &EthereumL1,
+        chain_id: u64,
+    ) -> MessageAcceptance {
+        if announcement.chain_id != chain_id {
+            warn!("Lookahead announcement for chain {}", announcement.chain_id);
+            return MessageAcceptance::Reject;
+        }
+        let preconfers: Vec<PreconferAddress> =
+            announcement.preconfers.iter().map(|p| p.0).collect();
+        match Self::recover_signer(ethereum_l1, &announcement) {
+            Ok(signer) if preconfers.contains(&signer) => {}
+            _ => {
+                warn!(
+                    "Lookahead announcement of epoch {} not signed by one of its preconfers",
+                    announcement.epoch
+                );
+                return MessageAcceptance::Reject;
+            }
+        }
+
+        match ethereum_l1
+            .execution_layer
+            .get_lookahead_preconfer_addresses_for_epoch(announcement.epoch)
+            .await
+        {
+            Ok(lookahead) if lookahead == preconfers => {
+                debug!("Valid lookahead announced for epoch {}", announcement.epoch);
+                MessageAcceptance::Accept
+            }
+            Ok(_) => {
+                warn!(
+                    "Announced lookahead of epoch {} doesn't match the contract",
+                    announcement.epoch
+                );
+                MessageAcceptance::Ignore
+            }
+            Err(e) => {
+                error!(
+                    "Failed to get the lookahead of epoch {}: {}",
+                    announcement.epoch, e
+                );
+                MessageAcceptance::Ignore
+            }
+        }
+    }
+
+    // A handover is accepted if it was signed by the preconfer of its slot. As for the lookahead
+    // announcements, a signer that isn't the preconfer in the local lookahead is only ignored.
+    async fn validate_preconfer_handover(
+        handover: PreconferHandover,
+        ethereum_l1: &EthereumL1,
+        chain_id: u64,
+    ) -> MessageAcceptance {
+        if handover.chain_id != chain_id {
+            warn!("Preconfer handover for chain {}", handover.chain_id);
+            return MessageAcceptance::Reject;
+        }
+        let Ok(signer) = Self::recover_signer(ethereum_l1, &handover) else {
+            warn!(
+                "Invalid signature of the handover of slot {}",
+                handover.slot
+            );
+            return MessageAcceptance::Reject;
+        };
+
+        let slots_per_epoch = ethereum_l1.slot_clock.get_slots_per_epoch();
+        match ethereum_l1
+            .execution_layer
+            .get_lookahead_preconfer_addresses_for_epoch(handover.slot / slots_per_epoch)
+            .await
+        {
+            Ok(lookahead)
+                if lookahead.get((handover.slot % slots_per_epoch) as usize) == Some(&signer) =>
+            {
+                info!(
+                    "Preconfer {} handed over after block {} to {}",
+                    hex::encode(signer),
+                    handover.last_block_id,
+                    hex::encode(handover.next_preconfer.0)
+                );
+                MessageAcceptance::Accept
+            }
+            Ok(_) => {
+                warn!(
+                    "Handover of slot {} not signed by its preconfer",
+                    handover.slot
+                );
+                MessageAcceptance::Ignore
+            }
             Err(e) => {
-                tracing::warn!("Failed to calculate hash: {}", e);
+                error!(
+                    "Failed to get the lookahead of slot {}: {}",
+                    handover.slot, e
+                );
+                MessageAcceptance::Ignore
             }
         }
     }
 
+    fn report_validation(
+        p2p_validation_tx: &Sender<ValidationResult>,
+        message: &GossipMessage,
+        acceptance: MessageAcceptance,
+    ) {
+        if let Err(err) = p2p_validation_tx.try_send(message.validation(acceptance)) {
+            error!("Failed to report the validation of a p2p message: {}", err);
+        }
+    }
+
+    async fn advance_l2_head(
+        msg: PreconfirmationMessage,
+        preconfirmed_blocks: &PreconfirmedBlocks,
+        equivocations: &Equivocations,
+        preconfirmation_store: &SharedPreconfirmationStore,
+        preconf_api: &PreconfApi,
+        taiko: Arc<Taiko>,
+    ) {
+        // Add to preconfirmation map. A conflicting commitment of the same preconfer is only
+        // kept as evidence, the L2 head stays on the first one.
+        let mut blocks = preconfirmed_blocks.lock().await;
+        let equivocation = blocks
+            .get(&msg.block_height)
+            .and_then(|known| Equivocation::detect(known, &msg));
+        if let Some(equivocation) = equivocation {
+            drop(blocks);
+            Self::record_equivocation(equivocation, equivocations, preconfirmation_store).await;
+            return;
+        }
+        blocks.insert(msg.block_height, msg.clone());
+        drop(blocks);
+        if let Err(e) = preconfirmation_store
+            .lock()
+            .await
+            .insert_preconfirmation(&msg)
+        {
+            error!(
+                "Failed to persist preconfirmation: {} for block_id: {}",
+                e, msg.block_height
+            );
+        }
+        preconf_api.publish(&msg).await;
+        // Advance head
+        if let Err(e) = Self::advance_head(&taiko, msg.tx_lists, "p2p").await {
+            error!(
+                "Failed to advance head: {} for block_id: {}",
+                e, msg.block_height
+            );
+        }
+    }
+
+    async fn record_equivocation(
+        equivocation: Equivocation,
+        equivocations: &Equivocations,
+        preconfirmation_store: &SharedPreconfirmationStore,
+    ) {
+        if !equivocation::record(equivocations, equivocation.clone()).await {
+            return;
+        }
+        if let Err(e) = preconfirmation_store
+            .lock()
+            .await
+            .insert_equivocation(&equivocation)
+        {
+            error!(
+                "Failed to persist equivocation: {} for block_id: {}",
+                e, equivocation.block_height
+            );
+        }
+    }
+
+    async fn advance_head(taiko: &Taiko, tx_lists: Value, source: &str) -> Result<(), Error> {
+        let start = Instant::now();
+        let result = taiko.advance_head_to_new_l2_block(tx_lists).await;
+        metrics::ADVANCE_HEAD_DURATION
+            .with_label_values(&[source, metrics::result_label(&result)])
+            .observe(start.elapsed().as_secs_f64());
+        result.map(|_| ())
+    }
+
+    // Besides the preconfirmation of the block, both commitments of an equivocation are checked:
+    // the one which doesn't match the proposed block is provably incorrect. The disputes are
+    // simulated and sent by the scheduler within the dispute window.
     async fn check_preconfirmed_blocks_correctness(
         preconfirmed_blocks: &PreconfirmedBlocks,
+        equivocations: &Equivocations,
         chain_id: u64,
         block_proposed: &BlockProposed,
-        ethereum_l1: Arc<EthereumL1>,
-    ) -> Result<(), Error> {
-        let preconfirmed_blocks = preconfirmed_blocks.lock().await;
-        if let Some(preconf_block) = preconfirmed_blocks.get(&block_proposed.block_id()) {
-            ethereum_l1
-                .execution_layer
-                .check_and_prove_incorrect_preconfirmation(
-                    chain_id,
-                    preconf_block.tx_list_hash,
-                    preconf_block.proof.signature,
-                    block_proposed,
-                )
-                .await?;
+        dispute_scheduler: &DisputeScheduler,
+    ) {
+        let block_id = block_proposed.block_id();
+        let mut commitments: Vec<PreconfirmationMessage> = preconfirmed_blocks
+            .lock()
+            .await
+            .get(&block_id)
+            .into_iter()
+            .cloned()
+            .collect();
+        if let Some(equivocations) = equivocations.lock().await.get(&block_id) {
+            for msg in equivocations.iter().flat_map(Equivocation::commitments) {
+                if !commitments
+                    .iter()
+                    .any(|known| known.tx_list_hash == msg.tx_list_hash)
+                {
+                    commitments.push(msg.clone());
+                }
+            }
+        }
+        for preconf_block in commitments {
+            dispute_scheduler.schedule(Dispute::Preconfirmation {
+                chain_id,
+                tx_list_hash: preconf_block.tx_list_hash,
+                signature: preconf_block.proof.signature,
+                block_proposed: Box::new(block_proposed.clone()),
+            });
         }
-        Ok(())
     }
 
     async fn preconfirmation_loop(&mut self) {
@@ -309,8 +1083,19 @@ impl Node {
         }
 
         let current_slot = self.ethereum_l1.slot_clock.get_current_slot()?;
+        metrics::L1_EPOCH.set(current_epoch as i64);
+        metrics::L1_SLOT.set(current_slot as i64);
 
-        match self.operator.get_status(current_slot).await? {
+        let status = self.operator.get_status(current_slot).await?;
+        metrics::set_operator_status(&status);
+        if let Some(preconfer) = self.operator.get_preconfer_for_slot(current_slot) {
+            self.preconf_api.set_current_preconfer(CurrentPreconfer {
+                epoch: current_epoch,
+                slot: current_slot,
+                preconfer: preconfer.into(),
+            });
+        }
+        match status {
             OperatorStatus::PreconferAndProposer => {
                 self.preconfirm_last_slot().await?;
             }
@@ -321,6 +1106,9 @@ impl Node {
                 }
                 self.preconfirm_block(true).await?;
             }
+            OperatorStatus::FallbackPreconfer => {
+                self.claim_fallback_slot(current_slot).await?;
+            }
             OperatorStatus::None => {
                 info!(
                     "Not my slot to preconfirm. Epoch {}, slot: {} ({}), L2 slot: {}",
@@ -347,6 +1135,9 @@ impl Node {
         self.operator = Operator::new(self.ethereum_l1.clone(), new_epoch)?;
         // TODO it would be better to do it 1 epoch later
         self.operator.update_preconfer_lookahead_for_epoch().await?;
+        if let Err(e) = self.announce_lookahead(new_epoch).await {
+            error!("Failed to announce the lookahead: {}", e);
+        }
         // TODO
         #[cfg(debug_assertions)]
         self.operator
@@ -356,6 +1147,40 @@ impl Node {
         Ok(())
     }
 
+    // The slot is empty and the node is the fallback preconfer of the next epoch. Pushing the
+    // lookahead of the next epoch assigns the empty slots to its first preconfer, the node when
+    // the lookahead is empty. It preconfirms them from the next slot, once the lookahead is
+    // updated.
+    async fn claim_fallback_slot(&mut self, current_slot: Slot) -> Result<(), Error> {
+        info!(
+            "Empty slot {} ({}), the node is the fallback preconfer of the next epoch",
+            current_slot,
+            self.ethereum_l1.slot_clock.slot_of_epoch(current_slot)
+        );
+        if self.fallback_push_pending.load(Ordering::Acquire) {
+            debug!("The lookahead of the next epoch is already being pushed");
+            return Ok(());
+        }
+        if let Some(lookahead_params) = self.get_lookahead_params().await? {
+            debug!(
+                "Pushing the lookahead of the next epoch, {} params",
+                lookahead_params.len()
+            );
+            // set before sending, the outcome may be published before the call returns
+            self.fallback_push_pending.store(true, Ordering::Release);
+            if let Err(e) = self
+                .ethereum_l1
+                .execution_layer
+                .force_push_lookahead(lookahead_params)
+                .await
+            {
+                self.fallback_push_pending.store(false, Ordering::Release);
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
     async fn get_lookahead_params(
         &mut self,
     ) -> Result<Option<Vec<PreconfTaskManager::LookaheadSetParam>>, Error> {
@@ -393,6 +1218,10 @@ impl Node {
             debug!("Last(4th) perconfirmation in the last L1 slot for the preconfer");
             // Last(4th) perconfirmation when we are proposer and preconfer
             self.is_preconfer_now.store(false, Ordering::Release);
+            let slot = self.ethereum_l1.slot_clock.get_current_slot()?;
+            if let Err(e) = self.hand_over(slot).await {
+                error!("Failed to announce the preconfer handover: {}", e);
+            }
 
             let mut preconfirmation_txs = self.preconfirmation_txs.lock().await;
             if !preconfirmation_txs.is_empty() {
@@ -405,11 +1234,20 @@ impl Node {
                 // Get slot_id
                 let slot_id = self.ethereum_l1.slot_clock.get_current_slot()?;
 
-                self.mev_boost
-                    .force_inclusion(constraints, slot_id, self.bls_service.clone())
-                    .await?;
+                let result = self
+                    .mev_boost
+                    .force_inclusion(constraints, slot_id, self.bls_signer.clone())
+                    .await;
+                metrics::MEV_BOOST_CONSTRAINTS
+                    .with_label_values(&[metrics::result_label(&result)])
+                    .inc();
+                result?;
 
                 preconfirmation_txs.clear();
+                self.preconfirmation_store
+                    .lock()
+                    .await
+                    .clear_proposal_txs()?;
             }
         }
 
@@ -440,9 +1278,13 @@ impl Node {
                 .get_l2_slot_number_within_l1_slot()?
         );
 
+        if send_to_contract {
+            self.retry_failed_proposals(current_slot).await?;
+        }
+
         let lookahead_params = self.get_lookahead_params().await?;
         let pending_tx_lists = self.taiko.get_pending_l2_tx_lists().await?;
-        let pending_tx_lists_bytes = if pending_tx_lists.tx_list_bytes.is_empty() {
+        if pending_tx_lists.tx_list_bytes.is_empty() {
             if let Some(lookahead_params) = lookahead_params {
                 debug!("No pending transactions to preconfirm, force pushing lookahead");
                 self.preconfirmation_helper.increment_nonce();
@@ -461,89 +1303,369 @@ impl Node {
             }
             // No transactions skip preconfirmation step
             return Ok(());
-        } else {
-            debug!(
-                "Pending {} transactions to preconfirm",
-                pending_tx_lists.tx_list_bytes.len()
-            );
-            pending_tx_lists.tx_list_bytes[0].clone() // TODO: handle multiple tx lists
-        };
+        }
 
-        let new_block_height = pending_tx_lists.parent_block_id + 1;
+        debug!(
+            "Pending {} tx lists to preconfirm",
+            pending_tx_lists.tx_list_bytes.len()
+        );
+        let tx_lists = pending_tx_lists
+            .tx_lists
+            .as_array()
+            .filter(|tx_lists| tx_lists.len() == pending_tx_lists.tx_list_bytes.len())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "preconfirm_block: tx lists don't match the {} tx list bytes",
+                    pending_tx_lists.tx_list_bytes.len()
+                )
+            })?;
 
-        let (commit_hash, signature) =
-            self.generate_commit_hash_and_signature(&pending_tx_lists, new_block_height)?;
+        let lookahead_pointer = self.operator.get_lookahead_pointer(current_slot)?;
+        // Lookahead params are sent only with the first proposal of the slot
+        let mut lookahead_params = lookahead_params.unwrap_or_default();
+        // Every tx list becomes its own L2 block on top of the parent block. A failure stops the
+        // slot, the next tx lists stay pending in the L2 mempool and are preconfirmed in the next
+        // slot, once the failed proposal is retried.
+        for (block_height, (tx_list, tx_list_bytes)) in (pending_tx_lists.parent_block_id + 1..)
+            .zip(tx_lists.iter().zip(pending_tx_lists.tx_list_bytes.iter()))
+        {
+            self.preconfirm_tx_list(
+                block_height,
+                Value::Array(vec![tx_list.clone()]),
+                tx_list_bytes,
+                lookahead_pointer,
+                std::mem::take(&mut lookahead_params),
+                send_to_contract,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn preconfirm_tx_list(
+        &mut self,
+        block_height: u64,
+        tx_lists: Value,
+        tx_list_bytes: &[u8],
+        lookahead_pointer: u64,
+        lookahead_params: Vec<PreconfTaskManager::LookaheadSetParam>,
+        send_to_contract: bool,
+    ) -> Result<(), Error> {
+        debug!("Preconfirming block {}", block_height);
+        let (commit_hash, signature) = self
+            .generate_commit_hash_and_signature(tx_list_bytes, block_height)
+            .await?;
 
         let proof = PreconfirmationProof {
             commit_hash,
             signature,
         };
-        let preconf_message = PreconfirmationMessage::new(
-            new_block_height,
-            pending_tx_lists.tx_lists.clone(),
-            &pending_tx_lists_bytes,
-            proof.clone(),
-        );
-        self.send_preconfirmations_to_the_avs_p2p(preconf_message.clone());
-        self.taiko
-            .advance_head_to_new_l2_block(pending_tx_lists.tx_lists)
-            .await?;
+        let preconf_message =
+            PreconfirmationMessage::new(block_height, tx_lists.clone(), tx_list_bytes, proof);
+        self.send_preconfirmations_to_the_avs_p2p(&preconf_message)?;
+        metrics::PRECONFIRMATIONS_PRODUCED.inc();
 
-        let lookahead_pointer = self.operator.get_lookahead_pointer(current_slot)?;
+        // The block is committed to once gossiped, it is proposed whatever fails from here
+        if let Err(e) = self
+            .preconfirmation_store
+            .lock()
+            .await
+            .insert_preconfirmation(&preconf_message)
+        {
+            error!(
+                "Failed to store the preconfirmation of block {}: {}",
+                block_height, e
+            );
+        }
+        self.preconfirmed_blocks
+            .lock()
+            .await
+            .insert(block_height, preconf_message.clone());
+        self.preconf_api.publish(&preconf_message).await;
+        let advanced = Self::advance_head(&self.taiko, tx_lists, "own").await;
+
+        if let Err(e) = self
+            .propose(
+                block_height,
+                tx_list_bytes.to_vec(),
+                None,
+                lookahead_pointer,
+                lookahead_params,
+                send_to_contract,
+            )
+            .await
+        {
+            error!(
+                "Failed to propose block {}, queued for retry: {}",
+                block_height, e
+            );
+            self.failed_proposals.lock().await.push(FailedProposal {
+                block_id: block_height,
+                tx_list: tx_list_bytes.to_vec(),
+                nonce: None,
+            });
+            return Err(e);
+        }
+        advanced
+    }
+
+    // Without a `nonce` to reuse, the next one is only used up once the proposal is built and,
+    // if it is sent, accepted by the L1 node
+    async fn propose(
+        &mut self,
+        block_id: u64,
+        tx_list: Vec<u8>,
+        nonce: Option<u64>,
+        lookahead_pointer: u64,
+        lookahead_params: Vec<PreconfTaskManager::LookaheadSetParam>,
+        send_to_contract: bool,
+    ) -> Result<(), Error> {
         let tx = self
             .ethereum_l1
             .execution_layer
             .propose_new_block(
-                self.preconfirmation_helper.get_next_nonce(),
-                pending_tx_lists_bytes,
+                block_id,
+                nonce.unwrap_or(self.preconfirmation_helper.get_nonce()),
+                tx_list,
                 lookahead_pointer,
-                lookahead_params.unwrap_or(vec![]),
+                lookahead_params,
                 send_to_contract,
             )
             .await?;
+        if nonce.is_none() {
+            self.preconfirmation_helper.increment_nonce();
+        }
+
+        self.insert_proposal_tx(block_id, tx).await
+    }
+
+    async fn retry_failed_proposals(&mut self, current_slot: Slot) -> Result<(), Error> {
+        if self.failed_proposals.lock().await.is_empty() {
+            return Ok(());
+        }
+
+        let lookahead_pointer = self.operator.get_lookahead_pointer(current_slot)?;
+        // Taiko numbers the blocks in the order they are proposed, so a tx list can only be
+        // proposed again as the preconfirmed block if it is the next block on L1
+        let mut next_block_id = self
+            .ethereum_l1
+            .execution_layer
+            .get_last_proposed_block_id()
+            .await?
+            + 1;
+        let account_nonce = self
+            .ethereum_l1
+            .execution_layer
+            .get_preconfer_nonce()
+            .await?;
+
+        let mut failed_proposals = std::mem::take(&mut *self.failed_proposals.lock().await);
+        failed_proposals.sort_by_key(|proposal| proposal.block_id);
+        let proposal_retries = self.proposal_retries.clone();
+        let mut proposal_retries = proposal_retries.lock().await;
+        let mut failed_proposals = failed_proposals.into_iter();
+        while let Some(proposal) = failed_proposals.next() {
+            let block_id = proposal.block_id;
+            if block_id < next_block_id {
+                error!(
+                    "Giving up proposing block {}, block {} is next on L1",
+                    block_id, next_block_id
+                );
+                proposal_retries.remove(&block_id);
+                continue;
+            }
+            if block_id > next_block_id {
+                debug!(
+                    "Proposal of block {} waits for block {} to be proposed",
+                    block_id, next_block_id
+                );
+                self.failed_proposals.lock().await.push(proposal);
+                continue;
+            }
+            let retries = proposal_retries.entry(block_id).or_insert(0);
+            if *retries >= MAX_PROPOSAL_RETRIES {
+                error!(
+                    "Giving up proposing block {} after {} retries",
+                    block_id, MAX_PROPOSAL_RETRIES
+                );
+                proposal_retries.remove(&block_id);
+                continue;
+            }
+            *retries += 1;
+            info!("Retrying proposal of block {} ({})", block_id, retries);
+
+            // the nonce of an evicted transaction has to be used, the next ones are stuck until
+            // it is
+            let nonce = proposal.nonce.filter(|nonce| *nonce >= account_nonce);
+            if let Err(e) = self
+                .propose(
+                    block_id,
+                    proposal.tx_list.clone(),
+                    nonce,
+                    lookahead_pointer,
+                    vec![],
+                    true,
+                )
+                .await
+            {
+                let mut queue = self.failed_proposals.lock().await;
+                queue.push(proposal);
+                queue.extend(failed_proposals);
+                return Err(e);
+            }
+            next_block_id += 1;
+        }
+
+        Ok(())
+    }
 
-        // insert transaction
-        self.preconfirmation_txs
+    async fn insert_proposal_tx(&mut self, block_id: u64, tx: Vec<u8>) -> Result<(), Error> {
+        self.preconfirmation_store
             .lock()
             .await
-            .insert(new_block_height, tx);
-
+            .insert_proposal_tx(block_id, &tx)?;
+        self.preconfirmation_txs.lock().await.insert(block_id, tx);
         Ok(())
     }
 
-    // TODO: use web3signer to sign the message
-    fn generate_commit_hash_and_signature(
-        &self,
-        reply: &RPCReplyL2TxLists,
+    async fn generate_commit_hash_and_signature(
+        &mut self,
+        tx_list_bytes: &[u8],
         block_height: u64,
     ) -> Result<([u8; 32], [u8; 65]), Error> {
-        let commit = L2TxListsCommit::new(reply, block_height, self.taiko.chain_id);
+        let commit = L2TxListsCommit::new(tx_list_bytes, block_height, self.taiko.chain_id);
         let hash = commit.hash()?;
+        self.slashing_protection.check_and_insert(
+            self.ethereum_l1.execution_layer.get_preconfer_address(),
+            self.taiko.chain_id,
+            block_height,
+            commit.tx_list_hash,
+        )?;
         let signature = self
             .ethereum_l1
             .execution_layer
-            .sign_message_with_private_ecdsa_key(&hash[..])?;
+            .sign_message_with_ecdsa_key(&hash[..])
+            .await?;
         Ok((hash, signature))
     }
 
     async fn clean_old_blocks(
         preconfirmed_blocks: &PreconfirmedBlocks,
+        preconfirmation_store: &SharedPreconfirmationStore,
+        preconf_api: &PreconfApi,
         current_block_height: u64,
     ) -> Result<(), Error> {
-        let oldest_block_to_keep = current_block_height - OLDEST_BLOCK_DISTANCE;
+        let oldest_block_to_keep = current_block_height.saturating_sub(OLDEST_BLOCK_DISTANCE);
         let mut preconfirmed_blocks = preconfirmed_blocks.lock().await;
         preconfirmed_blocks.retain(|block_height, _| block_height >= &oldest_block_to_keep);
+        preconf_api.prune(oldest_block_to_keep).await;
+        preconfirmation_store
+            .lock()
+            .await
+            .prune(oldest_block_to_keep)?;
         Ok(())
     }
 
-    fn send_preconfirmations_to_the_avs_p2p(&self, message: PreconfirmationMessage) {
+    // The disputes of the block are scheduled with their own copy of the commitments, the
+    // evidence is dropped once they can't be sent anymore
+    async fn clean_old_equivocations(
+        dispute_windows: &mut DisputeWindows,
+        block_proposed: &BlockProposed,
+        equivocations: &Equivocations,
+        preconfirmation_store: &SharedPreconfirmationStore,
+        ethereum_l1: &EthereumL1,
+    ) {
+        let Some(last_closed_block) = dispute_windows.on_block_proposed(
+            block_proposed.block_id(),
+            block_proposed.event_data().meta.timestamp,
+            dispute_scheduler::dispute_period(&ethereum_l1.slot_clock),
+        ) else {
+            return;
+        };
+        equivocation::prune(equivocations, last_closed_block).await;
+        if let Err(e) = preconfirmation_store
+            .lock()
+            .await
+            .prune_equivocations(last_closed_block)
+        {
+            error!("Failed to prune the stored equivocations: {}", e);
+        }
+    }
+
+    fn send_preconfirmations_to_the_avs_p2p(
+        &self,
+        message: &PreconfirmationMessage,
+    ) -> Result<(), Error> {
         debug!(
             "Send message to p2p, tx list hash: {}",
             hex::encode(message.tx_list_hash)
         );
+        self.send_to_p2p(WireMessage::Preconfirmation(message.try_into()?));
+        Ok(())
+    }
 
-        if let Err(err) = self.node_to_p2p_tx.try_send(message.into()) {
+    fn send_to_p2p(&self, message: WireMessage) {
+        if let Err(err) = self
+            .node_to_p2p_tx
+            .try_send((message.topic(), message.to_bytes()))
+        {
             error!("Failed to send message to node_to_p2p_tx: {}", err);
         }
     }
+
+    // Lets the peers learn the lookahead of the epoch without querying L1
+    async fn announce_lookahead(&self, epoch: Epoch) -> Result<(), Error> {
+        let preconfers = self.operator.get_lookahead_preconfers();
+        if !preconfers.contains(&self.ethereum_l1.execution_layer.get_preconfer_address()) {
+            return Ok(());
+        }
+
+        let mut announcement = LookaheadAnnouncement {
+            chain_id: self.taiko.chain_id,
+            epoch,
+            preconfers: preconfers.iter().copied().map(FixedBytes).collect(),
+            signature: FixedBytes([0; 65]),
+        };
+        announcement.signature = FixedBytes(
+            self.ethereum_l1
+                .execution_layer
+                .sign_message_with_ecdsa_key(&announcement.signed_data())
+                .await?,
+        );
+        self.send_to_p2p(WireMessage::LookaheadAnnouncement(announcement));
+        Ok(())
+    }
+
+    // Tells the next preconfer and the other peers that this node won't preconfirm anymore
+    async fn hand_over(&mut self, slot: Slot) -> Result<(), Error> {
+        let next_preconfer = self
+            .operator
+            .get_next_preconfer_address(self.ethereum_l1.slot_clock.slot_of_epoch(slot))
+            .await?;
+        let last_block_id = self
+            .preconfirmed_blocks
+            .lock()
+            .await
+            .keys()
+            .max()
+            .copied()
+            .unwrap_or_default();
+
+        let mut handover = PreconferHandover {
+            chain_id: self.taiko.chain_id,
+            slot,
+            last_block_id,
+            next_preconfer: FixedBytes(next_preconfer),
+            signature: FixedBytes([0; 65]),
+        };
+        handover.signature = FixedBytes(
+            self.ethereum_l1
+                .execution_layer
+                .sign_message_with_ecdsa_key(&handover.signed_data())
+                .await?,
+        );
+        self.send_to_p2p(WireMessage::PreconferHandover(handover));
+        Ok(())
+    }
 }
@@ -3,7 +3,7 @@ use crate::{
     utils::types::*,
 };
 use anyhow::Error;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use tracing::debug;
 
 pub struct Operator {
@@ -12,6 +12,9 @@ pub struct Operator {
     lookahead_preconfer_addresses: Vec<PreconferAddress>,
     lookahead_preconfer_buffer: Vec<PreconfTaskManager::LookaheadBufferEntry>,
     l1_slots_per_epoch: u64,
+    fallback_preconfers: HashMap<Epoch, PreconferAddress>,
+    // L1 slot the lookahead was last checked in by the fallback preconfer
+    fallback_lookahead_slot: Option<Slot>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -19,6 +22,7 @@ pub enum Status {
     None,
     Preconfer,
     PreconferAndProposer, // has to force include transactions
+    FallbackPreconfer,    // can claim the empty slot by pushing the next epoch lookahead
 }
 
 impl Operator {
@@ -31,6 +35,8 @@ impl Operator {
             lookahead_preconfer_addresses: vec![],
             lookahead_preconfer_buffer: vec![],
             l1_slots_per_epoch,
+            fallback_preconfers: HashMap::new(),
+            fallback_lookahead_slot: None,
         })
     }
 
@@ -62,18 +68,31 @@ impl Operator {
             ));
         }
 
+        let l1_slot = slot;
         let slot = slot % self.l1_slots_per_epoch;
 
+        // only the fallback preconfers of the current and the next epoch are checked
+        let epoch = self.epoch;
+        self.fallback_preconfers.retain(|e, _| *e >= epoch);
+
+        let preconfer_address = self.ethereum_l1.execution_layer.get_preconfer_address();
+
         // If the preconfer address is zero, next epoch preconfer may start preconfirming.
         // Update the lookahead to check if it is assigned as a preconfer for the rest slots
         // of the current epoch.
+        // A lookahead proven incorrect is replaced by the fallback preconfer of the epoch, which
+        // has to update it as well to notice it, once per L1 slot rather than every L2 slot.
         if self.lookahead_preconfer_addresses[slot as usize] == PRECONFER_ADDRESS_ZERO {
             self.update_preconfer_lookahead_for_epoch().await?;
+        } else if self.lookahead_preconfer_addresses[slot as usize] != preconfer_address
+            && self.fallback_lookahead_slot != Some(l1_slot)
+            && self.is_fallback_preconfer(self.epoch).await?
+        {
+            self.update_preconfer_lookahead_for_epoch().await?;
+            self.fallback_lookahead_slot = Some(l1_slot);
         }
 
-        if self.lookahead_preconfer_addresses[slot as usize]
-            == self.ethereum_l1.execution_layer.get_preconfer_address()
-        {
+        if self.lookahead_preconfer_addresses[slot as usize] == preconfer_address {
             let next_preconfer_address = self.get_next_preconfer_address(slot).await?;
             if self.is_the_final_slot_to_preconf(next_preconfer_address) {
                 return Ok(Status::PreconferAndProposer);
@@ -81,10 +100,46 @@ impl Operator {
             return Ok(Status::Preconfer);
         }
 
+        // The lookahead of the next epoch is not pushed yet. Once it is, its first entry covers
+        // the empty slots, which is the fallback preconfer when the lookahead is empty.
+        if self.lookahead_preconfer_addresses[slot as usize] == PRECONFER_ADDRESS_ZERO
+            && self.is_fallback_preconfer(self.epoch + 1).await?
+        {
+            return Ok(Status::FallbackPreconfer);
+        }
+
         Ok(Status::None)
     }
 
-    async fn get_next_preconfer_address(
+    async fn is_fallback_preconfer(&mut self, epoch: Epoch) -> Result<bool, Error> {
+        let fallback_preconfer = match self.fallback_preconfers.get(&epoch) {
+            Some(fallback_preconfer) => *fallback_preconfer,
+            None => {
+                let fallback_preconfer = self
+                    .ethereum_l1
+                    .execution_layer
+                    .get_fallback_preconfer(epoch)
+                    .await?;
+                self.fallback_preconfers.insert(epoch, fallback_preconfer);
+                fallback_preconfer
+            }
+        };
+        Ok(fallback_preconfer == self.ethereum_l1.execution_layer.get_preconfer_address())
+    }
+
+    /// Preconfers of every slot of the current epoch.
+    pub fn get_lookahead_preconfers(&self) -> &[PreconferAddress] {
+        &self.lookahead_preconfer_addresses
+    }
+
+    /// Returns the preconfer of `slot` from the lookahead of the current epoch.
+    pub fn get_preconfer_for_slot(&self, slot: Slot) -> Option<PreconferAddress> {
+        self.lookahead_preconfer_addresses
+            .get((slot % self.l1_slots_per_epoch) as usize)
+            .copied()
+    }
+
+    pub async fn get_next_preconfer_address(
         &mut self,
         slot_mod_slots_per_epoch: Slot,
     ) -> Result<PreconferAddress, Error> {
@@ -135,14 +190,19 @@ impl Operator {
             .slot_clock
             .get_real_slot_begin_timestamp_for_contract(slot)?;
 
+        // The entry covering the slot, either the slot of the preconfer or an empty slot before
+        // it, or the slots of the fallback preconfer
         let lookahead_pointer = self
             .lookahead_preconfer_buffer
             .iter()
             .position(|entry| {
-                entry.preconfer == self.ethereum_l1.execution_layer.get_preconfer_address()
-                    && slot_begin_timestamp > entry.prevTimestamp
+                slot_begin_timestamp > entry.prevTimestamp
                     && slot_begin_timestamp <= entry.timestamp
             })
+            .filter(|&pointer| {
+                self.lookahead_preconfer_buffer[pointer].preconfer
+                    == self.ethereum_l1.execution_layer.get_preconfer_address()
+            })
             .ok_or_else(|| {
                 let buffer_str = self
                     .lookahead_preconfer_buffer
@@ -189,6 +249,9 @@ mod tests {
         execution_layer
             .expect_get_lookahead_preconfer_buffer()
             .returning(|| Ok(create_lookahead_buffer()));
+        execution_layer
+            .expect_get_fallback_preconfer()
+            .returning(|_| Ok([2u8; 20]));
         let mut operator = create_operator(0, execution_layer).unwrap();
         operator
             .update_preconfer_lookahead_for_epoch()
@@ -257,6 +320,132 @@ mod tests {
         assert_eq!(status, Status::Preconfer);
     }
 
+    #[tokio::test]
+    async fn test_get_status_fallback_preconfer_of_disputed_epoch() {
+        let mut execution_layer = ExecutionLayer::default();
+        let mut lookahead_updates = 0;
+        execution_layer
+            .expect_get_lookahead_preconfer_addresses_for_epoch()
+            .returning(move |_| {
+                lookahead_updates += 1;
+                // the lookahead is proven incorrect after the first update
+                if lookahead_updates == 1 {
+                    Ok(vec![[2u8; 20]; 32])
+                } else {
+                    Ok(vec![[1u8; 20]; 32])
+                }
+            });
+        execution_layer
+            .expect_get_lookahead_preconfer_buffer()
+            .returning(|| Ok(create_lookahead_buffer()));
+        execution_layer
+            .expect_get_fallback_preconfer()
+            .returning(|epoch| Ok(if epoch == 0 { [1u8; 20] } else { [2u8; 20] }));
+
+        let mut operator = create_operator(0, execution_layer).unwrap();
+        operator
+            .update_preconfer_lookahead_for_epoch()
+            .await
+            .unwrap();
+        let status = operator.get_status(5).await.unwrap();
+        assert_eq!(status, Status::Preconfer);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_preconfer_checks_the_lookahead_once_per_slot() {
+        let mut execution_layer = ExecutionLayer::default();
+        // the first update, then once in slot 5 and once in slot 6
+        execution_layer
+            .expect_get_lookahead_preconfer_addresses_for_epoch()
+            .times(3)
+            .returning(|_| Ok(vec![[2u8; 20]; 32]));
+        execution_layer
+            .expect_get_lookahead_preconfer_buffer()
+            .returning(|| Ok(create_lookahead_buffer()));
+        execution_layer
+            .expect_get_fallback_preconfer()
+            .returning(|_| Ok([1u8; 20]));
+
+        let mut operator = create_operator(0, execution_layer).unwrap();
+        operator
+            .update_preconfer_lookahead_for_epoch()
+            .await
+            .unwrap();
+        for slot in [5, 5, 5, 6, 6] {
+            let status = operator.get_status(slot).await.unwrap();
+            assert_eq!(status, Status::None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_status_fallback_preconfer_of_next_epoch() {
+        let mut execution_layer = ExecutionLayer::default();
+        execution_layer
+            .expect_get_lookahead_preconfer_addresses_for_epoch()
+            .returning(|_| {
+                // the lookahead of the next epoch is not pushed yet
+                let mut lookahead = vec![[2u8; 20]; 32];
+                lookahead[20..].fill([0u8; 20]);
+                Ok(lookahead)
+            });
+        execution_layer
+            .expect_get_lookahead_preconfer_buffer()
+            .returning(|| Ok(create_lookahead_buffer()));
+        execution_layer
+            .expect_get_fallback_preconfer()
+            .returning(|epoch| Ok(if epoch == 1 { [1u8; 20] } else { [2u8; 20] }));
+
+        let mut operator = create_operator(0, execution_layer).unwrap();
+        operator
+            .update_preconfer_lookahead_for_epoch()
+            .await
+            .unwrap();
+        let status = operator.get_status(10).await.unwrap();
+        assert_eq!(status, Status::None);
+
+        let status = operator.get_status(25).await.unwrap();
+        assert_eq!(status, Status::FallbackPreconfer);
+    }
+
+    #[tokio::test]
+    async fn test_get_lookahead_pointer_of_fallback_entry() {
+        use alloy::primitives::Address;
+
+        let mut execution_layer = ExecutionLayer::default();
+        execution_layer
+            .expect_get_lookahead_preconfer_addresses_for_epoch()
+            .returning(|_| Ok(vec![[1u8; 20]; 32]));
+        execution_layer
+            .expect_get_lookahead_preconfer_buffer()
+            .returning(|| {
+                let mut buffer = create_lookahead_buffer();
+                // slots 0 to 4 of another preconfer
+                buffer[2] = PreconfTaskManager::LookaheadBufferEntry {
+                    isFallback: false,
+                    timestamp: 60,
+                    prevTimestamp: 0,
+                    preconfer: Address::from([2u8; 20]),
+                };
+                // the rest of the epoch of the fallback preconfer
+                buffer[3] = PreconfTaskManager::LookaheadBufferEntry {
+                    isFallback: true,
+                    timestamp: 384,
+                    prevTimestamp: 60,
+                    preconfer: Address::from([1u8; 20]),
+                };
+                Ok(buffer)
+            });
+
+        let mut operator = create_operator(0, execution_layer).unwrap();
+        operator
+            .update_preconfer_lookahead_for_epoch()
+            .await
+            .unwrap();
+        assert_eq!(operator.get_lookahead_pointer(5).unwrap(), 3);
+        assert_eq!(operator.get_lookahead_pointer(31).unwrap(), 3);
+        assert!(operator.get_lookahead_pointer(4).is_err());
+    }
+
     fn create_operator(
         epoch: Epoch,
         mut execution_layer: ExecutionLayer,
@@ -0,0 +1,356 @@
+// JSON-RPC API letting wallets and users check that their transactions were preconfirmed, and
+// verify the commitments themselves from the commit hash and the preconfer signature.
+use super::{
+    equivocation::Equivocations, preconfirmation_message::PreconfirmationMessage,
+    PreconfirmedBlocks,
+};
+use alloy::primitives::{Address, Bytes, B256};
+use anyhow::Error;
+use jsonrpsee::{
+    core::SubscriptionResult,
+    server::{ServerBuilder, ServerHandle},
+    types::ErrorObjectOwned,
+    PendingSubscriptionSink, RpcModule, SubscriptionMessage,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use tokio::sync::{broadcast, watch, Mutex};
+use tracing::{debug, info};
+
+const NEW_PRECONFIRMATIONS_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreconfirmationResponse {
+    pub block_id: u64,
+    pub chain_id: u64,
+    pub tx_list_hash: B256,
+    /// Hash of the RLP encoded (block id, chain id, tx list hash) commitment.
+    pub commit_hash: B256,
+    pub signature: Bytes,
+    /// Address recovered from the signature of the commit hash.
+    pub preconfer: Option<Address>,
+    pub tx_hashes: Vec<B256>,
+    pub tx_lists: Value,
+}
+
+impl PreconfirmationResponse {
+    fn new(msg: &PreconfirmationMessage, chain_id: u64) -> Self {
+        Self {
+            block_id: msg.block_height,
+            chain_id,
+            tx_list_hash: msg.tx_list_hash.into(),
+            commit_hash: msg.proof.commit_hash.into(),
+            signature: Bytes::copy_from_slice(&msg.proof.signature),
+            preconfer: msg.signer(),
+            tx_hashes: tx_hashes(&msg.tx_lists),
+            tx_lists: msg.tx_lists.clone(),
+        }
+    }
+}
+
+/// Two preconfirmations of the same block signed by the same preconfer.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EquivocationResponse {
+    pub block_id: u64,
+    pub preconfer: Address,
+    pub first: PreconfirmationResponse,
+    pub second: PreconfirmationResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentPreconfer {
+    pub epoch: u64,
+    pub slot: u64,
+    pub preconfer: Address,
+}
+
+// The tx lists are the JSON transactions returned by the Taiko proposer, nested in arrays.
+fn tx_hashes(tx_lists: &Value) -> Vec<B256> {
+    match tx_lists {
+        Value::Array(values) => values.iter().flat_map(tx_hashes).collect(),
+        Value::Object(tx) => tx
+            .get("hash")
+            .and_then(Value::as_str)
+            .and_then(|hash| hash.parse().ok())
+            .into_iter()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// State shared between the node, which publishes the preconfirmations, and the API server.
+#[derive(Clone)]
+pub struct PreconfApi {
+    preconfirmed_blocks: PreconfirmedBlocks,
+    // Height of the preconfirmed block of each tx, so a lookup doesn't scan all the blocks
+    tx_heights: Arc<Mutex<HashMap<B256, u64>>>,
+    equivocations: Equivocations,
+    chain_id: u64,
+    new_preconfirmations: broadcast::Sender<PreconfirmationResponse>,
+    current_preconfer: Arc<watch::Sender<Option<CurrentPreconfer>>>,
+}
+
+impl PreconfApi {
+    pub fn new(
+        preconfirmed_blocks: PreconfirmedBlocks,
+        equivocations: Equivocations,
+        chain_id: u64,
+    ) -> Self {
+        let (new_preconfirmations, _) = broadcast::channel(NEW_PRECONFIRMATIONS_CAPACITY);
+        Self {
+            preconfirmed_blocks,
+            tx_heights: Arc::new(Mutex::new(HashMap::new())),
+            equivocations,
+            chain_id,
+            new_preconfirmations,
+            current_preconfer: Arc::new(watch::channel(None).0),
+        }
+    }
+
+    /// Indexes the transactions of the blocks restored from the store.
+    pub async fn index_preconfirmed_blocks(&self) {
+        let preconfirmed_blocks = self.preconfirmed_blocks.lock().await;
+        let mut tx_heights = self.tx_heights.lock().await;
+        for msg in preconfirmed_blocks.values() {
+            for tx_hash in tx_hashes(&msg.tx_lists) {
+                tx_heights.insert(tx_hash, msg.block_height);
+            }
+        }
+    }
+
+    /// Indexes the transactions of `msg` and streams it to the subscribers, it has to be in the
+    /// preconfirmed blocks already.
+    pub async fn publish(&self, msg: &PreconfirmationMessage) {
+        let mut tx_heights = self.tx_heights.lock().await;
+        for tx_hash in tx_hashes(&msg.tx_lists) {
+            tx_heights.insert(tx_hash, msg.block_height);
+        }
+        drop(tx_heights);
+        // nobody subscribed is fine
+        let _ = self
+            .new_preconfirmations
+            .send(PreconfirmationResponse::new(msg, self.chain_id));
+    }
+
+    /// Forgets the transactions of the blocks below `oldest_block_to_keep`.
+    pub async fn prune(&self, oldest_block_to_keep: u64) {
+        self.tx_heights
+            .lock()
+            .await
+            .retain(|_, block_height| *block_height >= oldest_block_to_keep);
+    }
+
+    pub fn set_current_preconfer(&self, current_preconfer: CurrentPreconfer) {
+        self.current_preconfer.send_replace(Some(current_preconfer));
+    }
+
+    async fn get_by_block_id(&self, block_id: u64) -> Option<PreconfirmationResponse> {
+        self.preconfirmed_blocks
+            .lock()
+            .await
+            .get(&block_id)
+            .map(|msg| PreconfirmationResponse::new(msg, self.chain_id))
+    }
+
+    async fn get_by_tx_hash(&self, tx_hash: B256) -> Option<PreconfirmationResponse> {
+        let block_id = *self.tx_heights.lock().await.get(&tx_hash)?;
+        // The block may have been replaced by one without the tx since it was indexed
+        self.get_by_block_id(block_id)
+            .await
+            .filter(|preconfirmation| preconfirmation.tx_hashes.contains(&tx_hash))
+    }
+
+    // Oldest block first
+    async fn get_equivocations(&self) -> Vec<EquivocationResponse> {
+        let equivocations = self.equivocations.lock().await;
+        let mut block_ids: Vec<&u64> = equivocations.keys().collect();
+        block_ids.sort();
+        block_ids
+            .into_iter()
+            .flat_map(|block_id| &equivocations[block_id])
+            .map(|equivocation| EquivocationResponse {
+                block_id: equivocation.block_height,
+                preconfer: equivocation.preconfer,
+                first: PreconfirmationResponse::new(&equivocation.first, self.chain_id),
+                second: PreconfirmationResponse::new(&equivocation.second, self.chain_id),
+            })
+            .collect()
+    }
+
+    fn rpc_module(self) -> Result<RpcModule<Self>, Error> {
+        let mut module = RpcModule::new(self);
+
+        module.register_async_method("preconf_getByBlockId", |params, api, _| async move {
+            let block_id: u64 = params.one()?;
+            Ok::<_, ErrorObjectOwned>(api.get_by_block_id(block_id).await)
+        })?;
+        module.register_async_method("preconf_getByTxHash", |params, api, _| async move {
+            let tx_hash: B256 = params.one()?;
+            Ok::<_, ErrorObjectOwned>(api.get_by_tx_hash(tx_hash).await)
+        })?;
+        module.register_async_method("preconf_getEquivocations", |_, api, _| async move {
+            api.get_equivocations().await
+        })?;
+        module.register_method("preconf_getCurrentPreconfer", |_, api, _| {
+            api.current_preconfer.borrow().clone()
+        })?;
+        module.register_subscription(
+            "preconf_subscribe",
+            "preconf_subscription",
+            "preconf_unsubscribe",
+            |_, pending, api, _| async move { Self::stream_preconfirmations(pending, api).await },
+        )?;
+
+        Ok(module)
+    }
+
+    async fn stream_preconfirmations(
+        pending: PendingSubscriptionSink,
+        api: Arc<Self>,
+    ) -> SubscriptionResult {
+        let mut new_preconfirmations = api.new_preconfirmations.subscribe();
+        let sink = pending.accept().await?;
+        loop {
+            tokio::select! {
+                _ = sink.closed() => return Ok(()),
+                preconfirmation = new_preconfirmations.recv() => match preconfirmation {
+                    Ok(preconfirmation) => {
+                        sink.send(SubscriptionMessage::from_json(&preconfirmation)?).await?;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Preconfirmation subscriber missed {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
+                },
+            }
+        }
+    }
+
+    /// Serves the API over HTTP and websocket on `addr`.
+    pub async fn start(self, addr: SocketAddr) -> Result<ServerHandle, Error> {
+        let server = ServerBuilder::default().build(addr).await?;
+        let handle = server.start(self.rpc_module()?);
+        info!("Preconfirmation API listening on {}", addr);
+        Ok(handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{
+        equivocation::Equivocation, preconfirmation_message::signed_preconfirmation,
+    };
+    use alloy::signers::local::PrivateKeySigner;
+    use serde_json::json;
+
+    const TX_HASH: &str = "0xc653e446eafe51eea1f46e6e351adbd1cc8a3271e6935f1441f613a58d441f6a";
+
+    fn preconfirmation(block_height: u64, signer: &PrivateKeySigner) -> PreconfirmationMessage {
+        let tx_lists = json!([[{ "type": "0x2", "hash": TX_HASH }]]);
+        signed_preconfirmation(block_height, tx_lists, &[1, 2, 3], signer)
+    }
+
+    #[tokio::test]
+    async fn test_queries_and_subscription() {
+        let signer = PrivateKeySigner::random();
+        let msg = preconfirmation(5, &signer);
+        let preconfirmed_blocks = Arc::new(Mutex::new(HashMap::from([(5, msg.clone())])));
+        let equivocations = Equivocations::default();
+        let api = PreconfApi::new(preconfirmed_blocks.clone(), equivocations.clone(), 167);
+        api.index_preconfirmed_blocks().await;
+        let module = api.clone().rpc_module().unwrap();
+
+        let by_block: Option<Value> = module.call("preconf_getByBlockId", [5]).await.unwrap();
+        let by_block = by_block.unwrap();
+        assert_eq!(by_block["blockId"], 5);
+        assert_eq!(by_block["chainId"], 167);
+        assert_eq!(by_block["preconfer"], json!(signer.address()));
+        assert_eq!(by_block["txHashes"], json!([TX_HASH]));
+
+        let by_tx: Option<Value> = module.call("preconf_getByTxHash", [TX_HASH]).await.unwrap();
+        assert_eq!(by_tx.unwrap(), by_block);
+        let missing: Option<Value> = module.call("preconf_getByBlockId", [6]).await.unwrap();
+        assert!(missing.is_none());
+
+        let none: Vec<Value> = module
+            .call("preconf_getEquivocations", jsonrpsee::rpc_params![])
+            .await
+            .unwrap();
+        assert!(none.is_empty());
+        equivocations.lock().await.insert(
+            5,
+            vec![Equivocation {
+                preconfer: signer.address(),
+                block_height: 5,
+                first: msg.clone(),
+                second: preconfirmation(5, &signer),
+            }],
+        );
+        let found: Vec<Value> = module
+            .call("preconf_getEquivocations", jsonrpsee::rpc_params![])
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0]["blockId"], 5);
+        assert_eq!(found[0]["preconfer"], json!(signer.address()));
+        assert_eq!(found[0]["first"]["preconfer"], json!(signer.address()));
+
+        let current: Option<Value> = module
+            .call("preconf_getCurrentPreconfer", jsonrpsee::rpc_params![])
+            .await
+            .unwrap();
+        assert!(current.is_none());
+        api.set_current_preconfer(CurrentPreconfer {
+            epoch: 2,
+            slot: 70,
+            preconfer: signer.address(),
+        });
+        let current: Value = module
+            .call("preconf_getCurrentPreconfer", jsonrpsee::rpc_params![])
+            .await
+            .unwrap();
+        assert_eq!(current["slot"], 70);
+
+        let mut subscription = module
+            .subscribe_unbounded("preconf_subscribe", jsonrpsee::rpc_params![])
+            .await
+            .unwrap();
+        let next = preconfirmation(6, &signer);
+        preconfirmed_blocks.lock().await.insert(6, next.clone());
+        api.publish(&next).await;
+        let (streamed, _) = subscription.next::<Value>().await.unwrap().unwrap();
+        assert_eq!(streamed["blockId"], 6);
+        assert_eq!(
+            streamed["commitHash"],
+            json!(B256::from(next.proof.commit_hash))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tx_index() {
+        let signer = PrivateKeySigner::random();
+        let preconfirmed_blocks = PreconfirmedBlocks::default();
+        let api = PreconfApi::new(preconfirmed_blocks.clone(), Equivocations::default(), 167);
+        let tx_hash: B256 = TX_HASH.parse().unwrap();
+
+        let msg = preconfirmation(5, &signer);
+        preconfirmed_blocks.lock().await.insert(5, msg.clone());
+        api.publish(&msg).await;
+        assert_eq!(api.get_by_tx_hash(tx_hash).await.unwrap().block_id, 5);
+
+        // replaced by a block without the tx
+        let mut replacement = preconfirmation(5, &signer);
+        replacement.tx_lists = json!([[]]);
+        preconfirmed_blocks.lock().await.insert(5, replacement);
+        assert!(api.get_by_tx_hash(tx_hash).await.is_none());
+
+        preconfirmed_blocks.lock().await.insert(5, msg);
+        api.prune(6).await;
+        assert!(api.get_by_tx_hash(tx_hash).await.is_none());
+    }
+}
@@ -11,10 +11,8 @@ impl PreconfirmationHelper {
         self.nonce = nonce;
     }
 
-    pub fn get_next_nonce(&mut self) -> u64 {
-        let nonce = self.nonce;
-        self.nonce += 1;
-        nonce
+    pub fn get_nonce(&self) -> u64 {
+        self.nonce
     }
 
     pub fn increment_nonce(&mut self) {
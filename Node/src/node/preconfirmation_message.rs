@@ -1,5 +1,13 @@
-use super::preconfirmation_proof::PreconfirmationProof;
-use crate::utils::{bytes_tools::hash_bytes_with_keccak, types::*};
+use super::{
+    preconfirmation_proof::PreconfirmationProof,
+    wire::{self, FixedBytes},
+};
+use crate::{
+    taiko::transactions,
+    utils::{bytes_tools::hash_bytes_with_keccak, types::*},
+};
+use alloy::primitives::{Address, Signature};
+use anyhow::Error;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -16,16 +24,47 @@ impl PreconfirmationMessage {
     pub fn new(
         block_height: u64,
         tx_lists: Value,
-        tx_list_rlp_bytes: &Vec<u8>,
+        tx_list_rlp_bytes: &[u8],
         proof: PreconfirmationProof,
     ) -> Self {
         PreconfirmationMessage {
             block_height,
             tx_lists,
-            tx_list_hash: hash_bytes_with_keccak(tx_list_rlp_bytes.as_slice()),
+            tx_list_hash: hash_bytes_with_keccak(tx_list_rlp_bytes),
             proof,
         }
     }
+
+    /// Address recovered from the signature of the commit hash.
+    pub fn signer(&self) -> Option<Address> {
+        Signature::try_from(&self.proof.signature[..])
+            .and_then(|signature| signature.recover_address_from_msg(self.proof.commit_hash))
+            .ok()
+    }
+}
+
+/// Preconfirmation of `tx_list` as block `block_height` signed by `signer`, the commit hash
+/// only has to differ between the blocks and the tx lists.
+#[cfg(test)]
+pub fn signed_preconfirmation(
+    block_height: u64,
+    tx_lists: Value,
+    tx_list: &[u8],
+    signer: &alloy::signers::local::PrivateKeySigner,
+) -> PreconfirmationMessage {
+    use alloy::{primitives::keccak256, signers::SignerSync};
+
+    let commit_hash = keccak256([&block_height.to_be_bytes()[..], tx_list].concat());
+    let signature = signer.sign_message_sync(commit_hash.as_slice()).unwrap();
+    PreconfirmationMessage::new(
+        block_height,
+        tx_lists,
+        tx_list,
+        PreconfirmationProof {
+            commit_hash: commit_hash.0,
+            signature: signature.as_bytes(),
+        },
+    )
 }
 
 mod serde_json_as_string {
@@ -49,15 +88,61 @@ mod serde_json_as_string {
     }
 }
 
-impl From<PreconfirmationMessage> for Vec<u8> {
-    fn from(val: PreconfirmationMessage) -> Self {
-        bincode::serialize(&val).expect("Serialization failed")
+// The tx lists are JSON arrays of tx lists, themselves arrays of transactions
+impl TryFrom<&PreconfirmationMessage> for wire::Preconfirmation {
+    type Error = Error;
+
+    fn try_from(msg: &PreconfirmationMessage) -> Result<Self, Self::Error> {
+        let tx_lists = msg
+            .tx_lists
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("Tx lists are not an array"))?
+            .iter()
+            .map(|tx_list| {
+                tx_list
+                    .as_array()
+                    .ok_or_else(|| anyhow::anyhow!("Tx list is not an array"))?
+                    .iter()
+                    .map(transactions::encode_transaction)
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(wire::Preconfirmation {
+            block_height: msg.block_height,
+            tx_lists,
+            tx_list_hash: msg.tx_list_hash,
+            commit_hash: msg.proof.commit_hash,
+            signature: FixedBytes(msg.proof.signature),
+        })
     }
 }
 
-impl From<Vec<u8>> for PreconfirmationMessage {
-    fn from(bytes: Vec<u8>) -> Self {
-        bincode::deserialize(&bytes).expect("Deserialization failed")
+// Messages come from any peer of the p2p network, so decoding must not panic
+impl TryFrom<wire::Preconfirmation> for PreconfirmationMessage {
+    type Error = Error;
+
+    fn try_from(msg: wire::Preconfirmation) -> Result<Self, Self::Error> {
+        let tx_lists = msg
+            .tx_lists
+            .iter()
+            .map(|tx_list| {
+                tx_list
+                    .iter()
+                    .map(|tx| transactions::decode_transaction(tx))
+                    .collect::<Result<Vec<_>, Error>>()
+                    .map(Value::Array)
+            })
+            .collect::<Result<Vec<_>, Error>>()
+            .map_err(|e| anyhow::anyhow!("Invalid preconfirmation tx lists: {}", e))?;
+        Ok(PreconfirmationMessage {
+            block_height: msg.block_height,
+            tx_lists: Value::Array(tx_lists),
+            tx_list_hash: msg.tx_list_hash,
+            proof: PreconfirmationProof {
+                commit_hash: msg.commit_hash,
+                signature: msg.signature.0,
+            },
+        })
     }
 }
 
@@ -70,7 +155,9 @@ mod tests {
     #[test]
     fn test_preconfirmation_message() {
         let block_height: u64 = 1;
-        let tx_lists = json!("{value1: 1, value2: 2}");
+        let response: Value =
+            serde_json::from_str(include_str!("../utils/tx_lists_test_response.json")).unwrap();
+        let tx_lists = response["TxLists"].clone();
         let tx_list_rlp_bytes = vec![1, 2, 3, 4];
         let proof = PreconfirmationProof {
             commit_hash: [3; 32],
@@ -79,15 +166,29 @@ mod tests {
         let preconfirmation_message =
             PreconfirmationMessage::new(block_height, tx_lists, &tx_list_rlp_bytes, proof.clone());
 
-        let bytes: Vec<u8> = preconfirmation_message.clone().into();
-        let preconfirmation_message2 = PreconfirmationMessage::from(bytes);
+        let wire_message = wire::Preconfirmation::try_from(&preconfirmation_message).unwrap();
+        let preconfirmation_message2 =
+            PreconfirmationMessage::try_from(wire_message.clone()).unwrap();
         assert_eq!(
             preconfirmation_message2.block_height,
             preconfirmation_message.block_height
         );
+        // the transactions come back in the JSON format of go-ethereum, without the empty fields
+        let tx_hashes = |tx_lists: &Value| -> Vec<Value> {
+            tx_lists[0]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tx| tx["hash"].clone())
+                .collect()
+        };
+        assert_eq!(
+            tx_hashes(&preconfirmation_message2.tx_lists),
+            tx_hashes(&preconfirmation_message.tx_lists)
+        );
         assert_eq!(
-            preconfirmation_message2.tx_lists,
-            preconfirmation_message.tx_lists
+            wire::Preconfirmation::try_from(&preconfirmation_message2).unwrap(),
+            wire_message
         );
         assert_eq!(
             preconfirmation_message2.tx_list_hash,
@@ -102,4 +203,25 @@ mod tests {
             preconfirmation_message.proof.signature
         );
     }
+
+    #[test]
+    fn test_invalid_tx_lists_are_rejected() {
+        let proof = PreconfirmationProof {
+            commit_hash: [3; 32],
+            signature: [4; 65],
+        };
+        for tx_lists in [json!("[[]]"), json!([{}]), json!([[{ "type": "0x2" }]])] {
+            let message = PreconfirmationMessage::new(1, tx_lists, &[1], proof.clone());
+            assert!(wire::Preconfirmation::try_from(&message).is_err());
+        }
+
+        let wire_message = wire::Preconfirmation {
+            block_height: 1,
+            tx_lists: vec![vec![vec![2, 1]]],
+            tx_list_hash: [1; 32],
+            commit_hash: [2; 32],
+            signature: FixedBytes([3; 65]),
+        };
+        assert!(PreconfirmationMessage::try_from(wire_message).is_err());
+    }
 }
@@ -1,4 +1,5 @@
 use crate::utils::types::ECDSASignature;
+use anyhow::Error;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,9 +15,12 @@ impl From<PreconfirmationProof> for Vec<u8> {
     }
 }
 
-impl From<Vec<u8>> for PreconfirmationProof {
-    fn from(bytes: Vec<u8>) -> Self {
-        bincode::deserialize(&bytes).expect("Deserialization failed")
+impl TryFrom<&[u8]> for PreconfirmationProof {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bincode::deserialize(bytes)
+            .map_err(|e| anyhow::anyhow!("Invalid preconfirmation proof: {}", e))
     }
 }
 
@@ -35,7 +39,8 @@ mod tests {
         };
 
         let bytes: Vec<u8> = preconfirmation_proof.clone().into();
-        let preconfirmation_proof_restore: PreconfirmationProof = bytes.into();
+        let preconfirmation_proof_restore =
+            PreconfirmationProof::try_from(bytes.as_slice()).unwrap();
         assert_eq!(
             preconfirmation_proof_restore.commit_hash,
             preconfirmation_proof.commit_hash
@@ -0,0 +1,401 @@
+// Append-only on-disk log of the preconfirmation state of the node, replayed on startup
+// so a restart doesn't lose signed commitments or pending L1 proposal transactions.
+use super::{equivocation::Equivocation, preconfirmation_message::PreconfirmationMessage};
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
+
+const STORE_FILE_NAME: &str = "preconfirmations.log";
+// Written in every record, to be bumped whenever the encoding of a record changes
+const STORE_FORMAT_VERSION: u8 = 1;
+const FRAME_HEADER_LEN: usize = 5;
+// The log is rewritten once it reaches this size and twice its size after the last rewrite
+const MIN_COMPACTION_LEN: u64 = 1024 * 1024;
+const COMPACTION_RATIO: u64 = 2;
+
+#[derive(Debug, Serialize, Deserialize)]
+enum StoreRecord {
+    Preconfirmation(PreconfirmationMessage),
+    ProposalTx { block_id: u64, tx: Vec<u8> },
+    ProposalTxRemoved { block_id: u64 },
+    ProposalTxsCleared,
+    Equivocation(Box<Equivocation>),
+    Pruned { oldest_block_to_keep: u64 },
+    EquivocationsPruned { last_closed_block: u64 },
+}
+
+#[derive(Default)]
+pub struct StoredPreconfirmations {
+    pub preconfirmed_blocks: HashMap<u64, PreconfirmationMessage>,
+    pub preconfirmation_txs: HashMap<u64, Vec<u8>>, // block_id -> tx
+    pub equivocations: Vec<Equivocation>,
+}
+
+pub struct PreconfirmationStore {
+    path: PathBuf,
+    file: File,
+    compacted_len: u64,
+}
+
+impl PreconfirmationStore {
+    pub fn open(data_dir: &str) -> Result<Self, Error> {
+        fs::create_dir_all(data_dir)?;
+        let path = Path::new(data_dir).join(STORE_FILE_NAME);
+        let file = Self::open_for_append(&path)?;
+        // Drop a record torn by a stop in the middle of a write, the next ones would be
+        // unreadable after it. Any other unreadable record fails the opening, the records after
+        // it are signed commitments which must not be lost.
+        let (_, valid_len) = Self::read_records(&path)?;
+        if file.metadata()?.len() > valid_len {
+            warn!(
+                "Truncating the preconfirmation store to its last valid record at {} bytes",
+                valid_len
+            );
+            file.set_len(valid_len)?;
+        }
+        info!("Preconfirmation store opened at {}", path.display());
+        Ok(Self {
+            path,
+            file,
+            compacted_len: valid_len,
+        })
+    }
+
+    fn open_for_append(path: &Path) -> Result<File, Error> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", path.display(), e))
+    }
+
+    /// Replays the log and returns the state it describes.
+    pub fn load(&self) -> Result<StoredPreconfirmations, Error> {
+        let mut state = StoredPreconfirmations::default();
+        let (records, _) = Self::read_records(&self.path)?;
+        for record in records {
+            match record {
+                StoreRecord::Preconfirmation(msg) => {
+                    state.preconfirmed_blocks.insert(msg.block_height, msg);
+                }
+                StoreRecord::ProposalTx { block_id, tx } => {
+                    state.preconfirmation_txs.insert(block_id, tx);
+                }
+                StoreRecord::ProposalTxRemoved { block_id } => {
+                    state.preconfirmation_txs.remove(&block_id);
+                }
+                StoreRecord::ProposalTxsCleared => state.preconfirmation_txs.clear(),
+                StoreRecord::Equivocation(equivocation) => state.equivocations.push(*equivocation),
+                StoreRecord::Pruned {
+                    oldest_block_to_keep,
+                } => state.retain_from(oldest_block_to_keep),
+                StoreRecord::EquivocationsPruned { last_closed_block } => state
+                    .equivocations
+                    .retain(|equivocation| equivocation.block_height > last_closed_block),
+            }
+        }
+        debug!(
+            "Loaded {} preconfirmed blocks and {} proposal txs from the store",
+            state.preconfirmed_blocks.len(),
+            state.preconfirmation_txs.len()
+        );
+        Ok(state)
+    }
+
+    pub fn insert_preconfirmation(&mut self, msg: &PreconfirmationMessage) -> Result<(), Error> {
+        self.append(&StoreRecord::Preconfirmation(msg.clone()))
+    }
+
+    pub fn insert_proposal_tx(&mut self, block_id: u64, tx: &[u8]) -> Result<(), Error> {
+        self.append(&StoreRecord::ProposalTx {
+            block_id,
+            tx: tx.to_vec(),
+        })
+    }
+
+    pub fn remove_proposal_tx(&mut self, block_id: u64) -> Result<(), Error> {
+        self.append(&StoreRecord::ProposalTxRemoved { block_id })
+    }
+
+    pub fn clear_proposal_txs(&mut self) -> Result<(), Error> {
+        self.append(&StoreRecord::ProposalTxsCleared)
+    }
+
+    pub fn insert_equivocation(&mut self, equivocation: &Equivocation) -> Result<(), Error> {
+        self.append(&StoreRecord::Equivocation(Box::new(equivocation.clone())))
+    }
+
+    /// Drops every entry below `oldest_block_to_keep`. The log is only rewritten from its current
+    /// state once it has grown enough since the last rewrite.
+    /// The equivocations are kept until their dispute window is over, they are the evidence
+    /// against a preconfer.
+    pub fn prune(&mut self, oldest_block_to_keep: u64) -> Result<(), Error> {
+        self.append(&StoreRecord::Pruned {
+            oldest_block_to_keep,
+        })?;
+        let len = self.file.metadata()?.len();
+        if len < MIN_COMPACTION_LEN.max(self.compacted_len * COMPACTION_RATIO) {
+            return Ok(());
+        }
+        self.compact()
+    }
+
+    /// Drops the equivocations of the blocks up to `last_closed_block`, which can't be disputed
+    /// anymore. The log is rewritten at the next prune.
+    pub fn prune_equivocations(&mut self, last_closed_block: u64) -> Result<(), Error> {
+        self.append(&StoreRecord::EquivocationsPruned { last_closed_block })
+    }
+
+    fn compact(&mut self) -> Result<(), Error> {
+        let state = self.load()?;
+        let tmp_path = self.path.with_extension("log.tmp");
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            for msg in state.preconfirmed_blocks.into_values() {
+                Self::write_record(&mut tmp, &StoreRecord::Preconfirmation(msg))?;
+            }
+            for (block_id, tx) in state.preconfirmation_txs {
+                Self::write_record(&mut tmp, &StoreRecord::ProposalTx { block_id, tx })?;
+            }
+            for equivocation in state.equivocations {
+                Self::write_record(&mut tmp, &StoreRecord::Equivocation(Box::new(equivocation)))?;
+            }
+            tmp.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+        self.file = Self::open_for_append(&self.path)?;
+        self.compacted_len = self.file.metadata()?.len();
+        debug!(
+            "Preconfirmation store compacted to {} bytes",
+            self.compacted_len
+        );
+        Ok(())
+    }
+
+    fn append(&mut self, record: &StoreRecord) -> Result<(), Error> {
+        Self::write_record(&mut self.file, record)?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    // Each record is stored as a little-endian u32 length and the format version, followed by
+    // the bincode bytes.
+    fn write_record(file: &mut File, record: &StoreRecord) -> Result<(), Error> {
+        let bytes = bincode::serialize(record)?;
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + bytes.len());
+        frame.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        frame.push(STORE_FORMAT_VERSION);
+        frame.extend_from_slice(&bytes);
+        file.write_all(&frame)?;
+        Ok(())
+    }
+
+    /// Returns the records of the log and the length of the part holding them, which excludes a
+    /// frame torn at the end of the log.
+    fn read_records(path: &Path) -> Result<(Vec<StoreRecord>, u64), Error> {
+        let log = fs::read(path)?;
+        let mut records = Vec::new();
+        let mut offset = 0;
+        while offset < log.len() {
+            let frame = &log[offset..];
+            let len = match frame.get(..4) {
+                Some(len_bytes) => u32::from_le_bytes(len_bytes.try_into()?) as usize,
+                None => 0,
+            };
+            if frame.len() < FRAME_HEADER_LEN + len {
+                // The node was stopped in the middle of a write, the tail is incomplete
+                warn!("Preconfirmation store ends with a truncated record, ignoring it");
+                break;
+            }
+            let version = frame[4];
+            if version != STORE_FORMAT_VERSION {
+                return Err(anyhow::anyhow!(
+                    "Preconfirmation store record at offset {} has format version {}, expected {}",
+                    offset,
+                    version,
+                    STORE_FORMAT_VERSION
+                ));
+            }
+            let record = bincode::deserialize(&frame[FRAME_HEADER_LEN..FRAME_HEADER_LEN + len])
+                .map_err(|e| {
+                    anyhow::anyhow!(
+                        "Failed to decode preconfirmation store record at offset {}: {}",
+                        offset,
+                        e
+                    )
+                })?;
+            records.push(record);
+            offset += FRAME_HEADER_LEN + len;
+        }
+        Ok((records, offset as u64))
+    }
+}
+
+impl StoredPreconfirmations {
+    fn retain_from(&mut self, oldest_block_to_keep: u64) {
+        self.preconfirmed_blocks
+            .retain(|block_height, _| *block_height >= oldest_block_to_keep);
+        self.preconfirmation_txs
+            .retain(|block_id, _| *block_id >= oldest_block_to_keep);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::preconfirmation_proof::PreconfirmationProof;
+    use serde_json::json;
+
+    fn create_message(block_height: u64) -> PreconfirmationMessage {
+        PreconfirmationMessage::new(
+            block_height,
+            json!([[{"hash": "0x01"}]]),
+            &[block_height as u8; 8],
+            PreconfirmationProof {
+                commit_hash: [1; 32],
+                signature: [2; 65],
+            },
+        )
+    }
+
+    #[test]
+    fn test_replay_after_reopen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        {
+            let mut store = PreconfirmationStore::open(data_dir).unwrap();
+            store.insert_preconfirmation(&create_message(10)).unwrap();
+            store.insert_preconfirmation(&create_message(11)).unwrap();
+            store.insert_proposal_tx(10, &[1, 2, 3]).unwrap();
+            store.insert_proposal_tx(11, &[4, 5, 6]).unwrap();
+            store.remove_proposal_tx(10).unwrap();
+        }
+
+        let store = PreconfirmationStore::open(data_dir).unwrap();
+        let state = store.load().unwrap();
+        assert_eq!(state.preconfirmed_blocks.len(), 2);
+        assert_eq!(
+            state.preconfirmed_blocks[&11].tx_list_hash,
+            create_message(11).tx_list_hash
+        );
+        assert_eq!(state.preconfirmation_txs.len(), 1);
+        assert_eq!(state.preconfirmation_txs[&11], vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_prune() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        let mut store = PreconfirmationStore::open(data_dir).unwrap();
+        for block_height in 1..=5 {
+            store
+                .insert_preconfirmation(&create_message(block_height))
+                .unwrap();
+            store.insert_proposal_tx(block_height, &[0]).unwrap();
+        }
+        let equivocation = Equivocation {
+            preconfer: alloy::primitives::Address::repeat_byte(1),
+            block_height: 2,
+            first: create_message(2),
+            second: create_message(2),
+        };
+        store.insert_equivocation(&equivocation).unwrap();
+        store.prune(4).unwrap();
+        store.insert_preconfirmation(&create_message(6)).unwrap();
+        store.clear_proposal_txs().unwrap();
+
+        // the small log is not rewritten, the pruned entries are dropped on replay
+        let len = store.file.metadata().unwrap().len();
+        assert!(len > store.compacted_len);
+        let state = store.load().unwrap();
+        let mut block_heights: Vec<u64> = state.preconfirmed_blocks.into_keys().collect();
+        block_heights.sort();
+        assert_eq!(block_heights, vec![4, 5, 6]);
+
+        store.compact().unwrap();
+        assert!(store.compacted_len < len);
+        // the store is still writable after the rewrite
+        store.insert_preconfirmation(&create_message(7)).unwrap();
+
+        let state = store.load().unwrap();
+        let mut block_heights: Vec<u64> = state.preconfirmed_blocks.into_keys().collect();
+        block_heights.sort();
+        assert_eq!(block_heights, vec![4, 5, 6, 7]);
+        assert!(state.preconfirmation_txs.is_empty());
+        // the evidence outlives the pruned blocks
+        assert_eq!(state.equivocations.len(), 1);
+        assert_eq!(state.equivocations[0].preconfer, equivocation.preconfer);
+
+        // until the dispute window of the block is over
+        store.prune_equivocations(2).unwrap();
+        assert!(store.load().unwrap().equivocations.is_empty());
+    }
+
+    #[test]
+    fn test_truncated_record_is_dropped_on_open() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        {
+            let mut store = PreconfirmationStore::open(data_dir).unwrap();
+            store.insert_preconfirmation(&create_message(7)).unwrap();
+            store.file.write_all(&[100, 0, 0, 0, 1, 2]).unwrap();
+
+            let state = store.load().unwrap();
+            assert_eq!(state.preconfirmed_blocks.len(), 1);
+        }
+
+        // the records appended after reopening are readable
+        let mut store = PreconfirmationStore::open(data_dir).unwrap();
+        store.insert_preconfirmation(&create_message(8)).unwrap();
+        let state = store.load().unwrap();
+        assert_eq!(state.preconfirmed_blocks.len(), 2);
+    }
+
+    #[test]
+    fn test_corrupt_record_fails_the_opening() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        {
+            let mut store = PreconfirmationStore::open(data_dir).unwrap();
+            store.insert_preconfirmation(&create_message(7)).unwrap();
+            store.insert_preconfirmation(&create_message(8)).unwrap();
+        }
+        let path = temp_dir.path().join(STORE_FILE_NAME);
+        let log = fs::read(&path).unwrap();
+
+        // an unknown variant in the first record
+        let mut corrupt = log.clone();
+        corrupt[FRAME_HEADER_LEN..FRAME_HEADER_LEN + 4].copy_from_slice(&[0xff; 4]);
+        fs::write(&path, &corrupt).unwrap();
+        assert!(PreconfirmationStore::open(data_dir).is_err());
+        // the records are left for inspection
+        assert_eq!(fs::read(&path).unwrap(), corrupt);
+
+        // a record of another format version
+        let mut other_version = log;
+        other_version[4] = STORE_FORMAT_VERSION + 1;
+        fs::write(&path, &other_version).unwrap();
+        assert!(PreconfirmationStore::open(data_dir).is_err());
+    }
+
+    #[test]
+    fn test_length_past_the_end_is_a_torn_record() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        let mut store = PreconfirmationStore::open(data_dir).unwrap();
+        store.insert_preconfirmation(&create_message(7)).unwrap();
+        store
+            .file
+            .write_all(&[0xff, 0xff, 0xff, 0xff, 1, 2])
+            .unwrap();
+
+        let state = store.load().unwrap();
+        assert_eq!(state.preconfirmed_blocks.len(), 1);
+    }
+}
@@ -0,0 +1,310 @@
+// Recovers the preconfirmations missed on gossip, after joining late or a disconnection, by
+// asking the peers for the missing blocks. The preconfirmations received after a hole are held
+// until it is filled, so that the L2 head is always advanced in order, or until the hole is
+// proposed on L1 or the peers repeatedly fail to fill it.
+use super::{
+    preconfirmation_message::PreconfirmationMessage, wire::WireMessage, PreconfirmedBlocks,
+};
+use p2p_network::network::{PeerId, PeerOffence, PeerReport};
+use p2p_network::sync::{SyncRequest, SyncResponse, MAX_RESPONSE_BYTES};
+use std::collections::BTreeMap;
+use tokio::sync::mpsc::Sender;
+use tracing::{debug, error, warn};
+
+// Preconfirmations held beyond this distance from the last applied block are dropped
+pub const MAX_PENDING_DISTANCE: u64 = 256;
+// Requests in a row which don't fill the hole before the held preconfirmations are released
+const MAX_FAILED_REQUESTS: u32 = 3;
+
+pub struct PreconfirmationSync {
+    requests_tx: Sender<SyncRequest>,
+    report_tx: Sender<PeerReport>,
+    in_flight: Option<SyncRequest>,
+    failed_requests: u32,
+    pending: BTreeMap<u64, PreconfirmationMessage>,
+    // Last block proposed on L1, the blocks up to it are not synced from the peers
+    last_proposed_block: Option<u64>,
+}
+
+impl PreconfirmationSync {
+    pub fn new(
+        requests_tx: Sender<SyncRequest>,
+        report_tx: Sender<PeerReport>,
+        last_proposed_block: Option<u64>,
+    ) -> Self {
+        Self {
+            requests_tx,
+            report_tx,
+            in_flight: None,
+            failed_requests: 0,
+            pending: BTreeMap::new(),
+            last_proposed_block,
+        }
+    }
+
+    /// The block the next preconfirmation follows, the last preconfirmed one unless a later
+    /// block was proposed on L1.
+    pub fn last_block(&self, last_preconfirmed_block: Option<u64>) -> Option<u64> {
+        last_preconfirmed_block.max(self.last_proposed_block)
+    }
+
+    /// Takes the held preconfirmations which follow `block_id`, just proposed on L1, as the
+    /// blocks before it don't have to be synced anymore.
+    pub fn on_block_proposed(
+        &mut self,
+        block_id: u64,
+        last_preconfirmed_block: Option<u64>,
+    ) -> Vec<PreconfirmationMessage> {
+        self.last_proposed_block = self.last_proposed_block.max(Some(block_id));
+        match self.last_block(last_preconfirmed_block) {
+            Some(last_block) => self.take_ready(last_block),
+            None => vec![],
+        }
+    }
+
+    /// Returns `msg` if it can be applied on top of `last_block`, otherwise holds it and asks
+    /// the peers for the missing blocks.
+    pub fn on_new_preconfirmation(
+        &mut self,
+        msg: PreconfirmationMessage,
+        last_block: Option<u64>,
+    ) -> Option<PreconfirmationMessage> {
+        match last_block {
+            Some(last_block) if msg.block_height > last_block + 1 => {
+                self.hold(msg, last_block);
+                self.request_missing(last_block);
+                None
+            }
+            _ => Some(msg),
+        }
+    }
+
+    /// Keeps `msg` until the blocks between `last_block` and it are applied.
+    pub fn hold(&mut self, msg: PreconfirmationMessage, last_block: u64) {
+        if msg.block_height <= last_block {
+            return;
+        }
+        if msg.block_height - last_block > MAX_PENDING_DISTANCE {
+            warn!(
+                "Dropping preconfirmation of block {}, {} blocks ahead of the last one",
+                msg.block_height,
+                msg.block_height - last_block
+            );
+            return;
+        }
+        debug!(
+            "Holding preconfirmation of block {}, missing blocks since {}",
+            msg.block_height, last_block
+        );
+        self.pending.insert(msg.block_height, msg);
+    }
+
+    /// Takes the held preconfirmations which follow `last_block`, in order.
+    pub fn take_ready(&mut self, mut last_block: u64) -> Vec<PreconfirmationMessage> {
+        let mut ready = vec![];
+        while let Some(entry) = self.pending.first_entry() {
+            if *entry.key() > last_block + 1 {
+                break;
+            }
+            let msg = entry.remove();
+            if msg.block_height == last_block + 1 {
+                last_block = msg.block_height;
+                ready.push(msg);
+            }
+        }
+        if !ready.is_empty() {
+            self.failed_requests = 0;
+        }
+        ready
+    }
+
+    pub fn request_done(&mut self) {
+        self.in_flight = None;
+    }
+
+    /// Counts a request which didn't fill the hole, after too many of them the held
+    /// preconfirmations are taken without the missing blocks.
+    pub fn request_failed(&mut self) -> Vec<PreconfirmationMessage> {
+        self.failed_requests += 1;
+        if self.failed_requests < MAX_FAILED_REQUESTS {
+            return vec![];
+        }
+        let Some(&first_pending) = self.pending.keys().next() else {
+            return vec![];
+        };
+        warn!(
+            "Skipping the blocks before {} after {} failed requests",
+            first_pending, self.failed_requests
+        );
+        self.take_ready(first_pending - 1)
+    }
+
+    /// Asks for the blocks between `last_block` and the first held one, unless a request is
+    /// already waiting for its response.
+    pub fn request_missing(&mut self, last_block: u64) {
+        if self.in_flight.is_some() {
+            return;
+        }
+        let Some(&first_pending) = self.pending.keys().next() else {
+            return;
+        };
+        if first_pending <= last_block + 1 {
+            return;
+        }
+        let request = SyncRequest::new(last_block + 1, first_pending - 1);
+        match self.requests_tx.try_send(request) {
+            Ok(()) => self.in_flight = Some(request),
+            Err(e) => warn!("Failed to request missing preconfirmations: {}", e),
+        }
+    }
+
+    /// Reports the peer which answered with forged or corrupted preconfirmations.
+    pub fn report_invalid_response(&self, peer: PeerId) {
+        let report = PeerReport {
+            peer,
+            offence: PeerOffence::InvalidMessage,
+        };
+        if let Err(e) = self.report_tx.try_send(report) {
+            error!("Failed to report peer {}: {}", peer, e);
+        }
+    }
+
+    /// Answers a peer with the preconfirmations of the requested blocks this node knows.
+    pub async fn serve(
+        preconfirmed_blocks: &PreconfirmedBlocks,
+        request: SyncRequest,
+    ) -> SyncResponse {
+        let preconfirmed_blocks = preconfirmed_blocks.lock().await;
+        let mut response = SyncResponse::default();
+        let mut size = 0;
+        for block_id in request.from_block..=request.to_block {
+            let Some(msg) = preconfirmed_blocks.get(&block_id) else {
+                continue;
+            };
+            // the stored preconfirmations were all encoded once, by their preconfer or a peer
+            let preconfirmation = match msg.try_into() {
+                Ok(preconfirmation) => preconfirmation,
+                Err(e) => {
+                    warn!(
+                        "Failed to encode the preconfirmation of block {}: {}",
+                        block_id, e
+                    );
+                    continue;
+                }
+            };
+            let bytes = WireMessage::Preconfirmation(preconfirmation).to_bytes();
+            size += bytes.len();
+            if size > MAX_RESPONSE_BYTES {
+                break;
+            }
+            response.messages.push(bytes);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::preconfirmation_proof::PreconfirmationProof;
+    use serde_json::json;
+    use std::{collections::HashMap, sync::Arc};
+    use tokio::sync::{mpsc, Mutex};
+
+    fn message(block_height: u64) -> PreconfirmationMessage {
+        PreconfirmationMessage::new(
+            block_height,
+            json!([[]]),
+            &[1, 2, 3],
+            PreconfirmationProof {
+                commit_hash: [1; 32],
+                signature: [2; 65],
+            },
+        )
+    }
+
+    #[test]
+    fn test_gap_is_requested_and_filled_in_order() {
+        let (requests_tx, mut requests_rx) = mpsc::channel(10);
+        let (report_tx, _report_rx) = mpsc::channel(10);
+        let mut sync = PreconfirmationSync::new(requests_tx, report_tx, None);
+
+        assert!(sync.on_new_preconfirmation(message(1), None).is_some());
+        assert!(sync.on_new_preconfirmation(message(11), Some(10)).is_some());
+        assert!(sync.on_new_preconfirmation(message(14), Some(11)).is_none());
+        assert_eq!(requests_rx.try_recv().unwrap(), SyncRequest::new(12, 13));
+        // no new request while waiting for the response
+        assert!(sync.on_new_preconfirmation(message(15), Some(11)).is_none());
+        assert!(requests_rx.try_recv().is_err());
+
+        assert!(sync.take_ready(11).is_empty());
+        let ready: Vec<u64> = sync
+            .take_ready(13)
+            .iter()
+            .map(|msg| msg.block_height)
+            .collect();
+        assert_eq!(ready, vec![14, 15]);
+
+        sync.request_done();
+        assert!(sync.on_new_preconfirmation(message(17), Some(15)).is_none());
+        assert_eq!(requests_rx.try_recv().unwrap(), SyncRequest::new(16, 16));
+    }
+
+    fn heights(messages: Vec<PreconfirmationMessage>) -> Vec<u64> {
+        messages.iter().map(|msg| msg.block_height).collect()
+    }
+
+    #[test]
+    fn test_hole_proposed_on_l1_is_released() {
+        let (requests_tx, _requests_rx) = mpsc::channel(10);
+        let (report_tx, _report_rx) = mpsc::channel(10);
+        let mut sync = PreconfirmationSync::new(requests_tx, report_tx, Some(3));
+
+        // the stored preconfirmations are older than the blocks proposed on L1
+        assert_eq!(sync.last_block(Some(1)), Some(3));
+        sync.hold(message(7), 3);
+        sync.hold(message(8), 3);
+        assert!(sync.on_block_proposed(5, Some(3)).is_empty());
+        assert_eq!(heights(sync.on_block_proposed(6, Some(3))), vec![7, 8]);
+        assert_eq!(sync.last_block(Some(2)), Some(6));
+    }
+
+    #[test]
+    fn test_hole_is_skipped_after_failed_requests() {
+        let (requests_tx, _requests_rx) = mpsc::channel(10);
+        let (report_tx, _report_rx) = mpsc::channel(10);
+        let mut sync = PreconfirmationSync::new(requests_tx, report_tx, None);
+
+        sync.hold(message(5), 1);
+        sync.hold(message(6), 1);
+        sync.hold(message(9), 1);
+        assert!(sync.request_failed().is_empty());
+        // progress resets the count
+        sync.hold(message(2), 1);
+        assert_eq!(heights(sync.take_ready(1)), vec![2]);
+        assert!(sync.request_failed().is_empty());
+        assert!(sync.request_failed().is_empty());
+        assert_eq!(heights(sync.request_failed()), vec![5, 6]);
+        // the next hole gets its own count
+        assert!(sync.request_failed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_serve_known_blocks() {
+        let preconfirmed_blocks = Arc::new(Mutex::new(HashMap::from([
+            (3, message(3)),
+            (5, message(5)),
+        ])));
+        let response =
+            PreconfirmationSync::serve(&preconfirmed_blocks, SyncRequest::new(2, 6)).await;
+        let blocks: Vec<u64> = response
+            .messages
+            .iter()
+            .map(|bytes| match WireMessage::from_bytes(bytes).unwrap() {
+                WireMessage::Preconfirmation(preconfirmation) => preconfirmation.block_height,
+                _ => panic!("not a preconfirmation"),
+            })
+            .collect();
+        assert_eq!(blocks, vec![3, 5]);
+    }
+}
@@ -0,0 +1,434 @@
+// Record of the preconfirmation commitments signed by the node, checked before each signature so
+// that two different tx lists are never committed for the same block, which is what
+// `proveIncorrectPreconfirmation` slashes. Like the EIP-3076 protection of the validators, it
+// survives restarts and moves between machines as a JSON interchange file.
+use crate::utils::types::{L2TxListHash, PreconferAddress};
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+const PROTECTION_FILE_NAME: &str = "slashing_protection.json";
+const INTERCHANGE_FORMAT_VERSION: &str = "1";
+// Commitments this far below the latest signed block are dropped, and signing below them is
+// refused since their records are gone
+const KEPT_BLOCK_DISTANCE: u64 = 256;
+
+/// JSON interchange format, which is also the format of the protection file. The numbers are
+/// decimal strings and the hashes 0x-prefixed hex, as in EIP-3076.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Interchange {
+    pub metadata: InterchangeMetadata,
+    pub data: Vec<InterchangeSigner>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InterchangeMetadata {
+    pub interchange_format_version: String,
+}
+
+/// Commitments signed by one preconfer key on one chain.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InterchangeSigner {
+    pub address: String,
+    pub chain_id: String,
+    /// Blocks below are refused.
+    #[serde(default)]
+    pub min_block_height: Option<String>,
+    pub signed_commitments: Vec<InterchangeCommitment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InterchangeCommitment {
+    pub block_height: String,
+    pub tx_list_hash: String,
+}
+
+#[derive(Default, Clone)]
+struct SignerHistory {
+    min_block_height: u64,
+    commitments: BTreeMap<u64, L2TxListHash>,
+}
+
+pub struct SlashingProtection {
+    path: PathBuf,
+    history: BTreeMap<(PreconferAddress, u64), SignerHistory>,
+}
+
+impl SlashingProtection {
+    pub fn open(data_dir: &str) -> Result<Self, Error> {
+        fs::create_dir_all(data_dir)?;
+        let path = Path::new(data_dir).join(PROTECTION_FILE_NAME);
+        let mut protection = Self {
+            path,
+            history: BTreeMap::new(),
+        };
+        match fs::read(&protection.path) {
+            Ok(bytes) => {
+                let interchange = serde_json::from_slice(&bytes).map_err(|e| {
+                    anyhow::anyhow!("Failed to parse {}: {}", protection.path.display(), e)
+                })?;
+                protection.history = parse_interchange(&interchange)?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(anyhow::anyhow!(
+                    "Failed to read {}: {}",
+                    protection.path.display(),
+                    e
+                ))
+            }
+        }
+        info!(
+            "Slashing protection opened at {} with {} signed commitments",
+            protection.path.display(),
+            protection.commitments_count()
+        );
+        Ok(protection)
+    }
+
+    /// Records the commitment of `signer` to `tx_list_hash` for `block_height`, and fails if it
+    /// committed to another tx list for that block. Signing the same commitment again is allowed.
+    /// The record is saved before returning, so it can't be lost once the commitment is signed.
+    pub fn check_and_insert(
+        &mut self,
+        signer: PreconferAddress,
+        chain_id: u64,
+        block_height: u64,
+        tx_list_hash: L2TxListHash,
+    ) -> Result<(), Error> {
+        let key = (signer, chain_id);
+        let mut history = self.history.get(&key).cloned().unwrap_or_default();
+        if block_height < history.min_block_height {
+            return Err(anyhow::anyhow!(
+                "Slashing protection: block {} is below the protected range starting at {}",
+                block_height,
+                history.min_block_height
+            ));
+        }
+        match history.commitments.get(&block_height) {
+            Some(signed) if *signed == tx_list_hash => return Ok(()),
+            Some(signed) => {
+                return Err(anyhow::anyhow!(
+                "Slashing protection: block {} already committed to tx list 0x{}, refusing 0x{}",
+                block_height,
+                hex::encode(signed),
+                hex::encode(tx_list_hash)
+            ))
+            }
+            None => {}
+        }
+
+        history.commitments.insert(block_height, tx_list_hash);
+        let latest = *history.commitments.keys().next_back().unwrap_or(&0);
+        history.min_block_height = history
+            .min_block_height
+            .max(latest.saturating_sub(KEPT_BLOCK_DISTANCE));
+        let min_block_height = history.min_block_height;
+        history
+            .commitments
+            .retain(|height, _| *height >= min_block_height);
+
+        // the record is only kept in memory once it is on disk
+        let previous = self.history.insert(key, history);
+        if let Err(e) = self.save() {
+            match previous {
+                Some(previous) => self.history.insert(key, previous),
+                None => self.history.remove(&key),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn export(&self) -> Interchange {
+        Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: INTERCHANGE_FORMAT_VERSION.to_string(),
+            },
+            data: self
+                .history
+                .iter()
+                .map(|((signer, chain_id), history)| InterchangeSigner {
+                    address: format!("0x{}", hex::encode(signer)),
+                    chain_id: chain_id.to_string(),
+                    min_block_height: Some(history.min_block_height.to_string()),
+                    signed_commitments: history
+                        .commitments
+                        .iter()
+                        .map(|(block_height, tx_list_hash)| InterchangeCommitment {
+                            block_height: block_height.to_string(),
+                            tx_list_hash: format!("0x{}", hex::encode(tx_list_hash)),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn export_to_file(&self, path: &str) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(&self.export())?;
+        fs::write(path, json).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path, e))?;
+        info!(
+            "Exported {} signed commitments to {}",
+            self.commitments_count(),
+            path
+        );
+        Ok(())
+    }
+
+    /// Merges the commitments of `interchange` into the protection. Nothing is imported if one of
+    /// them conflicts with a recorded commitment, since signing either would be slashable.
+    /// Returns the number of new commitments.
+    pub fn import(&mut self, interchange: &Interchange) -> Result<usize, Error> {
+        let imported = parse_interchange(interchange)?;
+        let mut merged = self.history.clone();
+        let mut new_commitments = 0;
+        for (key, imported) in imported {
+            let history = merged.entry(key).or_default();
+            history.min_block_height = history.min_block_height.max(imported.min_block_height);
+            for (block_height, tx_list_hash) in imported.commitments {
+                match history.commitments.get(&block_height) {
+                    Some(signed) if *signed != tx_list_hash => {
+                        return Err(anyhow::anyhow!(
+                            "Slashing protection: imported commitment of 0x{} to block {} conflicts with the recorded one",
+                            hex::encode(key.0),
+                            block_height
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        history.commitments.insert(block_height, tx_list_hash);
+                        new_commitments += 1;
+                    }
+                }
+            }
+            let min_block_height = history.min_block_height;
+            history
+                .commitments
+                .retain(|height, _| *height >= min_block_height);
+        }
+
+        let previous = std::mem::replace(&mut self.history, merged);
+        if let Err(e) = self.save() {
+            self.history = previous;
+            return Err(e);
+        }
+        Ok(new_commitments)
+    }
+
+    pub fn import_from_file(&mut self, path: &str) -> Result<(), Error> {
+        let bytes =
+            fs::read(path).map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path, e))?;
+        let interchange: Interchange = serde_json::from_slice(&bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path, e))?;
+        let new_commitments = self.import(&interchange)?;
+        info!(
+            "Imported {} new signed commitments from {}",
+            new_commitments, path
+        );
+        Ok(())
+    }
+
+    fn commitments_count(&self) -> usize {
+        self.history
+            .values()
+            .map(|history| history.commitments.len())
+            .sum()
+    }
+
+    // Written to a temporary file first, so that a crash while saving keeps the previous records
+    fn save(&self) -> Result<(), Error> {
+        let json = serde_json::to_vec(&self.export())?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        {
+            let mut tmp = File::create(&tmp_path)?;
+            tmp.write_all(&json)?;
+            tmp.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn parse_interchange(
+    interchange: &Interchange,
+) -> Result<BTreeMap<(PreconferAddress, u64), SignerHistory>, Error> {
+    if interchange.metadata.interchange_format_version != INTERCHANGE_FORMAT_VERSION {
+        return Err(anyhow::anyhow!(
+            "Unsupported slashing protection interchange format version {}",
+            interchange.metadata.interchange_format_version
+        ));
+    }
+    let mut history: BTreeMap<(PreconferAddress, u64), SignerHistory> = BTreeMap::new();
+    for signer in &interchange.data {
+        let address: PreconferAddress = parse_hex(&signer.address)?;
+        let chain_id = parse_number(&signer.chain_id)?;
+        let entry = history.entry((address, chain_id)).or_default();
+        if let Some(min_block_height) = &signer.min_block_height {
+            entry.min_block_height = entry.min_block_height.max(parse_number(min_block_height)?);
+        }
+        for commitment in &signer.signed_commitments {
+            let block_height = parse_number(&commitment.block_height)?;
+            let tx_list_hash: L2TxListHash = parse_hex(&commitment.tx_list_hash)?;
+            if let Some(other) = entry.commitments.insert(block_height, tx_list_hash) {
+                if other != tx_list_hash {
+                    return Err(anyhow::anyhow!(
+                        "Slashing protection: conflicting commitments of {} to block {}",
+                        signer.address,
+                        block_height
+                    ));
+                }
+                warn!(
+                    "Duplicate commitment of {} to block {}",
+                    signer.address, block_height
+                );
+            }
+        }
+    }
+    Ok(history)
+}
+
+fn parse_number(value: &str) -> Result<u64, Error> {
+    value
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid number {:?}: {}", value, e))
+}
+
+fn parse_hex<const N: usize>(value: &str) -> Result<[u8; N], Error> {
+    let bytes = hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| anyhow::anyhow!("Invalid hex {:?}: {}", value, e))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Invalid hex {:?}: expected {} bytes", value, N))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNER: PreconferAddress = [7; 20];
+    const CHAIN_ID: u64 = 167;
+
+    #[test]
+    fn test_conflicting_commitment_is_refused() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        let mut protection = SlashingProtection::open(data_dir).unwrap();
+        protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [1; 32])
+            .unwrap();
+        // signing the same commitment again is safe
+        protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [1; 32])
+            .unwrap();
+        assert!(protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [2; 32])
+            .is_err());
+        // other blocks, chains and keys are independent
+        protection
+            .check_and_insert(SIGNER, CHAIN_ID, 11, [2; 32])
+            .unwrap();
+        protection
+            .check_and_insert(SIGNER, CHAIN_ID + 1, 10, [2; 32])
+            .unwrap();
+        protection
+            .check_and_insert([8; 20], CHAIN_ID, 10, [2; 32])
+            .unwrap();
+
+        // the records survive a restart
+        let mut protection = SlashingProtection::open(data_dir).unwrap();
+        assert!(protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [2; 32])
+            .is_err());
+        assert_eq!(protection.commitments_count(), 4);
+    }
+
+    #[test]
+    fn test_old_blocks_are_pruned_and_refused() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let data_dir = temp_dir.path().to_str().unwrap();
+        let mut protection = SlashingProtection::open(data_dir).unwrap();
+        protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [1; 32])
+            .unwrap();
+        protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10 + KEPT_BLOCK_DISTANCE + 1, [1; 32])
+            .unwrap();
+        assert_eq!(protection.commitments_count(), 1);
+        assert!(protection
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [1; 32])
+            .is_err());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let mut source = SlashingProtection::open(source_dir.path().to_str().unwrap()).unwrap();
+        source
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [1; 32])
+            .unwrap();
+        source
+            .check_and_insert(SIGNER, CHAIN_ID, 11, [2; 32])
+            .unwrap();
+        let json = serde_json::to_string(&source.export()).unwrap();
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let mut target = SlashingProtection::open(target_dir.path().to_str().unwrap()).unwrap();
+        target
+            .check_and_insert(SIGNER, CHAIN_ID, 11, [2; 32])
+            .unwrap();
+        let interchange: Interchange = serde_json::from_str(&json).unwrap();
+        assert_eq!(target.import(&interchange).unwrap(), 1);
+        assert!(target
+            .check_and_insert(SIGNER, CHAIN_ID, 10, [3; 32])
+            .is_err());
+
+        // a conflicting import changes nothing
+        let conflicting_dir = tempfile::tempdir().unwrap();
+        let mut conflicting =
+            SlashingProtection::open(conflicting_dir.path().to_str().unwrap()).unwrap();
+        conflicting
+            .check_and_insert(SIGNER, CHAIN_ID, 11, [3; 32])
+            .unwrap();
+        conflicting
+            .check_and_insert(SIGNER, CHAIN_ID, 12, [3; 32])
+            .unwrap();
+        assert!(conflicting.import(&interchange).is_err());
+        assert_eq!(conflicting.commitments_count(), 2);
+    }
+
+    #[test]
+    fn test_interchange_format() {
+        let interchange: Interchange = serde_json::from_str(
+            r#"{
+                "metadata": { "interchange_format_version": "1" },
+                "data": [{
+                    "address": "0x0707070707070707070707070707070707070707",
+                    "chain_id": "167",
+                    "signed_commitments": [{
+                        "block_height": "5",
+                        "tx_list_hash": "0x0101010101010101010101010101010101010101010101010101010101010101"
+                    }]
+                }]
+            }"#,
+        )
+        .unwrap();
+        let history = parse_interchange(&interchange).unwrap();
+        assert_eq!(history[&(SIGNER, CHAIN_ID)].commitments[&5], [1; 32]);
+        assert_eq!(history[&(SIGNER, CHAIN_ID)].min_block_height, 0);
+
+        let unsupported = Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: "2".to_string(),
+            },
+            data: vec![],
+        };
+        assert!(parse_interchange(&unsupported).is_err());
+    }
+}
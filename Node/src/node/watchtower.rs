@@ -0,0 +1,290 @@
+// Special mode of the AVS node that audits all the preconfers without preconfirming: every
+// preconfirmation gossiped is checked against the block proposed on L1 and disputed if it
+// doesn't match. The lookaheads are checked by the lookahead updated event receiver. No
+// validator or BLS key is needed, only the ECDSA key paying for the disputes.
+use super::{
+    dispute_scheduler::{Dispute, DisputeScheduler},
+    equivocation::{self, Equivocation, Equivocations},
+    preconfirmation_message::PreconfirmationMessage,
+    preconfirmation_sync::MAX_PENDING_DISTANCE,
+    wire::WireMessage,
+    Node,
+};
+use crate::{
+    ethereum_l1::{block_proposed::BlockProposed, EthereumL1},
+    metrics,
+};
+use p2p_network::{
+    network::{GossipMessage, MessageAcceptance, ValidationResult},
+    topics::Topic,
+};
+use std::{collections::BTreeMap, sync::Arc};
+use tokio::sync::mpsc::{Receiver, Sender};
+use tracing::{debug, error, info, warn};
+
+/// Every distinct commitment received for the blocks which are not proposed yet, up to
+/// `MAX_PENDING_DISTANCE` blocks after the last proposed one.
+#[derive(Default)]
+struct Commitments {
+    by_block: BTreeMap<u64, Vec<PreconfirmationMessage>>,
+    last_proposed_block: Option<u64>,
+}
+
+impl Commitments {
+    /// Adds `msg` unless it is known already, returns the equivocations it makes or none if its
+    /// block is already proposed or too far ahead.
+    fn insert(&mut self, msg: PreconfirmationMessage) -> Option<Vec<Equivocation>> {
+        let in_range = match self.last_proposed_block {
+            Some(last_proposed_block) => {
+                msg.block_height > last_proposed_block
+                    && msg.block_height - last_proposed_block <= MAX_PENDING_DISTANCE
+            }
+            // until a proposal is known, only the number of pending blocks is bounded
+            None => {
+                self.by_block.contains_key(&msg.block_height)
+                    || (self.by_block.len() as u64) < MAX_PENDING_DISTANCE
+            }
+        };
+        if !in_range {
+            warn!(
+                "Ignoring preconfirmation of block {}, out of the pending blocks after {:?}",
+                msg.block_height, self.last_proposed_block
+            );
+            return None;
+        }
+        let known = self.by_block.entry(msg.block_height).or_default();
+        if known.iter().any(|k| k.tx_list_hash == msg.tx_list_hash) {
+            return Some(vec![]);
+        }
+        let equivocations = known
+            .iter()
+            .filter_map(|k| Equivocation::detect(k, &msg))
+            .collect();
+        known.push(msg);
+        Some(equivocations)
+    }
+
+    /// Removes the commitments of `block_id` and drops the ones of the earlier blocks, which
+    /// won't be proposed anymore.
+    fn take(&mut self, block_id: u64) -> Vec<PreconfirmationMessage> {
+        self.last_proposed_block = self.last_proposed_block.max(Some(block_id));
+        let commitments = self.by_block.remove(&block_id).unwrap_or_default();
+        self.by_block = self.by_block.split_off(&block_id);
+        commitments
+    }
+}
+
+pub struct Watchtower {
+    ethereum_l1: Arc<EthereumL1>,
+    chain_id: u64,
+    require_registered_author: bool,
+    p2p_validation_tx: Sender<ValidationResult>,
+    dispute_scheduler: DisputeScheduler,
+    commitments: Commitments,
+    equivocations: Equivocations,
+}
+
+impl Watchtower {
+    pub fn new(
+        ethereum_l1: Arc<EthereumL1>,
+        chain_id: u64,
+        require_registered_author: bool,
+        p2p_validation_tx: Sender<ValidationResult>,
+        dispute_scheduler: DisputeScheduler,
+    ) -> Self {
+        Self {
+            ethereum_l1,
+            chain_id,
+            require_registered_author,
+            p2p_validation_tx,
+            dispute_scheduler,
+            commitments: Commitments::default(),
+            equivocations: Equivocations::default(),
+        }
+    }
+
+    pub async fn start(
+        mut self,
+        mut block_proposed_rx: Receiver<BlockProposed>,
+        mut p2p_to_node_rx: Receiver<GossipMessage>,
+    ) {
+        info!("Starting watchtower");
+        match self
+            .ethereum_l1
+            .execution_layer
+            .get_last_proposed_block_id()
+            .await
+        {
+            Ok(block_id) => self.commitments.last_proposed_block = Some(block_id),
+            Err(e) => error!("Failed to get the last proposed block: {}", e),
+        }
+        loop {
+            tokio::select! {
+                Some(block_proposed) = block_proposed_rx.recv() => {
+                    self.on_block_proposed(block_proposed).await;
+                }
+                Some(message) = p2p_to_node_rx.recv() => {
+                    self.on_gossip_message(message).await;
+                }
+                else => {
+                    error!("Watchtower channels closed");
+                    return;
+                }
+            }
+        }
+    }
+
+    // Every commitment of the block is disputed, the scheduler drops the correct ones once the
+    // contract confirms them
+    async fn on_block_proposed(&mut self, block_proposed: BlockProposed) {
+        let block_id = block_proposed.block_id();
+        let commitments = self.commitments.take(block_id);
+        debug!(
+            "Watchtower checking {} preconfirmations of the proposed block {}",
+            commitments.len(),
+            block_id
+        );
+        for msg in commitments {
+            self.dispute_scheduler.schedule(Dispute::Preconfirmation {
+                chain_id: self.chain_id,
+                tx_list_hash: msg.tx_list_hash,
+                signature: msg.proof.signature,
+                block_proposed: Box::new(block_proposed.clone()),
+            });
+        }
+        self.equivocations
+            .lock()
+            .await
+            .retain(|&block_height, _| block_height > block_id);
+    }
+
+    async fn on_gossip_message(&mut self, message: GossipMessage) {
+        if message.topic == Topic::Preconfirmations {
+            metrics::PRECONFIRMATIONS_RECEIVED.inc();
+        }
+        let acceptance = match Node::decode_p2p_message(&message) {
+            Ok(WireMessage::Preconfirmation(preconfirmation)) => {
+                match Node::validate_preconfirmation(
+                    preconfirmation,
+                    &message,
+                    self.require_registered_author,
+                    self.ethereum_l1.clone(),
+                    self.chain_id,
+                )
+                .await
+                {
+                    Ok(msg) => match self.commitments.insert(msg) {
+                        Some(equivocations) => {
+                            for equivocation in equivocations {
+                                equivocation::record(&self.equivocations, equivocation).await;
+                            }
+                            MessageAcceptance::Accept
+                        }
+                        None => MessageAcceptance::Ignore,
+                    },
+                    Err(acceptance) => acceptance,
+                }
+            }
+            Ok(WireMessage::LookaheadAnnouncement(announcement)) => {
+                Node::validate_lookahead_announcement(
+                    announcement,
+                    &self.ethereum_l1,
+                    self.chain_id,
+                )
+                .await
+            }
+            Ok(WireMessage::PreconferHandover(handover)) => {
+                Node::validate_preconfer_handover(handover, &self.ethereum_l1, self.chain_id).await
+            }
+            Err(acceptance) => acceptance,
+        };
+        Node::report_validation(&self.p2p_validation_tx, &message, acceptance);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::preconfirmation_message::signed_preconfirmation;
+    use alloy::signers::local::PrivateKeySigner;
+    use serde_json::json;
+
+    #[test]
+    fn test_every_distinct_commitment_is_kept() {
+        let signer = PrivateKeySigner::random();
+        let other = PrivateKeySigner::random();
+        let mut commitments = Commitments::default();
+
+        assert!(commitments
+            .insert(signed_preconfirmation(5, json!([]), &[1], &signer))
+            .unwrap()
+            .is_empty());
+        // the same commitment from another peer
+        assert!(commitments
+            .insert(signed_preconfirmation(5, json!([]), &[1], &signer))
+            .unwrap()
+            .is_empty());
+        // a conflict between two preconfers is not an equivocation, but is still checked
+        assert!(commitments
+            .insert(signed_preconfirmation(5, json!([]), &[2], &other))
+            .unwrap()
+            .is_empty());
+        let equivocations = commitments
+            .insert(signed_preconfirmation(5, json!([]), &[3], &signer))
+            .unwrap();
+        assert_eq!(equivocations.len(), 1);
+        assert_eq!(equivocations[0].preconfer, signer.address());
+
+        assert_eq!(commitments.take(5).len(), 3);
+        assert!(commitments.take(5).is_empty());
+    }
+
+    #[test]
+    fn test_take_drops_earlier_blocks() {
+        let signer = PrivateKeySigner::random();
+        let mut commitments = Commitments::default();
+        for block_height in 4..=7 {
+            commitments.insert(signed_preconfirmation(
+                block_height,
+                json!([]),
+                &[1],
+                &signer,
+            ));
+        }
+
+        assert_eq!(commitments.take(6).len(), 1);
+        assert!(commitments.take(4).is_empty());
+        assert_eq!(commitments.take(7).len(), 1);
+    }
+
+    #[test]
+    fn test_pending_blocks_are_bounded() {
+        let signer = PrivateKeySigner::random();
+        let mut commitments = Commitments::default();
+        for block_height in 1..=MAX_PENDING_DISTANCE {
+            assert!(commitments
+                .insert(signed_preconfirmation(
+                    block_height,
+                    json!([]),
+                    &[1],
+                    &signer
+                ))
+                .is_some());
+        }
+        let far = MAX_PENDING_DISTANCE + 10;
+        assert!(commitments
+            .insert(signed_preconfirmation(far, json!([]), &[1], &signer))
+            .is_none());
+
+        commitments.take(10);
+        assert!(commitments
+            .insert(signed_preconfirmation(10, json!([]), &[2], &signer))
+            .is_none());
+        assert!(commitments
+            .insert(signed_preconfirmation(far, json!([]), &[1], &signer))
+            .is_some());
+        assert!(commitments
+            .insert(signed_preconfirmation(far + 1, json!([]), &[1], &signer))
+            .is_none());
+    }
+}
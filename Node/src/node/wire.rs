@@ -0,0 +1,260 @@
+// Wire format of the AVS gossip messages: an SSZ envelope holding the protocol version and the
+// message itself, an SSZ union whose selector is the message type. Messages of an unknown
+// version are ignored rather than rejected, so a new version can be rolled out while the older
+// nodes are still running.
+use p2p_network::topics::Topic;
+use ssz::{Decode, DecodeError, Encode};
+use ssz_derive::{Decode, Encode};
+use std::fmt;
+
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Fixed size byte array, SSZ only implements a few sizes of `[u8; N]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedBytes<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Encode for FixedBytes<N> {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        N
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        N
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.0);
+    }
+}
+
+impl<const N: usize> Decode for FixedBytes<N> {
+    fn is_ssz_fixed_len() -> bool {
+        true
+    }
+
+    fn ssz_fixed_len() -> usize {
+        N
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        bytes
+            .try_into()
+            .map(FixedBytes)
+            .map_err(|_| DecodeError::InvalidByteLength {
+                len: bytes.len(),
+                expected: N,
+            })
+    }
+}
+
+pub type WireAddress = FixedBytes<20>;
+pub type WireSignature = FixedBytes<65>;
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct Preconfirmation {
+    pub block_height: u64,
+    /// Tx lists of the block, every transaction in its EIP-2718 encoding.
+    pub tx_lists: Vec<Vec<Vec<u8>>>,
+    pub tx_list_hash: [u8; 32],
+    pub commit_hash: [u8; 32],
+    pub signature: WireSignature,
+}
+
+/// Lookahead of an epoch, announced by one of its preconfers.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct LookaheadAnnouncement {
+    pub chain_id: u64,
+    pub epoch: u64,
+    /// Preconfer of every slot of the epoch.
+    pub preconfers: Vec<WireAddress>,
+    pub signature: WireSignature,
+}
+
+/// Sent by a preconfer after its last preconfirmation, before the next preconfer takes over.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct PreconferHandover {
+    pub chain_id: u64,
+    /// Last L1 slot of the preconfer.
+    pub slot: u64,
+    pub last_block_id: u64,
+    pub next_preconfer: WireAddress,
+    pub signature: WireSignature,
+}
+
+/// The announcements are signed over their SSZ encoding with an empty signature.
+pub trait Signed: Encode + Clone {
+    fn signature(&self) -> &WireSignature;
+
+    fn signature_mut(&mut self) -> &mut WireSignature;
+
+    fn signed_data(&self) -> Vec<u8> {
+        let mut unsigned = self.clone();
+        *unsigned.signature_mut() = FixedBytes([0; 65]);
+        unsigned.as_ssz_bytes()
+    }
+}
+
+impl Signed for LookaheadAnnouncement {
+    fn signature(&self) -> &WireSignature {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut WireSignature {
+        &mut self.signature
+    }
+}
+
+impl Signed for PreconferHandover {
+    fn signature(&self) -> &WireSignature {
+        &self.signature
+    }
+
+    fn signature_mut(&mut self) -> &mut WireSignature {
+        &mut self.signature
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[ssz(enum_behaviour = "union")]
+pub enum WireMessage {
+    Preconfirmation(Preconfirmation),
+    LookaheadAnnouncement(LookaheadAnnouncement),
+    PreconferHandover(PreconferHandover),
+}
+
+#[derive(Encode, Decode)]
+struct Envelope {
+    version: u16,
+    message: WireMessage,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum WireError {
+    UnsupportedVersion(u16),
+    Invalid(String),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::UnsupportedVersion(version) => {
+                write!(f, "unsupported protocol version {}", version)
+            }
+            WireError::Invalid(e) => write!(f, "invalid message: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WireError {}
+
+impl WireMessage {
+    /// Topic the message is published on.
+    pub fn topic(&self) -> Topic {
+        match self {
+            WireMessage::Preconfirmation(_) => Topic::Preconfirmations,
+            WireMessage::LookaheadAnnouncement(_) => Topic::LookaheadAnnouncements,
+            WireMessage::PreconferHandover(_) => Topic::PreconferHandovers,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        Envelope {
+            version: PROTOCOL_VERSION,
+            message: self.clone(),
+        }
+        .as_ssz_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WireError> {
+        // the version is the first fixed size field, it is read before the rest of the envelope
+        // whose layout depends on it
+        let version = bytes
+            .get(..2)
+            .map(|version| u16::from_le_bytes([version[0], version[1]]))
+            .ok_or_else(|| WireError::Invalid("missing version".to_string()))?;
+        if version != PROTOCOL_VERSION {
+            return Err(WireError::UnsupportedVersion(version));
+        }
+        Envelope::from_ssz_bytes(bytes)
+            .map(|envelope| envelope.message)
+            .map_err(|e| WireError::Invalid(format!("{:?}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handover() -> WireMessage {
+        WireMessage::PreconferHandover(PreconferHandover {
+            chain_id: 167,
+            slot: 64,
+            last_block_id: 100,
+            next_preconfer: FixedBytes([7; 20]),
+            signature: FixedBytes([9; 65]),
+        })
+    }
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let messages = [
+            WireMessage::Preconfirmation(Preconfirmation {
+                block_height: 5,
+                tx_lists: vec![vec![vec![2, 1], vec![0xc0]], vec![]],
+                tx_list_hash: [1; 32],
+                commit_hash: [2; 32],
+                signature: FixedBytes([3; 65]),
+            }),
+            WireMessage::LookaheadAnnouncement(LookaheadAnnouncement {
+                chain_id: 167,
+                epoch: 2,
+                preconfers: vec![FixedBytes([4; 20]), FixedBytes([5; 20])],
+                signature: FixedBytes([6; 65]),
+            }),
+            handover(),
+        ];
+        for message in messages {
+            let bytes = message.to_bytes();
+            assert_eq!(&bytes[..2], &PROTOCOL_VERSION.to_le_bytes());
+            assert_eq!(WireMessage::from_bytes(&bytes).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn test_unknown_versions_and_types() {
+        let mut bytes = handover().to_bytes();
+        bytes[..2].copy_from_slice(&2u16.to_le_bytes());
+        assert_eq!(
+            WireMessage::from_bytes(&bytes),
+            Err(WireError::UnsupportedVersion(2))
+        );
+
+        // the union selector follows the version and the message offset
+        let mut bytes = handover().to_bytes();
+        bytes[6] = 10;
+        assert!(matches!(
+            WireMessage::from_bytes(&bytes),
+            Err(WireError::Invalid(_))
+        ));
+        assert!(matches!(
+            WireMessage::from_bytes(&[1]),
+            Err(WireError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_signed_data_ignores_signature() {
+        let WireMessage::PreconferHandover(mut handover) = handover() else {
+            unreachable!()
+        };
+        let signed_data = handover.signed_data();
+        handover.signature = FixedBytes([1; 65]);
+        assert_eq!(handover.signed_data(), signed_data);
+        handover.last_block_id += 1;
+        assert_ne!(handover.signed_data(), signed_data);
+    }
+}
@@ -1,18 +1,38 @@
-use p2p_network::network::{P2PNetwork, P2PNetworkConfig};
+use crate::metrics;
+use alloy::primitives::Address;
+use p2p_network::network::{
+    GossipMessage, P2PNetwork, P2PNetworkConfig, PeerId, PeerReport, ValidationResult,
+};
+use p2p_network::sync::SyncChannels;
+use p2p_network::topics::Topic;
+use std::sync::atomic::Ordering;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task;
+use tokio::time::{interval, Duration};
 use tracing::info;
 
 pub struct AVSp2p {
-    node_tx: Sender<Vec<u8>>,
-    node_to_p2p_rx: Receiver<Vec<u8>>,
+    node_tx: Sender<GossipMessage>,
+    node_to_p2p_rx: Receiver<(Topic, Vec<u8>)>,
+    validation_rx: Receiver<ValidationResult>,
+    peer_report_rx: Receiver<PeerReport>,
+    sync_channels: SyncChannels,
 }
 
 impl AVSp2p {
-    pub fn new(node_tx: Sender<Vec<u8>>, node_to_p2p_rx: Receiver<Vec<u8>>) -> Self {
+    pub fn new(
+        node_tx: Sender<GossipMessage>,
+        node_to_p2p_rx: Receiver<(Topic, Vec<u8>)>,
+        validation_rx: Receiver<ValidationResult>,
+        peer_report_rx: Receiver<PeerReport>,
+        sync_channels: SyncChannels,
+    ) -> Self {
         AVSp2p {
             node_tx,
             node_to_p2p_rx,
+            validation_rx,
+            peer_report_rx,
+            sync_channels,
         }
     }
 
@@ -20,10 +40,52 @@ impl AVSp2p {
     pub async fn start(self, config: P2PNetworkConfig) {
         info!("Starting P2P network");
 
-        let mut p2p = P2PNetwork::new(&config, self.node_tx.clone(), self.node_to_p2p_rx).await;
+        let mut p2p = P2PNetwork::new(
+            &config,
+            self.node_tx.clone(),
+            self.node_to_p2p_rx,
+            self.validation_rx,
+            self.peer_report_rx,
+            self.sync_channels,
+        )
+        .await;
+
+        let connected_peers = p2p.connected_peers();
+        task::spawn(async move {
+            let mut interval = interval(Duration::from_secs(1));
+            loop {
+                interval.tick().await;
+                metrics::GOSSIPSUB_PEERS.set(connected_peers.load(Ordering::Relaxed) as i64);
+            }
+        });
 
         task::spawn(async move {
             p2p.run(&config).await;
         });
     }
 }
+
+/// Ethereum address of the secp256k1 key the peer id of `author` was derived from.
+pub fn author_address(author: &PeerId) -> Option<Address> {
+    let public_key = p2p_network::secp256k1_public_key(author)?;
+    // skip the 0x04 prefix of the uncompressed key
+    Some(Address::from_raw_public_key(&public_key[1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::local::PrivateKeySigner;
+
+    #[test]
+    fn test_author_address() {
+        let signer = PrivateKeySigner::random();
+        let mut secret = signer.to_bytes().to_vec();
+        let keypair = p2p_network::secp256k1_from_bytes(&mut secret).unwrap();
+        let author = keypair.public().to_peer_id();
+        assert_eq!(author_address(&author), Some(signer.address()));
+
+        // not derived from a public key
+        assert_eq!(author_address(&PeerId::random()), None);
+    }
+}
@@ -27,8 +27,9 @@ mod tests {
 
     use crate::{
         bls::BLSService,
-        ethereum_l1::EthereumL1,
+        ethereum_l1::{gas_oracle::GasOracleConfig, EthereumL1},
         registration::Registration,
+        signer::LocalEcdsaSigner,
         utils::config::{AvsContractAddresses, ContractAddresses, EigenLayerContractAddresses},
     };
     fn get_contract_address(output: &Cow<'_, str>, contract_name: &str) -> String {
@@ -160,19 +161,19 @@ mod tests {
 
         // Create AVS contract addresses
         let avs_contracts = AvsContractAddresses {
-            preconf_task_manager: preconf_task_manager,
-            directory: avs_directory,
-            service_manager: preconf_service_manager,
-            preconf_registry: preconf_regestry.clone(),
+            preconf_task_manager: preconf_task_manager.parse().unwrap(),
+            directory: avs_directory.parse().unwrap(),
+            service_manager: preconf_service_manager.parse().unwrap(),
+            preconf_registry: preconf_regestry.parse().unwrap(),
         };
 
         // Create Eigenlayer contract addresses
         let eigen_layer = EigenLayerContractAddresses {
-            strategy_manager: strategy_manager,
-            slasher: slasher,
+            strategy_manager: strategy_manager.parse().unwrap(),
+            slasher: slasher.parse().unwrap(),
         };
         let contracts = ContractAddresses {
-            taiko_l1: mock_address,
+            taiko_l1: mock_address.parse().unwrap(),
             eigen_layer,
             avs: avs_contracts,
         };
@@ -181,7 +182,7 @@ mod tests {
         // Create an Ethereum L1 client
         let eth = EthereumL1::new(
             &ws_rpc_url,
-            &pk_str,
+            Arc::new(LocalEcdsaSigner::new(&pk_str).unwrap()),
             &contracts,
             &concensus_url_str,
             12000,
@@ -190,6 +191,7 @@ mod tests {
             bls_service.clone(),
             1,
             3,
+            GasOracleConfig::default(),
         )
         .await
         .unwrap();
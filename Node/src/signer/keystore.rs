@@ -0,0 +1,397 @@
+// Loads the signing keys from encrypted keystores: EIP-2335 for the BLS key and Web3 Secret
+// Storage (v3) for the ECDSA key. Both formats derive a key with scrypt or pbkdf2 and encrypt
+// the secret with AES-128-CTR, they only differ in the layout and in the checksum. Decrypted
+// key material is kept in zeroizing buffers.
+use super::LocalEcdsaSigner;
+use crate::bls::BLSService;
+use aes::Aes128;
+use alloy::primitives::keccak256;
+use anyhow::Error;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use hmac::Hmac;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+/// Where a signing key is loaded from.
+pub enum KeySource {
+    /// Hex encoded private key.
+    PrivateKey(Zeroizing<String>),
+    Keystore {
+        path: String,
+        password_path: String,
+    },
+}
+
+pub fn load_ecdsa_signer(source: &KeySource) -> Result<LocalEcdsaSigner, Error> {
+    match source {
+        KeySource::PrivateKey(private_key) => LocalEcdsaSigner::new(private_key),
+        KeySource::Keystore { .. } => LocalEcdsaSigner::from_slice(&load_ecdsa_secret(source)?),
+    }
+}
+
+/// Returns the raw 32 bytes ECDSA private key, e.g. to derive the p2p identity from it.
+pub fn load_ecdsa_secret(source: &KeySource) -> Result<Zeroizing<Vec<u8>>, Error> {
+    match source {
+        KeySource::PrivateKey(private_key) => {
+            Ok(Zeroizing::new(alloy::hex::decode(private_key.as_str())?))
+        }
+        KeySource::Keystore {
+            path,
+            password_path,
+        } => {
+            let password = read_password(password_path)?;
+            decrypt_ecdsa_keystore(&read_keystore(path)?, password.as_bytes())
+        }
+    }
+}
+
+pub fn load_bls_signer(source: &KeySource) -> Result<BLSService, Error> {
+    match source {
+        KeySource::PrivateKey(private_key) => BLSService::new(private_key),
+        KeySource::Keystore {
+            path,
+            password_path,
+        } => {
+            let password = read_password(password_path)?;
+            let keystore: Eip2335Keystore = serde_json::from_str(&read_keystore(path)?)?;
+            let secret = decrypt_bls_keystore(&keystore, &password)?;
+            let bls_service = BLSService::from_secret_key_bytes(&secret);
+
+            let public_key = alloy::hex::decode(&keystore.pubkey)?;
+            if bls_service.get_public_key_compressed() != public_key {
+                return Err(anyhow::anyhow!(
+                    "BLS keystore {}: decrypted key doesn't match the public key {}",
+                    path,
+                    keystore.pubkey
+                ));
+            }
+            Ok(bls_service)
+        }
+    }
+}
+
+fn read_keystore(path: &str) -> Result<String, Error> {
+    std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read keystore {}: {}", path, e))
+}
+
+fn read_password(password_path: &str) -> Result<Zeroizing<String>, Error> {
+    let password =
+        Zeroizing::new(std::fs::read_to_string(password_path).map_err(|e| {
+            anyhow::anyhow!("Failed to read password file {}: {}", password_path, e)
+        })?);
+    Ok(Zeroizing::new(
+        password.trim_end_matches(['\r', '\n']).to_string(),
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KdfParams {
+    Scrypt {
+        dklen: usize,
+        n: u32,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        salt: String,
+    },
+}
+
+fn derive_key(kdf: &str, params: &KdfParams, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut key = Zeroizing::new(vec![]);
+    match (kdf, params) {
+        (
+            "scrypt",
+            KdfParams::Scrypt {
+                dklen,
+                n,
+                r,
+                p,
+                salt,
+            },
+        ) => {
+            if !n.is_power_of_two() {
+                return Err(anyhow::anyhow!("Keystore: scrypt n must be a power of two"));
+            }
+            let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p)
+                .map_err(|e| anyhow::anyhow!("Keystore: invalid scrypt params: {}", e))?;
+            key.resize(*dklen, 0);
+            scrypt::scrypt(password, &alloy::hex::decode(salt)?, &params, &mut key)
+                .map_err(|e| anyhow::anyhow!("Keystore: scrypt failed: {}", e))?;
+        }
+        (
+            "pbkdf2",
+            KdfParams::Pbkdf2 {
+                dklen,
+                c,
+                prf,
+                salt,
+            },
+        ) => {
+            if prf != "hmac-sha256" {
+                return Err(anyhow::anyhow!("Keystore: unsupported pbkdf2 prf {}", prf));
+            }
+            key.resize(*dklen, 0);
+            pbkdf2::pbkdf2::<Hmac<Sha256>>(password, &alloy::hex::decode(salt)?, *c, &mut key);
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Keystore: unsupported kdf {} or params not matching it",
+                kdf
+            ))
+        }
+    }
+
+    if key.len() < 32 {
+        return Err(anyhow::anyhow!("Keystore: derived key is too short"));
+    }
+    Ok(key)
+}
+
+fn decrypt_aes_128_ctr(
+    key: &[u8],
+    iv: &str,
+    ciphertext: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let iv = alloy::hex::decode(iv)?;
+    if iv.len() != 16 {
+        return Err(anyhow::anyhow!("Keystore: invalid cipher iv length"));
+    }
+    let mut secret = Zeroizing::new(ciphertext.to_vec());
+    Aes128Ctr::new(key[..16].into(), iv.as_slice().into()).apply_keystream(&mut secret);
+    Ok(secret)
+}
+
+#[derive(Deserialize)]
+struct Eip2335Keystore {
+    crypto: Eip2335Crypto,
+    pubkey: String,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Eip2335Crypto {
+    kdf: Eip2335Module<KdfParams>,
+    checksum: Eip2335Module<serde_json::Value>,
+    cipher: Eip2335Module<CipherParams>,
+}
+
+#[derive(Deserialize)]
+struct Eip2335Module<P> {
+    function: String,
+    params: P,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct CipherParams {
+    iv: String,
+}
+
+// The password is NFKD normalized and stripped of control codes, as required by EIP-2335.
+fn eip2335_password(password: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        password
+            .nfkd()
+            .filter(|c| !matches!(*c as u32, 0x00..=0x1f | 0x7f..=0x9f))
+            .collect(),
+    )
+}
+
+fn decrypt_bls_keystore(
+    keystore: &Eip2335Keystore,
+    password: &str,
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    if keystore.version != 4 {
+        return Err(anyhow::anyhow!(
+            "BLS keystore: unsupported version {}",
+            keystore.version
+        ));
+    }
+    let crypto = &keystore.crypto;
+    if crypto.checksum.function != "sha256" || crypto.cipher.function != "aes-128-ctr" {
+        return Err(anyhow::anyhow!(
+            "BLS keystore: unsupported checksum {} or cipher {}",
+            crypto.checksum.function,
+            crypto.cipher.function
+        ));
+    }
+
+    let password = eip2335_password(password);
+    let key = derive_key(
+        &crypto.kdf.function,
+        &crypto.kdf.params,
+        password.as_bytes(),
+    )?;
+    let ciphertext = alloy::hex::decode(&crypto.cipher.message)?;
+
+    let checksum = Sha256::new()
+        .chain_update(&key[16..32])
+        .chain_update(&ciphertext)
+        .finalize();
+    if checksum.as_slice() != alloy::hex::decode(&crypto.checksum.message)? {
+        return Err(anyhow::anyhow!("BLS keystore: invalid password"));
+    }
+
+    decrypt_aes_128_ctr(&key, &crypto.cipher.params.iv, &ciphertext)
+}
+
+#[derive(Deserialize)]
+struct Web3Keystore {
+    #[serde(alias = "Crypto")]
+    crypto: Web3Crypto,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Web3Crypto {
+    cipher: String,
+    cipherparams: CipherParams,
+    ciphertext: String,
+    kdf: String,
+    kdfparams: KdfParams,
+    mac: String,
+}
+
+fn decrypt_ecdsa_keystore(keystore: &str, password: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let keystore: Web3Keystore = serde_json::from_str(keystore)?;
+    if keystore.version != 3 {
+        return Err(anyhow::anyhow!(
+            "ECDSA keystore: unsupported version {}",
+            keystore.version
+        ));
+    }
+    let crypto = &keystore.crypto;
+    if crypto.cipher != "aes-128-ctr" {
+        return Err(anyhow::anyhow!(
+            "ECDSA keystore: unsupported cipher {}",
+            crypto.cipher
+        ));
+    }
+
+    let key = derive_key(&crypto.kdf, &crypto.kdfparams, password)?;
+    let ciphertext = alloy::hex::decode(&crypto.ciphertext)?;
+
+    let mut mac_data = key[16..32].to_vec();
+    mac_data.extend_from_slice(&ciphertext);
+    if keccak256(&mac_data).as_slice() != alloy::hex::decode(&crypto.mac)? {
+        return Err(anyhow::anyhow!("ECDSA keystore: invalid password"));
+    }
+
+    decrypt_aes_128_ctr(&key, &crypto.cipherparams.iv, &ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signer::EcdsaSigner;
+
+    // Test vectors from EIP-2335 and the Web3 Secret Storage definition
+    const BLS_KEYSTORE: &str = r#"{
+        "crypto": {
+            "kdf": {
+                "function": "pbkdf2",
+                "params": {
+                    "dklen": 32,
+                    "c": 262144,
+                    "prf": "hmac-sha256",
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "8a9f5d9912ed7e75ea794bc5a89bca5f193721d30868ade6f73043c6ea6febf1"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "cee03fde2af33149775b7223e7845e4fb2c8ae1792e5f99fe9ecf474cc8c16ad"
+            }
+        },
+        "description": "This is a test keystore that uses PBKDF2 to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/0/0",
+        "uuid": "64625def-3331-4eea-ab6f-782f3ed16a83",
+        "version": 4
+    }"#;
+    const BLS_PASSWORD: &str = "𝔱𝔢𝔰𝔱𝔭𝔞𝔰𝔰𝔴𝔬𝔯𝔡🔑";
+    const BLS_SECRET: &str = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+    const ECDSA_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": {
+                "iv": "6087dab2f9fdbbfaddc31a909735c1e6"
+            },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+    const ECDSA_PASSWORD: &str = "testpassword";
+    const ECDSA_SECRET: &str = "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d";
+
+    fn write_keystore(dir: &std::path::Path, keystore: &str, password: &str) -> KeySource {
+        let path = dir.join("keystore.json");
+        let password_path = dir.join("password.txt");
+        std::fs::write(&path, keystore).unwrap();
+        std::fs::write(&password_path, format!("{}\n", password)).unwrap();
+        KeySource::Keystore {
+            path: path.to_string_lossy().to_string(),
+            password_path: password_path.to_string_lossy().to_string(),
+        }
+    }
+
+    #[test]
+    fn test_decrypt_bls_keystore() {
+        let keystore: Eip2335Keystore = serde_json::from_str(BLS_KEYSTORE).unwrap();
+        let secret = decrypt_bls_keystore(&keystore, BLS_PASSWORD).unwrap();
+        assert_eq!(alloy::hex::encode(&secret), BLS_SECRET);
+
+        assert!(decrypt_bls_keystore(&keystore, "wrong password").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let bls_service =
+            load_bls_signer(&write_keystore(dir.path(), BLS_KEYSTORE, BLS_PASSWORD)).unwrap();
+        assert_eq!(
+            alloy::hex::encode(bls_service.get_public_key_compressed()),
+            keystore.pubkey
+        );
+    }
+
+    #[test]
+    fn test_decrypt_ecdsa_keystore() {
+        let secret = decrypt_ecdsa_keystore(ECDSA_KEYSTORE, ECDSA_PASSWORD.as_bytes()).unwrap();
+        assert_eq!(alloy::hex::encode(&secret), ECDSA_SECRET);
+
+        assert!(decrypt_ecdsa_keystore(ECDSA_KEYSTORE, b"wrong password").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let signer =
+            load_ecdsa_signer(&write_keystore(dir.path(), ECDSA_KEYSTORE, ECDSA_PASSWORD)).unwrap();
+        let expected = LocalEcdsaSigner::new(ECDSA_SECRET).unwrap();
+        assert_eq!(signer.address(), expected.address());
+    }
+}
@@ -0,0 +1,60 @@
+use super::{BlsSigner, EcdsaSigner};
+use crate::bls::BLSService;
+use alloy::{
+    primitives::{keccak256, Address, Signature},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use anyhow::Error;
+use async_trait::async_trait;
+use bls_on_arkworks::types::PublicKey;
+use std::str::FromStr;
+
+pub struct LocalEcdsaSigner {
+    // the signing key is zeroized when dropped
+    signer: PrivateKeySigner,
+}
+
+impl LocalEcdsaSigner {
+    pub fn new(private_key: &str) -> Result<Self, Error> {
+        Ok(Self {
+            signer: PrivateKeySigner::from_str(private_key)?,
+        })
+    }
+
+    pub fn from_slice(private_key: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            signer: PrivateKeySigner::from_slice(private_key)?,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn from_signer(signer: PrivateKeySigner) -> Self {
+        Self { signer }
+    }
+}
+
+#[async_trait]
+impl EcdsaSigner for LocalEcdsaSigner {
+    fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    async fn sign_data(&self, data: &[u8]) -> Result<Signature, Error> {
+        Ok(self.signer.sign_hash_sync(&keccak256(data))?)
+    }
+}
+
+#[async_trait]
+impl BlsSigner for BLSService {
+    fn public_key(&self) -> PublicKey {
+        self.get_public_key_compressed()
+    }
+
+    async fn sign(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<bls_on_arkworks::types::Signature, Error> {
+        Ok(BLSService::sign(self, &message.to_vec(), &dst.to_vec()))
+    }
+}
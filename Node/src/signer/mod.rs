@@ -0,0 +1,139 @@
+// Every signature produced by the node goes through the traits below, so the keys can be held
+// either by the node itself or by a remote Web3Signer instance.
+pub mod keystore;
+mod local;
+pub mod web3signer;
+
+use crate::utils::config::Config;
+use alloy::{
+    consensus::SignableTransaction,
+    network::{EthereumWallet, TxSigner},
+    primitives::{Address, Signature},
+};
+use anyhow::Error;
+use async_trait::async_trait;
+use bls_on_arkworks as bls;
+use bls_on_arkworks::types::{G1AffinePoint, G2AffinePoint, PublicKey};
+pub use local::LocalEcdsaSigner;
+use std::sync::Arc;
+use web3signer::{Web3Signer, Web3SignerBls, Web3SignerEcdsa};
+
+/// Domain separation tag of the Ethereum consensus BLS signatures.
+pub const BLS_POP_DST: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
+
+#[async_trait]
+pub trait EcdsaSigner: Send + Sync {
+    fn address(&self) -> Address;
+
+    /// Signs the keccak256 hash of `data`.
+    async fn sign_data(&self, data: &[u8]) -> Result<Signature, Error>;
+
+    /// Signs `message` prefixed as specified in EIP-191.
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature, Error> {
+        let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+        data.extend_from_slice(message);
+        self.sign_data(&data).await
+    }
+}
+
+#[async_trait]
+pub trait BlsSigner: Send + Sync {
+    /// Returns the compressed public key.
+    fn public_key(&self) -> PublicKey;
+
+    /// Returns the 96 bytes compressed signature of `message`.
+    async fn sign(&self, message: &[u8], dst: &[u8]) -> Result<bls::types::Signature, Error>;
+
+    fn public_key_point(&self) -> Result<G1AffinePoint, Error> {
+        bls::pubkey_to_point(&self.public_key())
+            .map_err(|e| anyhow::anyhow!("Invalid BLS public key: {:?}", e))
+    }
+
+    async fn sign_as_point(&self, message: &[u8], dst: &[u8]) -> Result<G2AffinePoint, Error> {
+        let signature = self.sign(message, dst).await?;
+        bls::signature_to_point(&signature)
+            .map_err(|e| anyhow::anyhow!("Invalid BLS signature: {:?}", e))
+    }
+}
+
+/// Stands for the validator BLS key in watchtower mode, where nothing is signed with it.
+pub struct NoBlsSigner;
+
+#[async_trait]
+impl BlsSigner for NoBlsSigner {
+    fn public_key(&self) -> PublicKey {
+        PublicKey::new()
+    }
+
+    async fn sign(&self, _message: &[u8], _dst: &[u8]) -> Result<bls::types::Signature, Error> {
+        Err(anyhow::anyhow!("No validator BLS key in watchtower mode"))
+    }
+}
+
+/// Lets an [`EcdsaSigner`] sign the transactions sent through an alloy wallet.
+pub struct EcdsaTxSigner(pub Arc<dyn EcdsaSigner>);
+
+#[async_trait]
+impl TxSigner<Signature> for EcdsaTxSigner {
+    fn address(&self) -> Address {
+        self.0.address()
+    }
+
+    async fn sign_transaction(
+        &self,
+        tx: &mut dyn SignableTransaction<Signature>,
+    ) -> alloy::signers::Result<Signature> {
+        let mut payload = vec![];
+        tx.encode_for_signing(&mut payload);
+        let signature = self
+            .0
+            .sign_data(&payload)
+            .await
+            .map_err(|e| alloy::signers::Error::other(e.to_string()))?;
+
+        match tx.chain_id() {
+            Some(chain_id) if tx.use_eip155() => Ok(signature.with_chain_id(chain_id)),
+            _ => Ok(signature.with_parity_bool()),
+        }
+    }
+}
+
+pub fn create_wallet(signer: Arc<dyn EcdsaSigner>) -> EthereumWallet {
+    EthereumWallet::from(EcdsaTxSigner(signer))
+}
+
+/// Creates the ECDSA and BLS signers. The ECDSA key is held by Web3Signer if it is configured,
+/// and the BLS key too if its public key is set, the keys come from the keystores or private keys
+/// of the config otherwise. There is no BLS key in watchtower mode.
+pub async fn create_signers(
+    config: &Config,
+) -> Result<(Arc<dyn EcdsaSigner>, Arc<dyn BlsSigner>), Error> {
+    let (ecdsa_signer, remote_bls_signer): (Arc<dyn EcdsaSigner>, Option<Arc<dyn BlsSigner>>) =
+        match &config.web3signer {
+            Some(web3signer_config) => {
+                let web3signer = Web3Signer::new(&web3signer_config.url);
+                let ecdsa_signer =
+                    Web3SignerEcdsa::new(web3signer.clone(), web3signer_config.ecdsa_address)
+                        .await?;
+                let bls_signer: Option<Arc<dyn BlsSigner>> = match &web3signer_config.bls_public_key
+                {
+                    Some(public_key) if !config.watchtower => {
+                        Some(Arc::new(Web3SignerBls::new(web3signer, public_key).await?))
+                    }
+                    _ => None,
+                };
+                tracing::info!("Signing with Web3Signer at {}", web3signer_config.url);
+                (Arc::new(ecdsa_signer), bls_signer)
+            }
+            None => (
+                Arc::new(keystore::load_ecdsa_signer(&config.avs_node_ecdsa_key)?),
+                None,
+            ),
+        };
+    let bls_signer: Arc<dyn BlsSigner> = match (remote_bls_signer, &config.validator_bls_key) {
+        (Some(bls_signer), _) => bls_signer,
+        (None, Some(key)) => Arc::new(keystore::load_bls_signer(key)?),
+        (None, None) => Arc::new(NoBlsSigner),
+    };
+    Ok((ecdsa_signer, bls_signer))
+}
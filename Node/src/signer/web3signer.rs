@@ -0,0 +1,361 @@
+// Client of the Web3Signer remote signing API. The keys never leave Web3Signer, the node only
+// sends the data to sign and checks at startup that the configured keys are loaded.
+use super::{BlsSigner, EcdsaSigner, BLS_POP_DST};
+use alloy::primitives::{Address, Signature};
+use anyhow::Error;
+use async_trait::async_trait;
+use bls_on_arkworks::types::PublicKey;
+use reqwest::Client;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone)]
+pub struct Web3SignerConfig {
+    pub url: String,
+    pub ecdsa_address: Address,
+    pub bls_public_key: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct Web3Signer {
+    client: Client,
+    url: String,
+}
+
+impl Web3Signer {
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn get_public_keys(&self, key_type: &str) -> Result<Vec<String>, Error> {
+        let response = self
+            .client
+            .get(format!("{}/api/v1/{}/publicKeys", self.url, key_type))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    async fn sign(&self, key_type: &str, identifier: &str, body: Value) -> Result<Vec<u8>, Error> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/api/v1/{}/sign/{}",
+                self.url, key_type, identifier
+            ))
+            .json(&body)
+            .send()
+            .await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(anyhow::anyhow!(
+                "Web3Signer: signing with {} failed with {}: {}",
+                identifier,
+                status,
+                text
+            ));
+        }
+        decode_signature(&text)
+    }
+}
+
+// The signature comes as a plain hex string, or as JSON if the server is set up to reply so.
+fn decode_signature(body: &str) -> Result<Vec<u8>, Error> {
+    let body = body.trim();
+    let signature = match serde_json::from_str::<Value>(body) {
+        Ok(Value::Object(object)) => object
+            .get("signature")
+            .and_then(Value::as_str)
+            .ok_or(anyhow::anyhow!("Web3Signer: no signature in {}", body))?
+            .to_string(),
+        Ok(Value::String(signature)) => signature,
+        _ => body.to_string(),
+    };
+    alloy::hex::decode(&signature)
+        .map_err(|e| anyhow::anyhow!("Web3Signer: invalid signature {}: {}", signature, e))
+}
+
+pub struct Web3SignerEcdsa {
+    web3signer: Web3Signer,
+    address: Address,
+    // Web3Signer identifies secp256k1 keys by their public key
+    public_key: String,
+}
+
+impl Web3SignerEcdsa {
+    pub async fn new(web3signer: Web3Signer, address: Address) -> Result<Self, Error> {
+        let public_key = web3signer
+            .get_public_keys("eth1")
+            .await?
+            .into_iter()
+            .find(|public_key| address_from_public_key(public_key).ok() == Some(address))
+            .ok_or(anyhow::anyhow!(
+                "Web3Signer: no ECDSA key loaded for address {}",
+                address
+            ))?;
+
+        Ok(Self {
+            web3signer,
+            address,
+            public_key,
+        })
+    }
+}
+
+fn address_from_public_key(public_key: &str) -> Result<Address, Error> {
+    let bytes = alloy::hex::decode(public_key)?;
+    // uncompressed key, with or without the 0x04 prefix
+    match bytes.len() {
+        64 => Ok(Address::from_raw_public_key(&bytes)),
+        65 => Ok(Address::from_raw_public_key(&bytes[1..])),
+        len => Err(anyhow::anyhow!("Unexpected public key length {}", len)),
+    }
+}
+
+#[async_trait]
+impl EcdsaSigner for Web3SignerEcdsa {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign_data(&self, data: &[u8]) -> Result<Signature, Error> {
+        let signature = self
+            .web3signer
+            .sign(
+                "eth1",
+                &self.public_key,
+                json!({ "data": format!("0x{}", alloy::hex::encode(data)) }),
+            )
+            .await?;
+        Ok(Signature::try_from(signature.as_slice())?)
+    }
+}
+
+pub struct Web3SignerBls {
+    web3signer: Web3Signer,
+    public_key: PublicKey,
+}
+
+impl Web3SignerBls {
+    pub async fn new(web3signer: Web3Signer, public_key: &str) -> Result<Self, Error> {
+        let public_key = alloy::hex::decode(public_key)?;
+        let loaded = web3signer
+            .get_public_keys("eth2")
+            .await?
+            .iter()
+            .any(|key| alloy::hex::decode(key).ok().as_ref() == Some(&public_key));
+        if !loaded {
+            return Err(anyhow::anyhow!(
+                "Web3Signer: BLS key 0x{} is not loaded",
+                alloy::hex::encode(&public_key)
+            ));
+        }
+
+        Ok(Self {
+            web3signer,
+            public_key,
+        })
+    }
+}
+
+#[async_trait]
+impl BlsSigner for Web3SignerBls {
+    fn public_key(&self) -> PublicKey {
+        self.public_key.clone()
+    }
+
+    // Web3Signer only signs 32 bytes signing roots, under the consensus domain separation tag.
+    // They are sent as builder domain roots, the domain the MEV-Boost constraints are signed in.
+    async fn sign(
+        &self,
+        message: &[u8],
+        dst: &[u8],
+    ) -> Result<bls_on_arkworks::types::Signature, Error> {
+        if dst != BLS_POP_DST || message.len() != 32 {
+            return Err(anyhow::anyhow!(
+                "Web3Signer: only 32 bytes signing roots with the POP domain separation tag can be signed, got {} bytes with tag {:?}",
+                message.len(),
+                String::from_utf8_lossy(dst)
+            ));
+        }
+
+        let signature = self
+            .web3signer
+            .sign(
+                "eth2",
+                &format!("0x{}", alloy::hex::encode(&self.public_key)),
+                json!({
+                    "type": "VALIDATOR_REGISTRATION",
+                    "signingRoot": format!("0x{}", alloy::hex::encode(message)),
+                }),
+            )
+            .await?;
+        if signature.len() != 96 {
+            return Err(anyhow::anyhow!(
+                "Web3Signer: unexpected BLS signature length {}",
+                signature.len()
+            ));
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bls::BLSService, signer::LocalEcdsaSigner};
+    use alloy::{
+        consensus::{SignableTransaction, TxEip1559},
+        primitives::{keccak256, TxKind, U256},
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+    use std::{str::FromStr, sync::Arc};
+
+    const ECDSA_PRIVATE_KEY: &str =
+        "0x4c0883a69102937d6231471b5dbb6204fe512961708279f2e3e8a5d4b8e3e3e8";
+    const BLS_PRIVATE_KEY: &str =
+        "0x14d50ac943d01069c206543a0bed3836f6062b35270607ebf1d1f238ceda26f1";
+
+    fn request_data(request: &mockito::Request, field: &str) -> Vec<u8> {
+        let body: Value = serde_json::from_slice(request.body().unwrap()).unwrap();
+        alloy::hex::decode(body[field].as_str().unwrap()).unwrap()
+    }
+
+    // Serves the Web3Signer API, signing with the local test keys.
+    async fn setup_server() -> mockito::ServerGuard {
+        let mut server = mockito::Server::new_async().await;
+        let ecdsa_signer = PrivateKeySigner::from_str(ECDSA_PRIVATE_KEY).unwrap();
+        let ecdsa_public_key = format!(
+            "0x{}",
+            alloy::hex::encode(
+                &ecdsa_signer
+                    .credential()
+                    .verifying_key()
+                    .to_encoded_point(false)
+                    .as_bytes()[1..]
+            )
+        );
+        let bls_service = Arc::new(BLSService::new(BLS_PRIVATE_KEY).unwrap());
+        let bls_public_key = format!(
+            "0x{}",
+            alloy::hex::encode(bls_service.get_public_key_compressed())
+        );
+
+        server
+            .mock("GET", "/api/v1/eth1/publicKeys")
+            .with_body(json!([ecdsa_public_key]).to_string())
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/eth2/publicKeys")
+            .with_body(json!([bls_public_key]).to_string())
+            .create_async()
+            .await;
+        server
+            .mock(
+                "POST",
+                format!("/api/v1/eth1/sign/{}", ecdsa_public_key).as_str(),
+            )
+            .with_body_from_request(move |request| {
+                let data = request_data(request, "data");
+                let signature = ecdsa_signer.sign_hash_sync(&keccak256(data)).unwrap();
+                format!("0x{}", alloy::hex::encode(signature.as_bytes())).into()
+            })
+            .create_async()
+            .await;
+        server
+            .mock(
+                "POST",
+                format!("/api/v1/eth2/sign/{}", bls_public_key).as_str(),
+            )
+            .with_body_from_request(move |request| {
+                let signing_root = request_data(request, "signingRoot");
+                let signature = bls_service.sign(&signing_root, &BLS_POP_DST.to_vec());
+                json!({ "signature": format!("0x{}", alloy::hex::encode(signature)) })
+                    .to_string()
+                    .into()
+            })
+            .create_async()
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_ecdsa_signatures_match_local_key() {
+        let server = setup_server().await;
+        let local = LocalEcdsaSigner::new(ECDSA_PRIVATE_KEY).unwrap();
+        let remote = Web3SignerEcdsa::new(Web3Signer::new(&server.url()), local.address())
+            .await
+            .unwrap();
+        assert_eq!(remote.address(), local.address());
+
+        let message = [7u8; 32];
+        let signature = remote.sign_message(&message).await.unwrap();
+        assert_eq!(
+            signature.as_bytes(),
+            local.sign_message(&message).await.unwrap().as_bytes()
+        );
+        assert_eq!(
+            signature.recover_address_from_msg(message).unwrap(),
+            local.address()
+        );
+
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            nonce: 3,
+            gas_limit: 21_000,
+            max_fee_per_gas: 20_000_000_000,
+            max_priority_fee_per_gas: 1_000_000_000,
+            to: TxKind::Call(Address::ZERO),
+            value: U256::from(1),
+            ..Default::default()
+        };
+        let wallet = crate::signer::create_wallet(Arc::new(remote));
+        let signature = wallet
+            .default_signer()
+            .sign_transaction(&mut tx)
+            .await
+            .unwrap();
+        let signed = tx.into_signed(signature);
+        assert_eq!(signed.recover_signer().unwrap(), local.address());
+    }
+
+    #[tokio::test]
+    async fn test_bls_signatures_match_local_key() {
+        let server = setup_server().await;
+        let local = BLSService::new(BLS_PRIVATE_KEY).unwrap();
+        let remote = Web3SignerBls::new(
+            Web3Signer::new(&server.url()),
+            &alloy::hex::encode(local.get_public_key_compressed()),
+        )
+        .await
+        .unwrap();
+
+        let signing_root = [1u8; 32];
+        let signature = remote.sign(&signing_root, BLS_POP_DST).await.unwrap();
+        assert_eq!(
+            signature,
+            local.sign(&signing_root.to_vec(), &BLS_POP_DST.to_vec())
+        );
+        assert!(remote.sign(&[1u8; 40], BLS_POP_DST).await.is_err());
+        assert!(remote.sign(&signing_root, &[]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_keys_are_rejected() {
+        let server = setup_server().await;
+        let web3signer = Web3Signer::new(&server.url());
+        assert!(Web3SignerEcdsa::new(web3signer.clone(), Address::ZERO)
+            .await
+            .is_err());
+        assert!(
+            Web3SignerBls::new(web3signer, &alloy::hex::encode([0u8; 48]))
+                .await
+                .is_err()
+        );
+    }
+}
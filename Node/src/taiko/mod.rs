@@ -4,6 +4,7 @@ use serde_json::Value;
 use tracing::debug;
 
 pub mod l2_tx_lists;
+pub mod transactions;
 
 pub struct Taiko {
     rpc_proposer: RpcClient,
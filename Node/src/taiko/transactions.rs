@@ -0,0 +1,236 @@
+// Transactions in the JSON format of go-ethereum, the one of the Taiko proposer and driver, and
+// their EIP-2718 encoding sent to the other nodes. Taiko L2 has no blob transactions.
+use alloy::{
+    consensus::{SignableTransaction, Signed, TxEip1559, TxEip2930, TxEnvelope, TxLegacy},
+    eips::{
+        eip2718::{Decodable2718, Encodable2718},
+        eip2930::AccessList,
+    },
+    primitives::{Address, Signature, TxKind, B256, U128, U256, U64},
+};
+use anyhow::Error;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+/// EIP-2718 encoding of a JSON transaction, checked against its hash if it has one.
+pub fn encode_transaction(tx: &Value) -> Result<Vec<u8>, Error> {
+    let tx_type = field::<U64>(tx, "type")?.to::<u8>();
+    let envelope = match tx_type {
+        0 => {
+            let signature = signature(tx, "v")?;
+            TxEnvelope::Legacy(signed(
+                TxLegacy {
+                    chain_id: signature.v().chain_id(),
+                    nonce: field::<U64>(tx, "nonce")?.to(),
+                    gas_price: field::<U128>(tx, "gasPrice")?.to(),
+                    gas_limit: field::<U128>(tx, "gas")?.to(),
+                    to: to(tx)?,
+                    value: field(tx, "value")?,
+                    input: field(tx, "input")?,
+                },
+                signature,
+            ))
+        }
+        1 => TxEnvelope::Eip2930(signed(
+            TxEip2930 {
+                chain_id: field::<U64>(tx, "chainId")?.to(),
+                nonce: field::<U64>(tx, "nonce")?.to(),
+                gas_price: field::<U128>(tx, "gasPrice")?.to(),
+                gas_limit: field::<U128>(tx, "gas")?.to(),
+                to: to(tx)?,
+                value: field(tx, "value")?,
+                access_list: access_list(tx)?,
+                input: field(tx, "input")?,
+            },
+            typed_signature(tx)?,
+        )),
+        2 => TxEnvelope::Eip1559(signed(
+            TxEip1559 {
+                chain_id: field::<U64>(tx, "chainId")?.to(),
+                nonce: field::<U64>(tx, "nonce")?.to(),
+                gas_limit: field::<U128>(tx, "gas")?.to(),
+                max_fee_per_gas: field::<U128>(tx, "maxFeePerGas")?.to(),
+                max_priority_fee_per_gas: field::<U128>(tx, "maxPriorityFeePerGas")?.to(),
+                to: to(tx)?,
+                value: field(tx, "value")?,
+                access_list: access_list(tx)?,
+                input: field(tx, "input")?,
+            },
+            typed_signature(tx)?,
+        )),
+        _ => return Err(anyhow::anyhow!("Unsupported transaction type {}", tx_type)),
+    };
+
+    if let Some(hash) = optional_field::<B256>(tx, "hash")? {
+        if hash != *envelope.tx_hash() {
+            return Err(anyhow::anyhow!(
+                "Transaction hash {} doesn't match its fields",
+                hash
+            ));
+        }
+    }
+    Ok(envelope.encoded_2718())
+}
+
+/// JSON transaction of an EIP-2718 encoding.
+pub fn decode_transaction(bytes: &[u8]) -> Result<Value, Error> {
+    let envelope = TxEnvelope::decode_2718(&mut &bytes[..])
+        .map_err(|e| anyhow::anyhow!("Invalid transaction encoding: {}", e))?;
+    // the buffer isn't advanced past a typed transaction, the encoding is compared instead,
+    // which also leaves out the non canonical ones
+    if envelope.encoded_2718() != bytes {
+        return Err(anyhow::anyhow!("Invalid transaction encoding"));
+    }
+
+    let (mut tx, signature) = match &envelope {
+        TxEnvelope::Legacy(signed) => {
+            let tx = signed.tx();
+            let json = json!({
+                "type": "0x0",
+                "chainId": tx.chain_id.map(quantity),
+                "nonce": quantity(tx.nonce),
+                "gasPrice": quantity(tx.gas_price),
+                "gas": quantity(tx.gas_limit),
+                "to": tx.to.to().copied(),
+                "value": tx.value,
+                "input": tx.input,
+                "v": quantity(signed.signature().v().to_u64()),
+            });
+            (json, signed.signature())
+        }
+        TxEnvelope::Eip2930(signed) => {
+            let tx = signed.tx();
+            let json = json!({
+                "type": "0x1",
+                "chainId": quantity(tx.chain_id),
+                "nonce": quantity(tx.nonce),
+                "gasPrice": quantity(tx.gas_price),
+                "gas": quantity(tx.gas_limit),
+                "to": tx.to.to().copied(),
+                "value": tx.value,
+                "accessList": tx.access_list,
+                "input": tx.input,
+            });
+            (json, signed.signature())
+        }
+        TxEnvelope::Eip1559(signed) => {
+            let tx = signed.tx();
+            let json = json!({
+                "type": "0x2",
+                "chainId": quantity(tx.chain_id),
+                "nonce": quantity(tx.nonce),
+                "maxPriorityFeePerGas": quantity(tx.max_priority_fee_per_gas),
+                "maxFeePerGas": quantity(tx.max_fee_per_gas),
+                "gas": quantity(tx.gas_limit),
+                "to": tx.to.to().copied(),
+                "value": tx.value,
+                "accessList": tx.access_list,
+                "input": tx.input,
+            });
+            (json, signed.signature())
+        }
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Unsupported transaction type {}",
+                envelope.tx_type() as u8
+            ))
+        }
+    };
+
+    if !matches!(envelope, TxEnvelope::Legacy(_)) {
+        // go-ethereum takes the parity of a typed transaction from either field
+        let y_parity = quantity(signature.v().y_parity_byte());
+        tx["v"] = json!(y_parity);
+        tx["yParity"] = json!(y_parity);
+    }
+    tx["r"] = json!(signature.r());
+    tx["s"] = json!(signature.s());
+    tx["hash"] = json!(envelope.tx_hash());
+    Ok(tx)
+}
+
+fn signed<T: SignableTransaction<Signature>>(tx: T, signature: Signature) -> Signed<T> {
+    tx.into_signed(signature)
+}
+
+fn signature(tx: &Value, parity_field: &str) -> Result<Signature, Error> {
+    let parity = field::<U64>(tx, parity_field)?.to::<u64>();
+    Signature::from_rs_and_parity(field::<U256>(tx, "r")?, field::<U256>(tx, "s")?, parity)
+        .map_err(|e| anyhow::anyhow!("Invalid transaction signature: {}", e))
+}
+
+fn typed_signature(tx: &Value) -> Result<Signature, Error> {
+    match optional_field::<U64>(tx, "yParity")? {
+        Some(_) => signature(tx, "yParity"),
+        None => signature(tx, "v"),
+    }
+}
+
+fn to(tx: &Value) -> Result<TxKind, Error> {
+    Ok(optional_field::<Address>(tx, "to")?.map_or(TxKind::Create, TxKind::Call))
+}
+
+fn access_list(tx: &Value) -> Result<AccessList, Error> {
+    Ok(optional_field(tx, "accessList")?.unwrap_or_default())
+}
+
+fn field<T: DeserializeOwned>(tx: &Value, name: &str) -> Result<T, Error> {
+    optional_field(tx, name)?.ok_or_else(|| anyhow::anyhow!("Transaction without {}", name))
+}
+
+fn optional_field<T: DeserializeOwned>(tx: &Value, name: &str) -> Result<Option<T>, Error> {
+    tx.get(name)
+        .filter(|value| !value.is_null())
+        .map(|value| serde_json::from_value(value.clone()))
+        .transpose()
+        .map_err(|e| anyhow::anyhow!("Invalid transaction {}: {}", name, e))
+}
+
+fn quantity(value: impl std::fmt::LowerHex) -> String {
+    format!("{:#x}", value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_transactions() -> Vec<Value> {
+        let response: Value =
+            serde_json::from_str(include_str!("../utils/tx_lists_test_response.json")).unwrap();
+        response["TxLists"][0].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let txs = test_transactions();
+        // a legacy and a dynamic fee transaction
+        assert_eq!(txs[0]["type"], "0x0");
+        assert_eq!(txs[1]["type"], "0x2");
+        for tx in txs {
+            let bytes = encode_transaction(&tx).unwrap();
+            let decoded = decode_transaction(&bytes).unwrap();
+            assert_eq!(decoded["hash"], tx["hash"]);
+            assert_eq!(decoded["input"], tx["input"]);
+            assert_eq!(decode_transaction(&bytes).unwrap(), decoded);
+            assert_eq!(encode_transaction(&decoded).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_invalid_transactions() {
+        let mut tx = test_transactions().remove(1);
+        tx["nonce"] = json!("0x1");
+        assert!(encode_transaction(&tx)
+            .unwrap_err()
+            .to_string()
+            .contains("doesn't match"));
+
+        tx.as_object_mut().unwrap().remove("gas");
+        assert!(encode_transaction(&tx).is_err());
+
+        let mut bytes = encode_transaction(&test_transactions()[1]).unwrap();
+        bytes.push(0);
+        assert!(decode_transaction(&bytes).is_err());
+        assert!(decode_transaction(&[0x03, 0xc0]).is_err());
+    }
+}
@@ -1,18 +1,29 @@
+use crate::ethereum_l1::gas_oracle::GasOracleConfig;
+use crate::signer::{keystore::KeySource, web3signer::Web3SignerConfig};
+use alloy::primitives::Address;
+use anyhow::Error;
 use p2p_network::generate_secp256k1;
+use p2p_network::gossip::GossipConfig;
 use p2p_network::network::P2PNetworkConfig;
+use p2p_network::peer_manager::DEFAULT_MAX_MESSAGES_PER_SEC;
+use std::{
+    collections::BTreeMap, fmt::Display, net::Ipv4Addr, path::Path, str::FromStr, time::Duration,
+};
 use tracing::{info, warn};
+use zeroize::{Zeroize, Zeroizing};
 
 pub struct Config {
     pub taiko_proposer_url: String,
     pub taiko_driver_url: String,
-    pub avs_node_ecdsa_private_key: String,
+    pub avs_node_ecdsa_key: KeySource,
     pub mev_boost_url: String,
     pub l1_ws_rpc_url: String,
     pub l1_beacon_url: String,
     pub l1_slot_duration_sec: u64,
     pub l1_slots_per_epoch: u64,
     pub l2_slot_duration_sec: u64,
-    pub validator_bls_privkey: String,
+    /// Not needed by the watchtower, which doesn't sign as a validator.
+    pub validator_bls_key: Option<KeySource>,
     pub msg_expiry_sec: u64,
     pub contract_addresses: ContractAddresses,
     pub p2p_network_config: P2PNetworkConfig,
@@ -20,260 +31,720 @@ pub struct Config {
     pub l1_chain_id: u64,
     pub validator_index: u64,
     pub enable_p2p: bool,
+    /// Derive the p2p identity from the AVS node ECDSA key, so that the peers can check the
+    /// author of a preconfirmation is a registered preconfer.
+    pub p2p_bind_preconfer_identity: bool,
+    /// Only accept the preconfirmations of registered preconfers, gossiped by their author.
+    pub p2p_require_registered_author: bool,
     pub enable_preconfirmation: bool,
+    /// Audit the preconfirmations and lookaheads of all the preconfers and dispute the incorrect
+    /// ones, without preconfirming.
+    pub watchtower: bool,
+    pub enable_metrics: bool,
+    pub metrics_port: u16,
+    pub enable_preconf_api: bool,
+    /// The preconfirmation API is only reachable locally unless another address is set.
+    pub preconf_api_address: Ipv4Addr,
+    pub preconf_api_port: u16,
+    pub data_dir: String,
+    pub gas_oracle: GasOracleConfig,
+    pub web3signer: Option<Web3SignerConfig>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct ContractAddresses {
-    pub taiko_l1: String,
+    pub taiko_l1: Address,
     pub eigen_layer: EigenLayerContractAddresses,
     pub avs: AvsContractAddresses,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct EigenLayerContractAddresses {
-    pub strategy_manager: String,
-    pub slasher: String,
+    pub strategy_manager: Address,
+    pub slasher: Address,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct AvsContractAddresses {
-    pub preconf_task_manager: String,
-    pub directory: String,
-    pub service_manager: String,
-    pub preconf_registry: String,
+    pub preconf_task_manager: Address,
+    pub directory: Address,
+    pub service_manager: Address,
+    pub preconf_registry: Address,
 }
 
-impl Config {
-    pub fn read_env_variables() -> Self {
-        // Load environment variables from .env file
-        dotenv::dotenv().ok();
+// Every setting can be given in the config file (in lower case), as an env var or on the command
+// line with `--set NAME=VALUE`, each of them overriding the previous ones.
+const SETTINGS: &[&str] = &[
+    "DEV_MODE",
+    "TAIKO_PROPOSER_URL",
+    "TAIKO_DRIVER_URL",
+    "MEV_BOOST_URL",
+    "L1_WS_RPC_URL",
+    "L1_BEACON_URL",
+    "L1_SLOT_DURATION_SEC",
+    "L1_SLOTS_PER_EPOCH",
+    "L2_SLOT_DURATION_SEC",
+    "MSG_EXPIRY_SEC",
+    "TAIKO_CHAIN_ID",
+    "L1_CHAIN_ID",
+    "VALIDATOR_INDEX",
+    "ENABLE_P2P",
+    "ENABLE_PRECONFIRMATION",
+    "WATCHTOWER",
+    "ENABLE_METRICS",
+    "METRICS_PORT",
+    "ENABLE_PRECONF_API",
+    "PRECONF_API_ADDRESS",
+    "PRECONF_API_PORT",
+    "DATA_DIR",
+    "P2P_ADDRESS",
+    "P2P_UDP_PORT",
+    "P2P_TCP_PORT",
+    "P2P_IPV6_ADDRESS",
+    "P2P_UDP6_PORT",
+    "P2P_TCP6_PORT",
+    "P2P_EXTERNAL_ADDRESS",
+    "P2P_EXTERNAL_IPV6_ADDRESS",
+    "P2P_BOOTNODE_ENR",
+    "P2P_SIGNED_MESSAGES",
+    "P2P_BIND_PRECONFER_IDENTITY",
+    "P2P_REQUIRE_REGISTERED_AUTHOR",
+    "P2P_HEARTBEAT_MS",
+    "P2P_TOPIC_WEIGHT",
+    "P2P_INVALID_MESSAGE_WEIGHT",
+    "P2P_IP_COLOCATION_WEIGHT",
+    "P2P_IP_COLOCATION_THRESHOLD",
+    "P2P_GOSSIP_THRESHOLD",
+    "P2P_PUBLISH_THRESHOLD",
+    "P2P_GRAYLIST_THRESHOLD",
+    "P2P_MAX_MESSAGES_PER_SEC",
+    "AVS_NODE_ECDSA_PRIVATE_KEY",
+    "AVS_NODE_ECDSA_KEYSTORE_PATH",
+    "AVS_NODE_ECDSA_KEYSTORE_PASSWORD_PATH",
+    "VALIDATOR_BLS_PRIVATEKEY",
+    "VALIDATOR_BLS_KEYSTORE_PATH",
+    "VALIDATOR_BLS_KEYSTORE_PASSWORD_PATH",
+    "WEB3SIGNER_URL",
+    "WEB3SIGNER_ECDSA_ADDRESS",
+    "WEB3SIGNER_BLS_PUBLIC_KEY",
+    "AVS_PRECONF_TASK_MANAGER_CONTRACT_ADDRESS",
+    "AVS_DIRECTORY_CONTRACT_ADDRESS",
+    "AVS_SERVICE_MANAGER_CONTRACT_ADDRESS",
+    "AVS_PRECONF_REGISTRY_CONTRACT_ADDRESS",
+    "EIGEN_LAYER_STRATEGY_MANAGER_CONTRACT_ADDRESS",
+    "EIGEN_LAYER_SLASHER_CONTRACT_ADDRESS",
+    "TAIKO_L1_ADDRESS",
+    "MAX_FEE_PER_GAS_CAP_GWEI",
+    "MAX_PRIORITY_FEE_PER_GAS_CAP_GWEI",
+    "GAS_LIMIT_MARGIN_PERCENT",
+    "FEE_BUMP_PERCENT",
+];
+
+const DEFAULTS: &[(&str, &str)] = &[
+    ("DEV_MODE", "false"),
+    ("TAIKO_PROPOSER_URL", "http://127.0.0.1:1234"),
+    ("TAIKO_DRIVER_URL", "http://127.0.0.1:1235"),
+    ("MEV_BOOST_URL", "http://127.0.0.1:8080"),
+    ("L1_WS_RPC_URL", "wss://127.0.0.1"),
+    ("L1_BEACON_URL", "http://127.0.0.1:4000"),
+    ("L1_SLOT_DURATION_SEC", "12"),
+    ("L1_SLOTS_PER_EPOCH", "32"),
+    ("L2_SLOT_DURATION_SEC", "3"),
+    ("MSG_EXPIRY_SEC", "3600"),
+    ("L1_CHAIN_ID", "1"),
+    ("ENABLE_P2P", "true"),
+    ("ENABLE_PRECONFIRMATION", "true"),
+    ("WATCHTOWER", "false"),
+    ("ENABLE_METRICS", "true"),
+    ("METRICS_PORT", "9090"),
+    ("ENABLE_PRECONF_API", "true"),
+    ("PRECONF_API_ADDRESS", "127.0.0.1"),
+    ("PRECONF_API_PORT", "8547"),
+    ("DATA_DIR", "data"),
+    ("P2P_ADDRESS", "0.0.0.0"),
+    ("P2P_UDP_PORT", "9000"),
+    ("P2P_TCP_PORT", "9000"),
+    ("P2P_UDP6_PORT", "9090"),
+    ("P2P_TCP6_PORT", "9090"),
+    ("P2P_SIGNED_MESSAGES", "false"),
+    ("P2P_BIND_PRECONFER_IDENTITY", "false"),
+    ("P2P_REQUIRE_REGISTERED_AUTHOR", "false"),
+];
+
+const DEFAULT_ECDSA_PRIVATE_KEY: &str =
+    "0x4c0883a69102937d6231471b5dbb6204fe512961708279f2e3e8a5d4b8e3e3e8";
+const DEFAULT_BLS_PRIVATE_KEY: &str = "0x0";
+const GWEI: u128 = 1_000_000_000;
+const PEER_DB_FILE_NAME: &str = "peers.json";
+
+/// Named sets of defaults for the networks the node is usually run on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Network {
+    /// Local kurtosis devnet
+    Devnet,
+    /// Helder-like preconfirmation testnet
+    Helder,
+}
 
-        let default_empty_address = "0x0000000000000000000000000000000000000000".to_string();
-
-        const AVS_NODE_ECDSA_PRIVATE_KEY: &str = "AVS_NODE_ECDSA_PRIVATE_KEY";
-        let avs_node_ecdsa_private_key =
-            std::env::var(AVS_NODE_ECDSA_PRIVATE_KEY).unwrap_or_else(|_| {
-                warn!(
-                    "No AVS node ECDSA private key found in {} env var, using default",
-                    AVS_NODE_ECDSA_PRIVATE_KEY
-                );
-                "0x4c0883a69102937d6231471b5dbb6204fe512961708279f2e3e8a5d4b8e3e3e8".to_string()
-            });
-
-        const AVS_PRECONF_TASK_MANAGER_CONTRACT_ADDRESS: &str =
-            "AVS_PRECONF_TASK_MANAGER_CONTRACT_ADDRESS";
-        let preconf_task_manager = std::env::var(AVS_PRECONF_TASK_MANAGER_CONTRACT_ADDRESS)
-            .unwrap_or_else(|_| {
-                warn!("No AVS preconf task manager contract address found in {} env var, using default", AVS_PRECONF_TASK_MANAGER_CONTRACT_ADDRESS);
-                default_empty_address.clone()
-            });
-
-        const AVS_DIRECTORY_CONTRACT_ADDRESS: &str = "AVS_DIRECTORY_CONTRACT_ADDRESS";
-        let directory = std::env::var(AVS_DIRECTORY_CONTRACT_ADDRESS).unwrap_or_else(|_| {
-            warn!(
-                "No AVS directory contract address found in {} env var, using default",
-                AVS_DIRECTORY_CONTRACT_ADDRESS
-            );
-            default_empty_address.clone()
-        });
+impl FromStr for Network {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "devnet" => Ok(Network::Devnet),
+            "helder" => Ok(Network::Helder),
+            _ => Err(anyhow::anyhow!(
+                "unknown network {}, expected devnet or helder",
+                s
+            )),
+        }
+    }
+}
 
-        const AVS_SERVICE_MANAGER_CONTRACT_ADDRESS: &str = "AVS_SERVICE_MANAGER_CONTRACT_ADDRESS";
-        let service_manager =
-            std::env::var(AVS_SERVICE_MANAGER_CONTRACT_ADDRESS).unwrap_or_else(|_| {
-                warn!(
-                    "No AVS service manager contract address found in {} env var, using default",
-                    AVS_SERVICE_MANAGER_CONTRACT_ADDRESS
-                );
-                default_empty_address.clone()
-            });
-
-        const AVS_PRECONF_REGISTRY_CONTRACT_ADDRESS: &str = "AVS_PRECONF_REGISTRY_CONTRACT_ADDRESS";
-        let preconf_registry =
-            std::env::var(AVS_PRECONF_REGISTRY_CONTRACT_ADDRESS).unwrap_or_else(|_| {
-                warn!(
-                    "No AVS preconf registry contract address found in {} env var, using default",
-                    AVS_PRECONF_REGISTRY_CONTRACT_ADDRESS
-                );
-                default_empty_address.clone()
-            });
-
-        let avs = AvsContractAddresses {
-            preconf_task_manager,
-            directory,
-            service_manager,
-            preconf_registry,
-        };
+impl Network {
+    fn settings(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Network::Devnet => &[
+                ("DEV_MODE", "true"),
+                ("L1_CHAIN_ID", "3151908"),
+                ("TAIKO_CHAIN_ID", "167"),
+                ("VALIDATOR_INDEX", "1"),
+                ("MEV_BOOST_URL", "http://127.0.0.1:33661"),
+                ("L1_WS_RPC_URL", "ws://127.0.0.1:32003"),
+                ("L1_BEACON_URL", "http://127.0.0.1:33001"),
+            ],
+            Network::Helder => &[
+                ("L1_CHAIN_ID", "7014190335"),
+                ("L1_SLOT_DURATION_SEC", "12"),
+                ("L1_SLOTS_PER_EPOCH", "32"),
+            ],
+        }
+    }
+}
 
-        const EIGEN_LAYER_STRATEGY_MANAGER_CONTRACT_ADDRESS: &str =
-            "EIGEN_LAYER_STRATEGY_MANAGER_CONTRACT_ADDRESS";
-        let strategy_manager = std::env::var(EIGEN_LAYER_STRATEGY_MANAGER_CONTRACT_ADDRESS).unwrap_or_else(|_| {
-            warn!("No Eigen Layer strategy manager contract address found in {} env var, using default", EIGEN_LAYER_STRATEGY_MANAGER_CONTRACT_ADDRESS);
-            default_empty_address.clone()
-        });
+/// Raw settings collected from all the sources, before they are parsed into a [`Config`].
+#[derive(Default)]
+pub struct ConfigValues {
+    values: BTreeMap<String, String>,
+    errors: Vec<String>,
+}
 
-        const EIGEN_LAYER_SLASHER_CONTRACT_ADDRESS: &str = "EIGEN_LAYER_SLASHER_CONTRACT_ADDRESS";
-        let slasher = std::env::var(EIGEN_LAYER_SLASHER_CONTRACT_ADDRESS).unwrap_or_else(|_| {
-            warn!(
-                "No Eigen Layer slasher contract address found in {} env var, using default",
-                EIGEN_LAYER_SLASHER_CONTRACT_ADDRESS
-            );
-            default_empty_address.clone()
-        });
+impl Drop for ConfigValues {
+    // the values may contain private keys
+    fn drop(&mut self) {
+        self.values.values_mut().for_each(|value| value.zeroize());
+    }
+}
 
-        let eigen_layer = EigenLayerContractAddresses {
-            strategy_manager,
-            slasher,
-        };
+impl ConfigValues {
+    fn with_defaults() -> Self {
+        let mut values = Self::default();
+        for (name, value) in DEFAULTS {
+            values.set(name, value.to_string(), "defaults");
+        }
+        values
+    }
 
-        const TAIKO_L1_ADDRESS: &str = "TAIKO_L1_ADDRESS";
-        let taiko_l1 = std::env::var(TAIKO_L1_ADDRESS).unwrap_or_else(|_| {
-            warn!(
-                "No TaikoL1 contract address found in {} env var, using default",
-                TAIKO_L1_ADDRESS
-            );
-            default_empty_address.clone()
-        });
+    fn set(&mut self, name: &str, value: String, source: &str) {
+        let name = name.to_uppercase();
+        if SETTINGS.contains(&name.as_str()) {
+            self.values.insert(name, value);
+        } else {
+            self.errors
+                .push(format!("{}: unknown setting {}", source, name));
+        }
+    }
 
-        let contract_addresses = ContractAddresses {
-            taiko_l1,
-            eigen_layer,
-            avs,
-        };
+    fn get(&self, name: &str) -> Option<&str> {
+        self.values
+            .get(name)
+            .map(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+    }
 
-        let l1_slot_duration_sec = std::env::var("L1_SLOT_DURATION_SEC")
-            .unwrap_or("12".to_string())
-            .parse::<u64>()
-            .map(|val| {
-                if val == 0 {
-                    panic!("L1_SLOT_DURATION_SEC must be a positive number");
-                }
-                val
-            })
-            .expect("L1_SLOT_DURATION_SEC must be a number");
-
-        let l1_slots_per_epoch = std::env::var("L1_SLOTS_PER_EPOCH")
-            .unwrap_or("32".to_string())
-            .parse::<u64>()
-            .map(|val| {
-                if val == 0 {
-                    panic!("L1_SLOTS_PER_EPOCH must be a positive number");
+    fn apply_network(&mut self, network: &str) {
+        match network.parse::<Network>() {
+            Ok(network) => {
+                for (name, value) in network.settings() {
+                    self.set(name, value.to_string(), "network preset");
                 }
-                val
-            })
-            .expect("L1_SLOTS_PER_EPOCH must be a number");
-
-        let l2_slot_duration_sec = std::env::var("L2_SLOT_DURATION_SEC")
-            .unwrap_or("3".to_string())
-            .parse::<u64>()
-            .map(|val| {
-                if val == 0 {
-                    panic!("L2_SLOT_DURATION_SEC must be a positive number");
-                }
-                val
-            })
-            .expect("L2_SLOT_DURATION_SEC must be a number");
+            }
+            Err(e) => self.errors.push(format!("NETWORK: {}", e)),
+        }
+    }
+
+    fn apply_file(&mut self, path: &str) {
+        match std::fs::read_to_string(path) {
+            Ok(content) => self.apply_file_content(path, &content),
+            Err(e) => self
+                .errors
+                .push(format!("failed to read config file {}: {}", path, e)),
+        }
+    }
+
+    fn apply_file_content(&mut self, path: &str, content: &str) {
+        let parsed: Result<BTreeMap<String, serde_json::Value>, String> =
+            if path.ends_with(".yaml") || path.ends_with(".yml") {
+                serde_yaml::from_str(content).map_err(|e| e.to_string())
+            } else {
+                toml::from_str(content).map_err(|e| e.to_string())
+            };
+        let parsed = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.errors
+                    .push(format!("failed to parse config file {}: {}", path, e));
+                return;
+            }
+        };
+
+        for (name, value) in parsed {
+            match value_to_string(&value) {
+                Some(value) => self.set(&name, value, path),
+                None => self
+                    .errors
+                    .push(format!("{}: unsupported value for {}", path, name)),
+            }
+        }
+    }
+
+    fn apply_env(&mut self) {
+        for name in SETTINGS {
+            if let Ok(value) = std::env::var(name) {
+                self.set(name, value, "env");
+            }
+        }
+    }
+
+    fn apply_overrides(&mut self, overrides: &[String]) {
+        for setting in overrides {
+            match setting.split_once('=') {
+                Some((name, value)) => self.set(name.trim(), value.to_string(), "--set"),
+                None => self
+                    .errors
+                    .push(format!("--set {}: expected NAME=VALUE", setting)),
+            }
+        }
+    }
+}
+
+// Lists, e.g. of boot nodes, are kept comma separated like in the env vars.
+fn value_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        serde_json::Value::Bool(value) => Some(value.to_string()),
+        serde_json::Value::Array(values) => values
+            .iter()
+            .map(value_to_string)
+            .collect::<Option<Vec<_>>>()
+            .map(|values| values.join(",")),
+        _ => None,
+    }
+}
+
+// Parses the settings, recording every problem instead of stopping at the first one.
+struct SettingsParser<'a> {
+    values: &'a ConfigValues,
+    errors: Vec<String>,
+}
+
+impl<'a> SettingsParser<'a> {
+    fn optional<T>(&mut self, name: &str) -> Option<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let value = self.values.get(name)?;
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.push(format!("{}: invalid value: {}", name, e));
+                None
+            }
+        }
+    }
+
+    fn required<T>(&mut self, name: &str) -> T
+    where
+        T: FromStr + Default,
+        T::Err: Display,
+    {
+        if self.values.get(name).is_none() {
+            self.errors.push(format!("{}: must be set", name));
+        }
+        self.optional(name).unwrap_or_default()
+    }
 
-        const VALIDATOR_BLS_PRIVATEKEY: &str = "VALIDATOR_BLS_PRIVATEKEY";
-        let validator_bls_privkey = std::env::var(VALIDATOR_BLS_PRIVATEKEY).unwrap_or_else(|_| {
+    fn positive(&mut self, name: &str) -> u64 {
+        let value = self.required(name);
+        if value == 0
+            && self
+                .values
+                .get(name)
+                .is_some_and(|v| v.parse::<u64>().is_ok())
+        {
+            self.errors
+                .push(format!("{}: must be a positive number", name));
+        }
+        value
+    }
+
+    fn url(&mut self, name: &str) -> String {
+        let value: String = self.required(name);
+        if !value.is_empty() {
+            if let Err(e) = reqwest::Url::parse(&value) {
+                self.errors.push(format!("{}: invalid URL: {}", name, e));
+            }
+        }
+        value
+    }
+
+    // The contracts may be left unset on a dev network, they are required otherwise.
+    fn contract_address(&mut self, name: &str, dev_mode: bool) -> Address {
+        if dev_mode && self.values.get(name).is_none() {
             warn!(
-                "No validator private key found in {} env var, using default",
-                VALIDATOR_BLS_PRIVATEKEY
+                "No contract address set in {}, using the zero address",
+                name
             );
-            "0x0".to_string()
-        });
+            return Address::ZERO;
+        }
+        let address: Address = self.required(name);
+        if address == Address::ZERO && !dev_mode {
+            self.errors
+                .push(format!("{}: must not be the zero address", name));
+        }
+        address
+    }
 
-        let msg_expiry_sec = std::env::var("MSG_EXPIRY_SEC")
-            .unwrap_or("3600".to_string())
-            .parse::<u64>()
-            .expect("MSG_EXPIRY_SEC must be a number");
+    fn gwei(&mut self, name: &str, default: u128) -> u128 {
+        self.optional::<u128>(name)
+            .map(|gwei| gwei * GWEI)
+            .unwrap_or(default)
+    }
 
-        // Load P2P config from env
-        // Load Ipv4 address from env
-        let address = std::env::var("P2P_ADDRESS").unwrap_or("0.0.0.0".to_string());
-        let ipv4 = address.parse().unwrap();
+    fn existing_path(&mut self, name: &str) -> String {
+        let path: String = self.required(name);
+        if !path.is_empty() && !Path::new(&path).exists() {
+            self.errors
+                .push(format!("{}: {} does not exist", name, path));
+        }
+        path
+    }
 
-        // Load boot node from env
-        let boot_nodes: Option<Vec<String>> =
-            if let Ok(bootnode_enr) = std::env::var("P2P_BOOTNODE_ENR") {
-                Some(vec![bootnode_enr])
-            } else {
-                None
+    // A key is read from an encrypted keystore if its path is set, from a hex private key
+    // otherwise. The built-in default key is only accepted in dev mode.
+    fn key_source(
+        &mut self,
+        private_key_name: &str,
+        keystore_path_name: &str,
+        password_path_name: &str,
+        default_key: &str,
+        default_key_allowed: bool,
+    ) -> KeySource {
+        if self.values.get(keystore_path_name).is_some() {
+            return KeySource::Keystore {
+                path: self.existing_path(keystore_path_name),
+                password_path: self.existing_path(password_path_name),
             };
+        }
+
+        if let Some(private_key) = self.values.get(private_key_name) {
+            return KeySource::PrivateKey(Zeroizing::new(private_key.to_string()));
+        }
+
+        if !default_key_allowed {
+            self.errors.push(format!(
+                "{}: no key set in {} or {}, the default key is only used with DEV_MODE=true",
+                private_key_name, keystore_path_name, private_key_name
+            ));
+        } else {
+            warn!(
+                "No key found in {} or {}, using default",
+                keystore_path_name, private_key_name
+            );
+        }
+        KeySource::PrivateKey(Zeroizing::new(default_key.to_string()))
+    }
+}
 
-        // Create P2P network config
-        let p2p_network_config: P2PNetworkConfig = P2PNetworkConfig {
-            local_key: generate_secp256k1(),
-            listen_addr: "/ip4/0.0.0.0/tcp/9000".parse().unwrap(),
-            ipv4,
-            udpv4: 9000,
-            tcpv4: 9000,
-            boot_nodes,
+impl Config {
+    /// Loads the configuration from, in increasing priority, the built-in defaults, the network
+    /// preset, the config file, the env vars and the command line overrides. All the problems
+    /// found are reported together.
+    pub fn load(
+        config_file: Option<&str>,
+        network: Option<&str>,
+        overrides: &[String],
+    ) -> Result<Self, Error> {
+        // Load environment variables from .env file
+        dotenv::dotenv().ok();
+
+        let mut values = ConfigValues::with_defaults();
+        if let Some(network) = network
+            .map(str::to_string)
+            .or_else(|| std::env::var("NETWORK").ok())
+        {
+            values.apply_network(&network);
+        }
+        if let Some(config_file) = config_file
+            .map(str::to_string)
+            .or_else(|| std::env::var("CONFIG_FILE").ok())
+        {
+            values.apply_file(&config_file);
+        }
+        values.apply_env();
+        values.apply_overrides(overrides);
+
+        let config = Self::from_values(&values)?;
+        config.log();
+        Ok(config)
+    }
+
+    fn from_values(values: &ConfigValues) -> Result<Self, Error> {
+        let mut parser = SettingsParser {
+            values,
+            errors: values.errors.clone(),
         };
 
-        let taiko_chain_id = std::env::var("TAIKO_CHAIN_ID")
-            .expect("TAIKO_CHAIN_ID env variable must be set")
-            .parse::<u64>()
-            .map(|val| {
-                if val == 0 {
-                    panic!("TAIKO_CHAIN_ID must be a positive number");
-                }
-                val
-            })
-            .expect("TAIKO_CHAIN_ID must be a number");
-
-        let l1_chain_id = std::env::var("L1_CHAIN_ID")
-            .unwrap_or("1".to_string())
-            .parse::<u64>()
-            .map(|val| {
-                if val == 0 {
-                    panic!("L1_CHAIN_ID must be a positive number");
+        let dev_mode: bool = parser.required("DEV_MODE");
+        let watchtower: bool = parser.required("WATCHTOWER");
+
+        // The ECDSA key is held by Web3Signer instead of the settings below if its URL is set,
+        // and the BLS key too if its public key is set.
+        let web3signer = match values.get("WEB3SIGNER_URL") {
+            Some(_) => {
+                let url = parser.url("WEB3SIGNER_URL");
+                let ecdsa_address = parser.required("WEB3SIGNER_ECDSA_ADDRESS");
+                let bls_public_key: Option<String> = parser.optional("WEB3SIGNER_BLS_PUBLIC_KEY");
+                if bls_public_key
+                    .as_ref()
+                    .is_some_and(|key| alloy::hex::decode(key).map_or(true, |key| key.len() != 48))
+                {
+                    parser.errors.push(
+                        "WEB3SIGNER_BLS_PUBLIC_KEY: must be a 48 bytes hex public key".to_string(),
+                    );
                 }
-                val
-            })
-            .expect("L1_CHAIN_ID must be a number");
-
-        let validator_index = std::env::var("VALIDATOR_INDEX")
-            .expect("VALIDATOR_INDEX env variable must be set")
-            .parse::<u64>()
-            .expect("VALIDATOR_INDEX must be a number");
+                Some(Web3SignerConfig {
+                    url,
+                    ecdsa_address,
+                    bls_public_key,
+                })
+            }
+            None => None,
+        };
+        let remote_bls_key = web3signer
+            .as_ref()
+            .is_some_and(|web3signer| web3signer.bls_public_key.is_some());
+
+        let avs_node_ecdsa_key = parser.key_source(
+            "AVS_NODE_ECDSA_PRIVATE_KEY",
+            "AVS_NODE_ECDSA_KEYSTORE_PATH",
+            "AVS_NODE_ECDSA_KEYSTORE_PASSWORD_PATH",
+            DEFAULT_ECDSA_PRIVATE_KEY,
+            dev_mode || web3signer.is_some(),
+        );
+        let validator_bls_key = (!watchtower && !remote_bls_key).then(|| {
+            parser.key_source(
+                "VALIDATOR_BLS_PRIVATEKEY",
+                "VALIDATOR_BLS_KEYSTORE_PATH",
+                "VALIDATOR_BLS_KEYSTORE_PASSWORD_PATH",
+                DEFAULT_BLS_PRIVATE_KEY,
+                dev_mode,
+            )
+        });
+        let validator_index = if watchtower {
+            parser.optional("VALIDATOR_INDEX").unwrap_or_default()
+        } else {
+            parser.required("VALIDATOR_INDEX")
+        };
 
-        let enable_p2p = std::env::var("ENABLE_P2P")
-            .unwrap_or("true".to_string())
-            .parse::<bool>()
-            .expect("ENABLE_P2P must be a boolean");
+        let contract_addresses = ContractAddresses {
+            taiko_l1: parser.contract_address("TAIKO_L1_ADDRESS", dev_mode),
+            eigen_layer: EigenLayerContractAddresses {
+                strategy_manager: parser
+                    .contract_address("EIGEN_LAYER_STRATEGY_MANAGER_CONTRACT_ADDRESS", dev_mode),
+                slasher: parser.contract_address("EIGEN_LAYER_SLASHER_CONTRACT_ADDRESS", dev_mode),
+            },
+            avs: AvsContractAddresses {
+                preconf_task_manager: parser
+                    .contract_address("AVS_PRECONF_TASK_MANAGER_CONTRACT_ADDRESS", dev_mode),
+                directory: parser.contract_address("AVS_DIRECTORY_CONTRACT_ADDRESS", dev_mode),
+                service_manager: parser
+                    .contract_address("AVS_SERVICE_MANAGER_CONTRACT_ADDRESS", dev_mode),
+                preconf_registry: parser
+                    .contract_address("AVS_PRECONF_REGISTRY_CONTRACT_ADDRESS", dev_mode),
+            },
+        };
 
-        let enable_preconfirmation = std::env::var("ENABLE_PRECONFIRMATION")
-            .unwrap_or("true".to_string())
-            .parse::<bool>()
-            .expect("ENABLE_PRECONFIRMATION must be a boolean");
+        let boot_nodes: Option<Vec<String>> = values.get("P2P_BOOTNODE_ENR").map(|enrs| {
+            enrs.split(',')
+                .map(|enr| enr.trim().to_string())
+                .filter(|enr| !enr.is_empty())
+                .collect()
+        });
+        let taiko_chain_id = parser.positive("TAIKO_CHAIN_ID");
+        let default_gossip = GossipConfig::default();
+        let gossip = GossipConfig {
+            heartbeat_interval: parser
+                .optional("P2P_HEARTBEAT_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(default_gossip.heartbeat_interval),
+            topic_weight: parser
+                .optional("P2P_TOPIC_WEIGHT")
+                .unwrap_or(default_gossip.topic_weight),
+            invalid_message_weight: parser
+                .optional("P2P_INVALID_MESSAGE_WEIGHT")
+                .unwrap_or(default_gossip.invalid_message_weight),
+            ip_colocation_factor_weight: parser
+                .optional("P2P_IP_COLOCATION_WEIGHT")
+                .unwrap_or(default_gossip.ip_colocation_factor_weight),
+            ip_colocation_factor_threshold: parser
+                .optional("P2P_IP_COLOCATION_THRESHOLD")
+                .unwrap_or(default_gossip.ip_colocation_factor_threshold),
+            gossip_threshold: parser
+                .optional("P2P_GOSSIP_THRESHOLD")
+                .unwrap_or(default_gossip.gossip_threshold),
+            publish_threshold: parser
+                .optional("P2P_PUBLISH_THRESHOLD")
+                .unwrap_or(default_gossip.publish_threshold),
+            graylist_threshold: parser
+                .optional("P2P_GRAYLIST_THRESHOLD")
+                .unwrap_or(default_gossip.graylist_threshold),
+            ..default_gossip
+        };
+        if let Err(e) = gossip.validate() {
+            parser.errors.push(format!("P2P gossip settings: {}", e));
+        }
+        let data_dir: String = parser.required("DATA_DIR");
+        let max_messages_per_sec: u32 = parser
+            .optional("P2P_MAX_MESSAGES_PER_SEC")
+            .unwrap_or(DEFAULT_MAX_MESSAGES_PER_SEC);
+        if max_messages_per_sec == 0 {
+            parser
+                .errors
+                .push("P2P_MAX_MESSAGES_PER_SEC: must be a positive number".to_string());
+        }
+        let p2p_network_config = P2PNetworkConfig {
+            local_key: generate_secp256k1(),
+            ipv4: parser
+                .optional("P2P_ADDRESS")
+                .unwrap_or(Ipv4Addr::UNSPECIFIED),
+            udpv4: parser.required("P2P_UDP_PORT"),
+            tcpv4: parser.required("P2P_TCP_PORT"),
+            ipv6: parser.optional("P2P_IPV6_ADDRESS"),
+            udpv6: parser.required("P2P_UDP6_PORT"),
+            tcpv6: parser.required("P2P_TCP6_PORT"),
+            external_ipv4: parser.optional("P2P_EXTERNAL_ADDRESS"),
+            external_ipv6: parser.optional("P2P_EXTERNAL_IPV6_ADDRESS"),
+            boot_nodes,
+            signed_messages: parser.required("P2P_SIGNED_MESSAGES"),
+            gossip,
+            chain_id: taiko_chain_id,
+            peer_db_path: Some(Path::new(&data_dir).join(PEER_DB_FILE_NAME)),
+            max_messages_per_sec,
+        };
+        if p2p_network_config.external_ipv6.is_some() && p2p_network_config.ipv6.is_none() {
+            parser
+                .errors
+                .push("P2P_EXTERNAL_IPV6_ADDRESS: requires P2P_IPV6_ADDRESS".to_string());
+        }
+        // The identity is only bound to the ECDSA key when the messages are signed with it, and
+        // the key has to be held by the node
+        let p2p_bind_preconfer_identity: bool = parser.required("P2P_BIND_PRECONFER_IDENTITY");
+        if p2p_bind_preconfer_identity && !p2p_network_config.signed_messages {
+            parser
+                .errors
+                .push("P2P_BIND_PRECONFER_IDENTITY: requires P2P_SIGNED_MESSAGES=true".to_string());
+        }
+        if p2p_bind_preconfer_identity && web3signer.is_some() {
+            parser.errors.push(
+                "P2P_BIND_PRECONFER_IDENTITY: not supported with WEB3SIGNER_URL, the ECDSA key is needed locally".to_string(),
+            );
+        }
+        // The author of a gossip message is only known when the messages are signed
+        let p2p_require_registered_author: bool = parser.required("P2P_REQUIRE_REGISTERED_AUTHOR");
+        if p2p_require_registered_author && !p2p_network_config.signed_messages {
+            parser.errors.push(
+                "P2P_REQUIRE_REGISTERED_AUTHOR: requires P2P_SIGNED_MESSAGES=true".to_string(),
+            );
+        }
+
+        // The watchtower only hears of the preconfirmations through the gossip
+        let enable_p2p: bool = parser.required("ENABLE_P2P");
+        if watchtower && !enable_p2p {
+            parser
+                .errors
+                .push("WATCHTOWER: requires ENABLE_P2P=true".to_string());
+        }
+
+        let default_gas_oracle = GasOracleConfig::default();
+        let gas_oracle = GasOracleConfig {
+            max_fee_per_gas_cap: parser.gwei(
+                "MAX_FEE_PER_GAS_CAP_GWEI",
+                default_gas_oracle.max_fee_per_gas_cap,
+            ),
+            max_priority_fee_per_gas_cap: parser.gwei(
+                "MAX_PRIORITY_FEE_PER_GAS_CAP_GWEI",
+                default_gas_oracle.max_priority_fee_per_gas_cap,
+            ),
+            gas_limit_margin_percent: parser
+                .optional("GAS_LIMIT_MARGIN_PERCENT")
+                .unwrap_or(default_gas_oracle.gas_limit_margin_percent),
+            fee_bump_percent: parser
+                .optional("FEE_BUMP_PERCENT")
+                .unwrap_or(default_gas_oracle.fee_bump_percent),
+        };
+        if gas_oracle.fee_bump_percent < 10 {
+            parser
+                .errors
+                .push("FEE_BUMP_PERCENT: must be at least 10".to_string());
+        }
 
         let config = Self {
-            taiko_proposer_url: std::env::var("TAIKO_PROPOSER_URL")
-                .unwrap_or("http://127.0.0.1:1234".to_string()),
-            taiko_driver_url: std::env::var("TAIKO_DRIVER_URL")
-                .unwrap_or("http://127.0.0.1:1235".to_string()),
-
-            avs_node_ecdsa_private_key,
-            mev_boost_url: std::env::var("MEV_BOOST_URL")
-                .unwrap_or("http://127.0.0.1:8080".to_string()),
-            l1_ws_rpc_url: std::env::var("L1_WS_RPC_URL").unwrap_or("wss://127.0.0.1".to_string()),
-            l1_beacon_url: std::env::var("L1_BEACON_URL")
-                .unwrap_or("http://127.0.0.1:4000".to_string()),
-            l1_slot_duration_sec,
-            l1_slots_per_epoch,
-            l2_slot_duration_sec,
-            validator_bls_privkey,
-            msg_expiry_sec,
+            taiko_proposer_url: parser.url("TAIKO_PROPOSER_URL"),
+            taiko_driver_url: parser.url("TAIKO_DRIVER_URL"),
+            avs_node_ecdsa_key,
+            mev_boost_url: parser.url("MEV_BOOST_URL"),
+            l1_ws_rpc_url: parser.url("L1_WS_RPC_URL"),
+            l1_beacon_url: parser.url("L1_BEACON_URL"),
+            l1_slot_duration_sec: parser.positive("L1_SLOT_DURATION_SEC"),
+            l1_slots_per_epoch: parser.positive("L1_SLOTS_PER_EPOCH"),
+            l2_slot_duration_sec: parser.positive("L2_SLOT_DURATION_SEC"),
+            validator_bls_key,
+            msg_expiry_sec: parser.required("MSG_EXPIRY_SEC"),
             contract_addresses,
             p2p_network_config,
             taiko_chain_id,
-            l1_chain_id,
+            l1_chain_id: parser.positive("L1_CHAIN_ID"),
             validator_index,
             enable_p2p,
-            enable_preconfirmation,
+            p2p_bind_preconfer_identity,
+            p2p_require_registered_author,
+            enable_preconfirmation: parser.required("ENABLE_PRECONFIRMATION"),
+            watchtower,
+            enable_metrics: parser.required("ENABLE_METRICS"),
+            metrics_port: parser.required("METRICS_PORT"),
+            enable_preconf_api: parser.required("ENABLE_PRECONF_API"),
+            preconf_api_address: parser
+                .optional("PRECONF_API_ADDRESS")
+                .unwrap_or(Ipv4Addr::LOCALHOST),
+            preconf_api_port: parser.required("PRECONF_API_PORT"),
+            data_dir,
+            gas_oracle,
+            web3signer,
         };
 
+        if !parser.errors.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Invalid configuration:\n  {}",
+                parser.errors.join("\n  ")
+            ));
+        }
+        Ok(config)
+    }
+
+    fn log(&self) {
         info!(
             r#"
 Configuration:
@@ -292,26 +763,185 @@ taiko chain id: {}
 l1 chain id: {}
 validator index: {}
 enable p2p: {}
+bind p2p identity to preconfer: {}
+require registered author: {}
 enable preconfirmation: {}
+watchtower: {}
+enable metrics: {}
+metrics port: {}
+enable preconf API: {}
+preconf API address: {}
+preconf API port: {}
+data dir: {}
+gas oracle: {:#?}
+web3signer: {:#?}
 "#,
-            config.taiko_proposer_url,
-            config.taiko_driver_url,
-            config.mev_boost_url,
-            config.l1_ws_rpc_url,
-            config.l1_beacon_url,
-            config.l1_slot_duration_sec,
-            config.l1_slots_per_epoch,
-            config.l2_slot_duration_sec,
-            config.msg_expiry_sec,
-            config.contract_addresses,
-            config.p2p_network_config,
-            config.taiko_chain_id,
-            config.l1_chain_id,
-            config.validator_index,
-            config.enable_p2p,
-            config.enable_preconfirmation,
+            self.taiko_proposer_url,
+            self.taiko_driver_url,
+            self.mev_boost_url,
+            self.l1_ws_rpc_url,
+            self.l1_beacon_url,
+            self.l1_slot_duration_sec,
+            self.l1_slots_per_epoch,
+            self.l2_slot_duration_sec,
+            self.msg_expiry_sec,
+            self.contract_addresses,
+            self.p2p_network_config,
+            self.taiko_chain_id,
+            self.l1_chain_id,
+            self.validator_index,
+            self.enable_p2p,
+            self.p2p_bind_preconfer_identity,
+            self.p2p_require_registered_author,
+            self.enable_preconfirmation,
+            self.watchtower,
+            self.enable_metrics,
+            self.metrics_port,
+            self.enable_preconf_api,
+            self.preconf_api_address,
+            self.preconf_api_port,
+            self.data_dir,
+            self.gas_oracle,
+            self.web3signer,
         );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG_FILE: &str = r#"
+taiko_chain_id = 167
+validator_index = 3
+l1_ws_rpc_url = "ws://127.0.0.1:32003"
+avs_node_ecdsa_private_key = "0x4c0883a69102937d6231471b5dbb6204fe512961708279f2e3e8a5d4b8e3e3e8"
+validator_bls_privatekey = "0x14d50ac943d01069c206543a0bed3836f6062b35270607ebf1d1f238ceda26f1"
+p2p_bootnode_enr = ["enr:-first", "enr:-second"]
+taiko_l1_address = "0x1000000000000000000000000000000000000001"
+avs_preconf_task_manager_contract_address = "0x1000000000000000000000000000000000000002"
+avs_directory_contract_address = "0x1000000000000000000000000000000000000003"
+avs_service_manager_contract_address = "0x1000000000000000000000000000000000000004"
+avs_preconf_registry_contract_address = "0x1000000000000000000000000000000000000005"
+eigen_layer_strategy_manager_contract_address = "0x1000000000000000000000000000000000000006"
+eigen_layer_slasher_contract_address = "0x1000000000000000000000000000000000000007"
+"#;
+
+    #[test]
+    fn test_layered_settings() {
+        let mut values = ConfigValues::with_defaults();
+        values.apply_network("helder");
+        values.apply_file_content("config.toml", CONFIG_FILE);
+        values.apply_overrides(&["VALIDATOR_INDEX=5".to_string()]);
+
+        let config = Config::from_values(&values).unwrap();
+        assert_eq!(config.l1_chain_id, 7014190335);
+        assert_eq!(config.taiko_chain_id, 167);
+        assert_eq!(config.validator_index, 5);
+        assert_eq!(config.l1_ws_rpc_url, "ws://127.0.0.1:32003");
+        assert_eq!(
+            config.contract_addresses.avs.preconf_registry,
+            "0x1000000000000000000000000000000000000005"
+                .parse::<Address>()
+                .unwrap()
+        );
+        assert_eq!(
+            config.p2p_network_config.boot_nodes,
+            Some(vec!["enr:-first".to_string(), "enr:-second".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_yaml_file() {
+        let mut values = ConfigValues::with_defaults();
+        values.apply_network("devnet");
+        values.apply_file_content(
+            "config.yaml",
+            "validator_index: 7\nfee_bump_percent: 20\nmax_fee_per_gas_cap_gwei: 50\n",
+        );
+
+        let config = Config::from_values(&values).unwrap();
+        assert_eq!(config.validator_index, 7);
+        assert_eq!(config.gas_oracle.fee_bump_percent, 20);
+        assert_eq!(config.gas_oracle.max_fee_per_gas_cap, 50 * GWEI);
+        // the devnet runs with the default keys and unset contracts
+        assert_eq!(config.contract_addresses.taiko_l1, Address::ZERO);
+    }
+
+    #[test]
+    fn test_watchtower_without_validator() {
+        let mut values = ConfigValues::with_defaults();
+        values.apply_file_content("config.toml", CONFIG_FILE);
+        values.apply_overrides(&["WATCHTOWER=true".to_string()]);
+        values.set("VALIDATOR_INDEX", String::new(), "test");
+        values.set("VALIDATOR_BLS_PRIVATEKEY", String::new(), "test");
+
+        let config = Config::from_values(&values).unwrap();
+        assert!(config.watchtower);
+        assert!(config.validator_bls_key.is_none());
+
+        values.apply_overrides(&["ENABLE_P2P=false".to_string()]);
+        let errors = Config::from_values(&values).err().unwrap().to_string();
+        assert!(errors.contains("WATCHTOWER: requires ENABLE_P2P=true"));
+    }
+
+    #[test]
+    fn test_web3signer_keys() {
+        let mut values = ConfigValues::with_defaults();
+        values.apply_file_content("config.toml", CONFIG_FILE);
+        values.apply_overrides(&[
+            "WEB3SIGNER_URL=http://127.0.0.1:9000".to_string(),
+            format!("WEB3SIGNER_ECDSA_ADDRESS={}", Address::ZERO),
+        ]);
+        values.set("AVS_NODE_ECDSA_PRIVATE_KEY", String::new(), "test");
+
+        // the BLS key stays local without its public key
+        let config = Config::from_values(&values).unwrap();
+        assert!(config.web3signer.unwrap().bls_public_key.is_none());
+        assert!(config.validator_bls_key.is_some());
+
+        values.apply_overrides(&[format!("WEB3SIGNER_BLS_PUBLIC_KEY=0x{}", "00".repeat(48))]);
+        values.set("VALIDATOR_BLS_PRIVATEKEY", String::new(), "test");
+        let config = Config::from_values(&values).unwrap();
+        assert!(config.web3signer.unwrap().bls_public_key.is_some());
+        assert!(config.validator_bls_key.is_none());
+
+        values.apply_overrides(&["WEB3SIGNER_BLS_PUBLIC_KEY=0x00".to_string()]);
+        let errors = Config::from_values(&values).err().unwrap().to_string();
+        assert!(errors.contains("WEB3SIGNER_BLS_PUBLIC_KEY: must be a 48 bytes hex public key"));
+    }
 
-        config
+    #[test]
+    fn test_all_problems_are_reported() {
+        let mut values = ConfigValues::with_defaults();
+        values.apply_file_content("config.toml", CONFIG_FILE);
+        values.apply_overrides(&[
+            "TAIKO_L1_ADDRESS=0x1234".to_string(),
+            "L1_SLOT_DURATION_SEC=0".to_string(),
+            "MEV_BOOST_URL=not a url".to_string(),
+            "UNKNOWN_SETTING=1".to_string(),
+            "VALIDATOR_INDEX".to_string(),
+            "P2P_BIND_PRECONFER_IDENTITY=true".to_string(),
+            "P2P_REQUIRE_REGISTERED_AUTHOR=true".to_string(),
+            "P2P_INVALID_MESSAGE_WEIGHT=10".to_string(),
+            "P2P_EXTERNAL_IPV6_ADDRESS=2001:db8::1".to_string(),
+        ]);
+        values.set("AVS_NODE_ECDSA_PRIVATE_KEY", String::new(), "test");
+
+        let errors = Config::from_values(&values).err().unwrap().to_string();
+        for expected in [
+            "TAIKO_L1_ADDRESS: invalid value",
+            "L1_SLOT_DURATION_SEC: must be a positive number",
+            "MEV_BOOST_URL: invalid URL",
+            "unknown setting UNKNOWN_SETTING",
+            "--set VALIDATOR_INDEX: expected NAME=VALUE",
+            "AVS_NODE_ECDSA_PRIVATE_KEY: no key set",
+            "P2P_BIND_PRECONFER_IDENTITY: requires P2P_SIGNED_MESSAGES=true",
+            "P2P_REQUIRE_REGISTERED_AUTHOR: requires P2P_SIGNED_MESSAGES=true",
+            "P2P gossip settings: Invalid score parameters",
+            "P2P_EXTERNAL_IPV6_ADDRESS: requires P2P_IPV6_ADDRESS",
+        ] {
+            assert!(errors.contains(expected), "{} not in {}", expected, errors);
+        }
     }
 }
@@ -1,16 +1,98 @@
-use discv5::{enr, enr::CombinedKey, ConfigBuilder, Discv5, Event, ListenConfig};
-use std::fs::File;
+use clap::Parser;
+use discv5::enr::NodeId;
+use discv5::{enr, enr::CombinedKey, ConfigBuilder, Discv5, Enr, Event, ListenConfig};
+use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
 use std::net::Ipv4Addr;
-use tracing::{error, info};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
 
-const BOOT_NODE_PATH: &str = "/shared/enr.txt";
+#[derive(Parser)]
+#[clap(about = "Discv5 boot node of the AVS p2p network")]
+struct Cli {
+    #[clap(long, default_value = "0.0.0.0", help = "Address to listen on")]
+    listen_address: Ipv4Addr,
+    #[clap(long, default_value_t = 9000, help = "UDP port to listen on")]
+    port: u16,
+    #[clap(
+        long,
+        help = "Address advertised in the ENR, learnt from the peers when unset"
+    )]
+    external_address: Option<Ipv4Addr>,
+    #[clap(long, help = "UDP port advertised in the ENR, defaults to --port")]
+    external_port: Option<u16>,
+    #[clap(
+        long,
+        default_value = "boot-node.key",
+        help = "File holding the secp256k1 key of the node, created if missing"
+    )]
+    key_file: PathBuf,
+    #[clap(
+        long,
+        default_value = "/shared/enr.txt",
+        help = "File the ENR is written to"
+    )]
+    enr_file: PathBuf,
+    #[clap(
+        long = "boot-node",
+        value_name = "ENR",
+        help = "ENR of another boot node to federate with, can be repeated"
+    )]
+    boot_nodes: Vec<String>,
+    #[clap(
+        long,
+        default_value_t = 60,
+        help = "Seconds between the routing table reports"
+    )]
+    report_interval: u64,
+}
 
-fn write_boot_node(enr: &str) -> Result<(), io::Error> {
-    info!("Writing boot node to {} end {}", BOOT_NODE_PATH, enr);
-    let mut file = File::create(BOOT_NODE_PATH)?;
-    file.write_all(enr.as_bytes())?;
-    Ok(())
+// The key is kept across restarts so that the ENR given to the nodes stays valid
+fn load_or_create_key(path: &Path) -> io::Result<CombinedKey> {
+    match fs::read(path) {
+        Ok(mut bytes) => {
+            let key = CombinedKey::secp256k1_from_bytes(&mut bytes).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid key in {}: {e}", path.display()),
+                )
+            })?;
+            info!("Loaded node key from {}", path.display());
+            Ok(key)
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key = CombinedKey::generate_secp256k1();
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(path)?;
+            file.write_all(&key.encode())?;
+            info!("Generated node key in {}", path.display());
+            Ok(key)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn write_enr(path: &Path, enr: &str) -> Result<(), io::Error> {
+    info!("Writing ENR to {}", path.display());
+    fs::write(path, enr)
+}
+
+// Looks up a random node through the other boot nodes, which adds them and their peers to the
+// routing table
+fn federate(discv5: &Discv5) {
+    let lookup = discv5.find_node(NodeId::random());
+    tokio::spawn(async move {
+        match lookup.await {
+            Ok(found) => debug!("Federation lookup found {} nodes", found.len()),
+            Err(e) => warn!("Federation lookup failed: {e:?}"),
+        }
+    });
 }
 
 #[tokio::main]
@@ -23,83 +105,97 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_env_filter(filter_layer)
         .try_init();
 
-    // if there is an address specified use it
-    let address = std::env::args()
-        .nth(1)
-        .map(|addr| addr.parse::<Ipv4Addr>().unwrap());
-    info!("IP address: {address:?}");
-
-    // if there is a port specified use it
-    let port = {
-        if let Some(udp_port) = std::env::args().nth(2) {
-            udp_port.parse().unwrap()
-        } else {
-            9000
-        }
-    };
-    info!("UDP port: {port}");
+    let cli = Cli::parse();
+    info!(
+        "Listening on {}:{}, external address: {:?}",
+        cli.listen_address, cli.port, cli.external_address
+    );
 
     // listening address and port
     let listen_config = ListenConfig::Ipv4 {
-        ip: Ipv4Addr::UNSPECIFIED,
-        port: 9000,
+        ip: cli.listen_address,
+        port: cli.port,
     };
 
-    let enr_key = CombinedKey::generate_secp256k1();
+    let enr_key = load_or_create_key(&cli.key_file)?;
 
     // construct a local ENR
     let enr = {
         let mut builder = enr::Enr::builder();
-        // if an IP was specified, use it
-        if let Some(external_address) = address {
+        if let Some(external_address) = cli.external_address {
             builder.ip4(external_address);
         }
-        // if a port was specified, use it
-        if std::env::args().nth(2).is_some() {
-            builder.udp4(port);
-        }
-        builder.build(&enr_key).unwrap()
+        builder.udp4(cli.external_port.unwrap_or(cli.port));
+        builder.build(&enr_key)?
     };
 
-    // if the ENR is useful print it
     info!("Node Id: {}", enr.node_id());
-    if enr.udp4_socket().is_some() {
-        info!("Base64 ENR: {}", enr.to_base64());
-        info!(
-            "IP: {}, UDP_PORT:{}",
-            enr.ip4().unwrap(),
-            enr.udp4().unwrap()
-        );
-    } else {
-        info!("ENR is not printed as no IP:PORT was specified");
-    }
+    info!("Base64 ENR: {}", enr.to_base64());
 
     // default configuration
     let config = ConfigBuilder::new(listen_config).build();
 
     // construct the discv5 server
-    let mut discv5: Discv5 = Discv5::new(enr, enr_key, config).unwrap();
+    let mut discv5: Discv5 = Discv5::new(enr, enr_key, config)?;
 
-    if let Err(e) = write_boot_node(&discv5.local_enr().to_base64()) {
-        error!("Failed to write boot node to file: {}", e);
+    for boot_node in &cli.boot_nodes {
+        match Enr::from_str(boot_node) {
+            Ok(enr) => {
+                info!("Federating with boot node {}", enr.node_id());
+                if let Err(e) = discv5.add_enr(enr) {
+                    warn!("Failed to add boot node {boot_node}: {e}");
+                }
+            }
+            Err(e) => warn!("Failed to parse boot node ENR {boot_node}: {e}"),
+        }
+    }
+
+    if let Err(e) = write_enr(&cli.enr_file, &discv5.local_enr().to_base64()) {
+        error!("Failed to write ENR to file: {}", e);
     }
 
     // start the discv5 service
-    discv5.start().await.unwrap();
+    discv5
+        .start()
+        .await
+        .map_err(|e| format!("discv5 start: {e:?}"))?;
     info!("Server started");
 
     // get an event stream
-    let mut event_stream = discv5.event_stream().await.unwrap();
+    let mut event_stream = discv5
+        .event_stream()
+        .await
+        .map_err(|e| format!("discv5 event stream: {e:?}"))?;
+    let mut report = tokio::time::interval(Duration::from_secs(cli.report_interval.max(1)));
 
     loop {
-        match event_stream.recv().await {
-            Some(Event::SocketUpdated(addr)) => {
-                info!("Nodes ENR socket address has been updated to: {addr:?}");
-            }
-            Some(Event::Discovered(enr)) => {
-                info!("A peer has been discovered: {}", enr.node_id());
+        tokio::select! {
+            event = event_stream.recv() => match event {
+                Some(Event::SocketUpdated(addr)) => {
+                    info!("Nodes ENR socket address has been updated to: {addr:?}");
+                    if let Err(e) = write_enr(&cli.enr_file, &discv5.local_enr().to_base64()) {
+                        error!("Failed to write ENR to file: {}", e);
+                    }
+                }
+                Some(Event::Discovered(enr)) => {
+                    info!("A peer has been discovered: {}", enr.node_id());
+                }
+                Some(_) => {}
+                None => {
+                    error!("Discv5 event stream closed");
+                    return Ok(());
+                }
+            },
+            _ = report.tick() => {
+                info!(
+                    "Routing table: {} nodes, {} connected",
+                    discv5.table_entries_id().len(),
+                    discv5.connected_peers()
+                );
+                if !cli.boot_nodes.is_empty() {
+                    federate(&discv5);
+                }
             }
-            _ => {}
         }
     }
 }
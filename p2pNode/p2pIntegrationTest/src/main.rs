@@ -1,5 +1,7 @@
 use p2p_network::generate_secp256k1;
-use p2p_network::network::{P2PNetwork, P2PNetworkConfig};
+use p2p_network::gossip::GossipConfig;
+use p2p_network::network::{MessageAcceptance, P2PNetwork, P2PNetworkConfig};
+use p2p_network::topics::Topic;
 use rand::Rng;
 use std::fs::File;
 use std::io::Write;
@@ -61,15 +63,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let config = P2PNetworkConfig {
         local_key: generate_secp256k1(),
-        listen_addr: "/ip4/0.0.0.0/tcp/9000".parse().unwrap(),
         ipv4,
         udpv4: 9000,
         tcpv4: 9000,
+        ipv6: None,
+        udpv6: 9090,
+        tcpv6: 9090,
+        external_ipv4: None,
+        external_ipv6: None,
         boot_nodes,
+        signed_messages: false,
+        gossip: GossipConfig::default(),
+        chain_id: 167,
+        peer_db_path: None,
+        max_messages_per_sec: p2p_network::peer_manager::DEFAULT_MAX_MESSAGES_PER_SEC,
     };
     let (node_to_p2p_tx, node_to_p2p_rx) = mpsc::channel(10);
     let (node_tx, mut node_rx) = mpsc::channel(10);
-    let mut p2p = P2PNetwork::new(&config, node_tx.clone(), node_to_p2p_rx).await;
+    let (validation_tx, validation_rx) = mpsc::channel(10);
+    let (_peer_report_tx, peer_report_rx) = mpsc::channel(10);
+    // the test node doesn't store the messages, so it has nothing to sync
+    let (sync_channels, _sync_handle) = p2p_network::sync::channels(10);
+    let mut p2p = P2PNetwork::new(
+        &config,
+        node_tx.clone(),
+        node_to_p2p_rx,
+        validation_rx,
+        peer_report_rx,
+        sync_channels,
+    )
+    .await;
 
     // Save boot node if it is not specified in shared directory
     if config.boot_nodes.is_none() {
@@ -95,12 +118,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 info!("SEND Message: {}", send_count);
 
                 node_to_p2p_tx
-                    .send(data)
+                    .send((Topic::Preconfirmations, data))
                     .await
                     .unwrap();
             }
             Some(message) = node_rx.recv() => {
-                info!("Node received message: {} size {}", message[0], message.len());
+                info!("Node received message: {} size {}", message.data[0], message.data.len());
+                validation_tx
+                    .send(message.validation(MessageAcceptance::Accept))
+                    .await
+                    .unwrap();
             }
         }
     }
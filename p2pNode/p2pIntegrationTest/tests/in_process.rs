@@ -0,0 +1,276 @@
+//! Boot node and AVS p2p nodes run in the test process on loopback, unlike the docker compose
+//! setup, so that the gossip can be checked offline with `cargo test`.
+
+use discv5::{enr, enr::CombinedKey, ConfigBuilder, Discv5, ListenConfig};
+use p2p_network::generate_secp256k1;
+use p2p_network::gossip::GossipConfig;
+use p2p_network::network::{GossipMessage, MessageAcceptance, P2PNetwork, P2PNetworkConfig};
+use p2p_network::peer_manager::DEFAULT_MAX_MESSAGES_PER_SEC;
+use p2p_network::topics::Topic;
+use rand::Rng;
+use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+const CHAIN_ID: u64 = 167;
+// A preconfirmation with a large transactions list
+const PRECONFIRMATION_SIZE: usize = 128 * 1024;
+// The peer manager dials the discovered peers at its next 30 seconds heartbeat, a node can need a
+// few of them to find every other one
+const CONVERGENCE_TIMEOUT: Duration = Duration::from_secs(150);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// Loose bound for loopback, a preconfirmation has to reach the network within a 3 seconds slot
+const MAX_LATENCY: Duration = Duration::from_secs(1);
+
+fn init_tracing() {
+    let filter_layer = tracing_subscriber::EnvFilter::try_from_default_env()
+        .or_else(|_| tracing_subscriber::EnvFilter::try_new("info"))
+        .unwrap();
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter_layer)
+        .with_test_writer()
+        .try_init();
+}
+
+// The sockets are closed right away, the ports are reused by the node
+fn free_udp_port() -> u16 {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    socket.local_addr().unwrap().port()
+}
+
+fn free_tcp_port() -> u16 {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+async fn start_boot_node() -> Discv5 {
+    let port = free_udp_port();
+    let key = CombinedKey::generate_secp256k1();
+    let enr = enr::Enr::builder()
+        .ip4(Ipv4Addr::LOCALHOST)
+        .udp4(port)
+        .build(&key)
+        .unwrap();
+    let config = ConfigBuilder::new(ListenConfig::Ipv4 {
+        ip: Ipv4Addr::LOCALHOST,
+        port,
+    })
+    .build();
+    let mut discv5 = Discv5::new(enr, key, config).unwrap();
+    discv5.start().await.unwrap();
+    discv5
+}
+
+/// Gossip message received by a node of the network.
+struct Received {
+    node: usize,
+    data: Vec<u8>,
+    at: Instant,
+}
+
+struct TestNode {
+    to_p2p_tx: Sender<(Topic, Vec<u8>)>,
+    connected_peers: Arc<AtomicUsize>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Drop for TestNode {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn start_node(index: usize, boot_enr: String, received_tx: Sender<Received>) -> TestNode {
+    let config = P2PNetworkConfig {
+        local_key: generate_secp256k1(),
+        ipv4: Ipv4Addr::LOCALHOST,
+        udpv4: free_udp_port(),
+        tcpv4: free_tcp_port(),
+        ipv6: None,
+        udpv6: 0,
+        tcpv6: 0,
+        external_ipv4: None,
+        external_ipv6: None,
+        boot_nodes: Some(vec![boot_enr]),
+        signed_messages: true,
+        gossip: GossipConfig::default(),
+        chain_id: CHAIN_ID,
+        peer_db_path: None,
+        max_messages_per_sec: DEFAULT_MAX_MESSAGES_PER_SEC,
+    };
+    let (to_p2p_tx, to_p2p_rx) = mpsc::channel(10);
+    let (node_tx, node_rx) = mpsc::channel(10);
+    let (validation_tx, validation_rx) = mpsc::channel(10);
+    let (_peer_report_tx, peer_report_rx) = mpsc::channel(10);
+    let (sync_channels, _sync_handle) = p2p_network::sync::channels(10);
+    let mut p2p = P2PNetwork::new(
+        &config,
+        node_tx,
+        to_p2p_rx,
+        validation_rx,
+        peer_report_rx,
+        sync_channels,
+    )
+    .await;
+    let connected_peers = p2p.connected_peers();
+
+    let network = tokio::spawn(async move {
+        p2p.run(&config).await;
+    });
+    let node = tokio::spawn(accept_messages(index, node_rx, validation_tx, received_tx));
+    TestNode {
+        to_p2p_tx,
+        connected_peers,
+        tasks: vec![network, node],
+    }
+}
+
+// Stands for the node: every message is valid and forwarded to the other peers
+async fn accept_messages(
+    node: usize,
+    mut node_rx: Receiver<GossipMessage>,
+    validation_tx: Sender<p2p_network::network::ValidationResult>,
+    received_tx: Sender<Received>,
+) {
+    while let Some(message) = node_rx.recv().await {
+        let at = Instant::now();
+        let _ = validation_tx
+            .send(message.validation(MessageAcceptance::Accept))
+            .await;
+        let _ = received_tx
+            .send(Received {
+                node,
+                data: message.data,
+                at,
+            })
+            .await;
+    }
+}
+
+/// A boot node and `size` p2p nodes, each of them known to the boot node before the next one
+/// starts so that the later nodes discover all the earlier ones.
+struct TestNetwork {
+    boot_node: Discv5,
+    nodes: Vec<TestNode>,
+    received_rx: Receiver<Received>,
+}
+
+impl TestNetwork {
+    async fn start(size: usize) -> Self {
+        init_tracing();
+        let boot_node = start_boot_node().await;
+        let boot_enr = boot_node.local_enr().to_base64();
+        let (received_tx, received_rx) = mpsc::channel(100);
+        let mut nodes = Vec::with_capacity(size);
+        for index in 0..size {
+            nodes.push(start_node(index, boot_enr.clone(), received_tx.clone()).await);
+            wait_for(
+                "the boot node to know the node",
+                CONVERGENCE_TIMEOUT,
+                || boot_node.table_entries_id().len() > index,
+            )
+            .await;
+        }
+        TestNetwork {
+            boot_node,
+            nodes,
+            received_rx,
+        }
+    }
+
+    fn peer_counts(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .map(|node| node.connected_peers.load(Ordering::Relaxed))
+            .collect()
+    }
+
+    /// Waits until every node is connected to all the others.
+    async fn wait_for_full_mesh(&self) {
+        let expected = self.nodes.len() - 1;
+        wait_for("the nodes to connect", CONVERGENCE_TIMEOUT, || {
+            self.peer_counts().iter().all(|&count| count == expected)
+        })
+        .await;
+        info!(
+            "Nodes connected: {:?}, boot node table: {}",
+            self.peer_counts(),
+            self.boot_node.table_entries_id().len()
+        );
+        // the gossipsub meshes are built at the next heartbeat
+        tokio::time::sleep(GossipConfig::default().heartbeat_interval * 2).await;
+    }
+
+    async fn publish(&self, node: usize, data: Vec<u8>) -> Instant {
+        let sent_at = Instant::now();
+        self.nodes[node]
+            .to_p2p_tx
+            .send((Topic::Preconfirmations, data))
+            .await
+            .unwrap();
+        sent_at
+    }
+
+    /// Checks that every node but the publisher receives `data` within the latency bound.
+    async fn assert_delivered(&mut self, publisher: usize, data: &[u8], sent_at: Instant) {
+        let mut pending: Vec<usize> = (0..self.nodes.len()).filter(|&n| n != publisher).collect();
+        let deadline = tokio::time::Instant::now() + DELIVERY_TIMEOUT;
+        while !pending.is_empty() {
+            let received = tokio::time::timeout_at(deadline, self.received_rx.recv())
+                .await
+                .unwrap_or_else(|_| panic!("nodes {pending:?} didn't receive the message"))
+                .unwrap();
+            if received.data != data {
+                continue;
+            }
+            assert_ne!(received.node, publisher, "message echoed to its publisher");
+            let latency = received.at - sent_at;
+            assert!(
+                latency <= MAX_LATENCY,
+                "node {} received the message after {latency:?}",
+                received.node
+            );
+            pending.retain(|&n| n != received.node);
+        }
+    }
+}
+
+async fn wait_for(what: &str, timeout: Duration, condition: impl Fn() -> bool) {
+    let start = Instant::now();
+    while !condition() {
+        assert!(start.elapsed() < timeout, "timed out waiting for {what}");
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+fn preconfirmation() -> Vec<u8> {
+    let mut data = vec![0u8; PRECONFIRMATION_SIZE];
+    rand::thread_rng().fill(&mut data[..]);
+    data
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_peer_managers_converge_to_full_mesh() {
+    let network = TestNetwork::start(5).await;
+    network.wait_for_full_mesh().await;
+    assert!(network.boot_node.table_entries_id().len() >= 5);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_preconfirmations_reach_every_node() {
+    let mut network = TestNetwork::start(4).await;
+    network.wait_for_full_mesh().await;
+
+    for round in 0..8 {
+        let publisher = round % network.nodes.len();
+        let data = preconfirmation();
+        let sent_at = network.publish(publisher, data.clone()).await;
+        network.assert_delivered(publisher, &data, sent_at).await;
+    }
+}
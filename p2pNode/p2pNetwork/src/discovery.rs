@@ -1,12 +1,11 @@
 use crate::enr::{build_enr, EnrAsPeerId};
-use crate::network::P2PNetworkConfig;
+use crate::network::{tcp_multiaddr, P2PNetworkConfig};
 use discv5::enr::NodeId;
 use discv5::{enr::CombinedKey, ConfigBuilder, Discv5, Enr, Event, ListenConfig};
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use libp2p::futures::FutureExt;
 use libp2p::identity::Keypair;
-use libp2p::multiaddr::Protocol;
 use libp2p::swarm::behaviour::{DialFailure, FromSwarm};
 use libp2p::swarm::THandlerInEvent;
 use libp2p::swarm::{dummy::ConnectionHandler, ConnectionId};
@@ -14,6 +13,7 @@ use libp2p::swarm::{NetworkBehaviour, ToSwarm};
 use libp2p::{Multiaddr, PeerId};
 use std::collections::HashMap;
 use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
 use std::pin::Pin;
 use std::str::FromStr;
 use std::task::{Context, Poll};
@@ -28,6 +28,9 @@ pub struct Discovery {
     peers_future: FuturesUnordered<std::pin::Pin<Box<dyn Future<Output = DiscResult> + Send>>>,
     peers_to_discover: usize,
     started: bool,
+    tcpv4: u16,
+    tcpv6: u16,
+    ipv6: bool,
 }
 
 type DiscResult = Result<Vec<discv5::enr::Enr<CombinedKey>>, discv5::QueryError>;
@@ -45,25 +48,35 @@ impl Discovery {
         let local_enr = build_enr(config, &enr_key);
 
         info!("Node Id: {:?}", local_enr.node_id());
-        if local_enr.udp4_socket().is_some() {
-            info!("Base64 ENR: {}", local_enr.to_base64());
-            info!(
-                "IP: {}, UDP_PORT:{}",
-                local_enr.ip4().unwrap(),
-                local_enr.udp4().unwrap()
-            );
-        } else {
-            warn!("ENR is not printed as no IP:PORT was specified");
+        info!("Base64 ENR: {}", local_enr.to_base64());
+        match (local_enr.udp4_socket(), local_enr.udp6_socket()) {
+            (None, None) => {
+                info!("No external address yet, it is learnt from the peers")
+            }
+            (udp4, udp6) => info!("UDP sockets: {:?} {:?}", udp4, udp6),
         }
 
-        // listening address and port
-        let listen_config = ListenConfig::Ipv4 {
-            ip: local_enr.ip4().unwrap(),
-            port: 9000,
+        // listening addresses and ports
+        let listen_config = match config.ipv6 {
+            Some(ipv6) => ListenConfig::DualStack {
+                ipv4: config.ipv4,
+                ipv4_port: config.udpv4,
+                ipv6,
+                ipv6_port: config.udpv6,
+            },
+            None => ListenConfig::Ipv4 {
+                ip: config.ipv4,
+                port: config.udpv4,
+            },
         };
 
         // Setup default config
-        let discv5_config = ConfigBuilder::new(listen_config)
+        let mut discv5_config = ConfigBuilder::new(listen_config);
+        // external addresses set by hand are kept, not replaced by the ones seen by the peers
+        if config.external_ipv4.is_some() || config.external_ipv6.is_some() {
+            discv5_config.disable_enr_update();
+        }
+        let discv5_config = discv5_config
             .enable_packet_filter()
             .request_timeout(Duration::from_secs(30))
             .query_peer_timeout(Duration::from_secs(30))
@@ -107,6 +120,9 @@ impl Discovery {
             peers_future: FuturesUnordered::new(),
             started: false,
             peers_to_discover: 0,
+            tcpv4: config.tcpv4,
+            tcpv6: config.tcpv6,
+            ipv6: config.ipv6.is_some(),
         }
     }
 
@@ -115,7 +131,10 @@ impl Discovery {
     }
 
     fn find_peers(&mut self, count: usize) {
-        let predicate = Box::new(|enr: &Enr| enr.ip4().is_some());
+        let ipv6 = self.ipv6;
+        let predicate = Box::new(move |enr: &Enr| {
+            enr.tcp4_socket().is_some() || ipv6 && enr.tcp6_socket().is_some()
+        });
 
         let target = NodeId::random();
 
@@ -130,12 +149,12 @@ impl Discovery {
 
     fn get_peers(&mut self, cx: &mut Context) -> Option<DiscoveredPeers> {
         while let Poll::Ready(Some(res)) = self.peers_future.poll_next_unpin(cx) {
-            if res.is_ok() {
+            if let Ok(peers_enr) = res {
                 self.peers_future = FuturesUnordered::new();
 
                 let mut peers: HashMap<PeerId, Option<Multiaddr>> = HashMap::new();
 
-                for peer_enr in res.unwrap() {
+                for peer_enr in peers_enr {
                     match self.discv5.add_enr(peer_enr.clone()) {
                         Ok(_) => {
                             debug!("Added peer: {:?} to discv5", peer_enr.node_id());
@@ -146,12 +165,7 @@ impl Discovery {
                     };
                     let peer_id = peer_enr.clone().as_peer_id();
 
-                    let mut multiaddr: Option<Multiaddr> = None;
-                    if peer_enr.ip4().is_some() && peer_enr.tcp4().is_some() {
-                        let mut multiaddr_inner: Multiaddr = peer_enr.ip4().unwrap().into();
-                        multiaddr_inner.push(Protocol::Tcp(peer_enr.tcp4().unwrap()));
-                        multiaddr = Some(multiaddr_inner);
-                    }
+                    let multiaddr = self.peer_multiaddr(&peer_enr);
                     peers.insert(peer_id, multiaddr);
                 }
 
@@ -162,6 +176,34 @@ impl Discovery {
 
         None
     }
+
+    // IPv4 is preferred, IPv6 is only dialled when the node listens on it
+    fn peer_multiaddr(&self, enr: &Enr) -> Option<Multiaddr> {
+        if let Some(socket) = enr.tcp4_socket() {
+            return Some(tcp_multiaddr(IpAddr::V4(*socket.ip()), socket.port()));
+        }
+        enr.tcp6_socket()
+            .filter(|_| self.ipv6)
+            .map(|socket| tcp_multiaddr(IpAddr::V6(*socket.ip()), socket.port()))
+    }
+
+    // Discv5 has learnt the UDP address the peers see, the gossip port is reachable at the same IP
+    fn on_socket_updated(&mut self, socket: SocketAddr) -> Multiaddr {
+        let tcp_port = match socket.ip() {
+            IpAddr::V4(_) => self.tcpv4,
+            IpAddr::V6(_) => self.tcpv6,
+        };
+        let tcp_socket = SocketAddr::new(socket.ip(), tcp_port);
+        if !self.discv5.update_local_enr_socket(tcp_socket, true) {
+            debug!("ENR TCP socket already {tcp_socket}");
+        }
+        info!(
+            "External address updated to {}, ENR: {}",
+            socket.ip(),
+            self.discv5.local_enr().to_base64()
+        );
+        tcp_multiaddr(socket.ip(), tcp_port)
+    }
 }
 
 enum EventStream {
@@ -239,8 +281,15 @@ impl NetworkBehaviour for Discovery {
             EventStream::InActive => {}
             EventStream::Present(ref mut stream) => {
                 while let Poll::Ready(Some(event)) = stream.poll_recv(cx) {
-                    if let Event::SessionEstablished(_enr, _) = event {
-                        debug!("Session Established: {:?}", _enr);
+                    match event {
+                        Event::SessionEstablished(enr, _) => {
+                            debug!("Session Established: {:?}", enr);
+                        }
+                        Event::SocketUpdated(socket) => {
+                            let addr = self.on_socket_updated(socket);
+                            return Poll::Ready(ToSwarm::ExternalAddrConfirmed(addr));
+                        }
+                        _ => {}
                     }
                 }
             }
@@ -8,9 +8,19 @@ use libp2p::PeerId;
 
 pub fn build_enr(config: &P2PNetworkConfig, combined_key: &CombinedKey) -> Enr {
     let mut enr_builder = enr::Enr::builder();
-    enr_builder.ip4(config.ipv4);
+    // an unspecified address is filled in by discv5 once the peers report how they see the node
+    if let Some(ipv4) = config.advertised_ipv4() {
+        enr_builder.ip4(ipv4);
+    }
     enr_builder.udp4(config.udpv4);
     enr_builder.tcp4(config.tcpv4);
+    if config.ipv6.is_some() {
+        if let Some(ipv6) = config.advertised_ipv6() {
+            enr_builder.ip6(ipv6);
+        }
+        enr_builder.udp6(config.udpv6);
+        enr_builder.tcp6(config.tcpv6);
+    }
     enr_builder.build(combined_key).unwrap()
 }
 
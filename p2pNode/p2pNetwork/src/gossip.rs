@@ -0,0 +1,114 @@
+use libp2p::gossipsub::{
+    Message, MessageId, PeerScoreParams, PeerScoreThresholds, TopicHash, TopicScoreParams,
+};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::time::Duration;
+
+/// Gossipsub tuning. The defaults suit 3 seconds L2 slots: a preconfirmation has to reach the
+/// whole network well within a slot, and a peer sending invalid preconfirmations is graylisted
+/// after a couple of them.
+#[derive(Debug, Clone)]
+pub struct GossipConfig {
+    pub heartbeat_interval: Duration,
+    /// Weight of each AVS topic in the peer score.
+    pub topic_weight: f64,
+    /// Penalty for each message rejected by the node, squared with the number of them.
+    pub invalid_message_weight: f64,
+    /// Fraction of the invalid messages count kept at every score decay, each second.
+    pub invalid_message_decay: f64,
+    /// Penalty for the peers sharing an IP above the threshold, squared with the surplus.
+    pub ip_colocation_factor_weight: f64,
+    pub ip_colocation_factor_threshold: f64,
+    pub gossip_threshold: f64,
+    pub publish_threshold: f64,
+    pub graylist_threshold: f64,
+}
+
+impl Default for GossipConfig {
+    fn default() -> Self {
+        GossipConfig {
+            heartbeat_interval: Duration::from_millis(700),
+            topic_weight: 1.0,
+            invalid_message_weight: -100.0,
+            invalid_message_decay: 0.99,
+            ip_colocation_factor_weight: -10.0,
+            ip_colocation_factor_threshold: 8.0,
+            gossip_threshold: -100.0,
+            publish_threshold: -200.0,
+            graylist_threshold: -400.0,
+        }
+    }
+}
+
+impl fmt::Display for GossipConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "heartbeat: {:?}, topic weight: {}, invalid message weight: {} (decay {}), ip colocation weight: {} (threshold {}), thresholds: gossip {} publish {} graylist {}",
+            self.heartbeat_interval,
+            self.topic_weight,
+            self.invalid_message_weight,
+            self.invalid_message_decay,
+            self.ip_colocation_factor_weight,
+            self.ip_colocation_factor_threshold,
+            self.gossip_threshold,
+            self.publish_threshold,
+            self.graylist_threshold
+        )
+    }
+}
+
+impl GossipConfig {
+    /// Checks the values the same way gossipsub does when it is built.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.heartbeat_interval.is_zero() {
+            return Err("heartbeat interval must be positive".to_string());
+        }
+        self.peer_score_params(&[TopicHash::from_raw("validate")])
+            .validate()?;
+        self.peer_score_thresholds().validate()?;
+        Ok(())
+    }
+
+    pub(crate) fn peer_score_params(&self, topics: &[TopicHash]) -> PeerScoreParams {
+        let mut params = PeerScoreParams {
+            ip_colocation_factor_weight: self.ip_colocation_factor_weight,
+            ip_colocation_factor_threshold: self.ip_colocation_factor_threshold,
+            ..Default::default()
+        };
+        // Only the invalid messages are scored in the topic: the preconfirmations are produced
+        // by a single peer per slot, so the delivery counters say nothing about the others
+        for topic in topics {
+            params.topics.insert(
+                topic.clone(),
+                TopicScoreParams {
+                    topic_weight: self.topic_weight,
+                    time_in_mesh_weight: 0.0,
+                    first_message_deliveries_weight: 0.0,
+                    mesh_message_deliveries_weight: 0.0,
+                    mesh_failure_penalty_weight: 0.0,
+                    invalid_message_deliveries_weight: self.invalid_message_weight,
+                    invalid_message_deliveries_decay: self.invalid_message_decay,
+                    ..Default::default()
+                },
+            );
+        }
+        params
+    }
+
+    pub(crate) fn peer_score_thresholds(&self) -> PeerScoreThresholds {
+        PeerScoreThresholds {
+            gossip_threshold: self.gossip_threshold,
+            publish_threshold: self.publish_threshold,
+            graylist_threshold: self.graylist_threshold,
+            ..Default::default()
+        }
+    }
+}
+
+/// Content addressed message id, so that the same preconfirmation has the same id on every peer
+/// whichever build or peer it comes from.
+pub fn message_id(message: &Message) -> MessageId {
+    MessageId::new(&Sha256::digest(&message.data))
+}
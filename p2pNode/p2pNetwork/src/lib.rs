@@ -1,8 +1,41 @@
 pub mod discovery;
 pub mod enr;
+pub mod gossip;
 pub mod network;
+pub mod peer_db;
 pub mod peer_manager;
+pub mod sync;
+pub mod topics;
 
-pub fn generate_secp256k1() -> libp2p::identity::Keypair {
-    libp2p::identity::Keypair::generate_secp256k1()
+use libp2p::{
+    identity::{secp256k1, DecodingError, Keypair, PublicKey},
+    multihash::Multihash,
+    PeerId,
+};
+
+// Multihash code of the peer ids embedding the public key instead of hashing it
+const IDENTITY_MULTIHASH_CODE: u64 = 0x00;
+
+pub fn generate_secp256k1() -> Keypair {
+    Keypair::generate_secp256k1()
+}
+
+/// Builds the libp2p identity from a raw secp256k1 private key, so that the peer id is bound to
+/// the Ethereum address of the same key. The key bytes are zeroized.
+pub fn secp256k1_from_bytes(secret: &mut [u8]) -> Result<Keypair, DecodingError> {
+    let secret = secp256k1::SecretKey::try_from_bytes(secret)?;
+    Ok(secp256k1::Keypair::from(secret).into())
+}
+
+/// Uncompressed secp256k1 public key embedded in `peer_id`, if any.
+pub fn secp256k1_public_key(peer_id: &PeerId) -> Option<[u8; 65]> {
+    let multihash: &Multihash<64> = peer_id.as_ref();
+    if multihash.code() != IDENTITY_MULTIHASH_CODE {
+        return None;
+    }
+    PublicKey::try_decode_protobuf(multihash.digest())
+        .ok()?
+        .try_into_secp256k1()
+        .ok()
+        .map(|public_key| public_key.to_bytes_uncompressed())
 }
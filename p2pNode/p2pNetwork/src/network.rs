@@ -1,61 +1,205 @@
 use crate::discovery::Discovery;
+use crate::gossip::{self, GossipConfig};
+use crate::peer_db::PeerDb;
 use crate::peer_manager::PeerManager;
+use crate::sync::{
+    self, InboundSyncRequest, SyncChannels, SyncRequest, SyncResponse, SyncResult,
+    MAX_BLOCKS_PER_REQUEST,
+};
+use crate::topics::Topic;
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
 use libp2p::futures::StreamExt;
-use libp2p::gossipsub::{MessageAuthenticity, ValidationMode};
+use libp2p::gossipsub::{MessageAuthenticity, MessageId, TopicHash, ValidationMode};
+use libp2p::multiaddr::Protocol;
+use libp2p::request_response::{self, OutboundRequestId, ResponseChannel};
 use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
-use libp2p::{gossipsub, identify, identity, noise, PeerId};
+use libp2p::{gossipsub, identify, identity, noise};
 use libp2p::{Multiaddr, SwarmBuilder};
 use libp2p_mplex::{MaxBufferBehaviour, MplexConfig};
-use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fmt;
-use std::hash::{Hash, Hasher};
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc::{Receiver, Sender};
+use tokio::sync::oneshot;
 use tracing::{debug, info, warn};
 
+pub use crate::peer_manager::PeerOffence;
+pub use libp2p::{gossipsub::MessageAcceptance, PeerId};
+
 pub struct P2PNetworkConfig {
     pub local_key: identity::Keypair,
-    pub listen_addr: Multiaddr,
+    /// IPv4 address to listen on, advertised in the ENR unless it is unspecified.
     pub ipv4: Ipv4Addr,
+    /// Discv5 port.
     pub udpv4: u16,
+    /// Gossip port.
     pub tcpv4: u16,
+    /// IPv6 address to also listen on, with its own ports.
+    pub ipv6: Option<Ipv6Addr>,
+    pub udpv6: u16,
+    pub tcpv6: u16,
+    /// Addresses reachable by the peers, when behind a NAT. Without them the external addresses
+    /// are learnt from the peers and the ENR is updated accordingly.
+    pub external_ipv4: Option<Ipv4Addr>,
+    pub external_ipv6: Option<Ipv6Addr>,
     pub boot_nodes: Option<Vec<String>>,
+    /// Sign the published messages with `local_key` and drop the received ones which are unsigned
+    /// or badly signed, instead of publishing anonymous messages.
+    pub signed_messages: bool,
+    pub gossip: GossipConfig,
+    /// L2 chain id the topics are scoped to.
+    pub chain_id: u64,
+    /// File the known peers are saved to and reloaded from on restart.
+    pub peer_db_path: Option<PathBuf>,
+    /// Gossip messages and sync requests accepted from each peer per second, the ones above
+    /// are dropped and count against the peer.
+    pub max_messages_per_sec: u32,
+}
+/// A gossip message held back by gossipsub until the application reports whether it is valid.
+pub struct GossipMessage {
+    pub id: MessageId,
+    pub source: PeerId,
+    pub topic: Topic,
+    /// Peer which signed the message, only set in signed mode. Unlike `source` it stays the same
+    /// while the message is forwarded.
+    pub author: Option<PeerId>,
+    pub data: Vec<u8>,
+}
+
+impl GossipMessage {
+    pub fn validation(&self, acceptance: MessageAcceptance) -> ValidationResult {
+        ValidationResult {
+            id: self.id.clone(),
+            source: self.source,
+            acceptance,
+        }
+    }
+}
+
+/// Valid messages are forwarded to the other peers, rejected ones lower the score of the sender
+/// and are reported as an [`PeerOffence::InvalidMessage`] of it.
+pub struct ValidationResult {
+    id: MessageId,
+    source: PeerId,
+    acceptance: MessageAcceptance,
 }
+
+/// Misbehaviour of a peer seen by the node outside of gossip validation, e.g. an invalid sync
+/// response. Peers reported too often are banned.
+pub struct PeerReport {
+    pub peer: PeerId,
+    pub offence: PeerOffence,
+}
+
 #[derive(NetworkBehaviour)]
 struct SwarmBehaviour {
     gossipsub: gossipsub::Behaviour,
     discovery: Discovery,
     identify: identify::Behaviour,
     peer_manager: PeerManager,
+    sync: sync::Behaviour,
 }
 
+type PendingResponse = BoxFuture<
+    'static,
+    (
+        ResponseChannel<SyncResponse>,
+        Result<SyncResponse, oneshot::error::RecvError>,
+    ),
+>;
+
 pub struct P2PNetwork {
-    node_tx: Sender<Vec<u8>>,
-    node_to_p2p_rx: Receiver<Vec<u8>>,
+    node_tx: Sender<GossipMessage>,
+    node_to_p2p_rx: Receiver<(Topic, Vec<u8>)>,
+    validation_rx: Receiver<ValidationResult>,
+    peer_report_rx: Receiver<PeerReport>,
     swarm: libp2p::Swarm<SwarmBehaviour>,
-    topic_name: String,
+    chain_id: u64,
+    topics: HashMap<TopicHash, Topic>,
+    connected_peers: Arc<AtomicUsize>,
+    sync_channels: SyncChannels,
+    // requests of the node waiting for a peer response
+    sync_requests: HashMap<OutboundRequestId, SyncRequest>,
+    // requests of the peers waiting for the node response
+    sync_responses: FuturesUnordered<PendingResponse>,
+    next_sync_peer: usize,
 }
 
 impl fmt::Display for P2PNetworkConfig {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "P2PNetworkConfig {{\n  listen_addr: {},\n  ipv4: {},\n  udpv4: {},\n  tcpv4: {},\n  boot_nodes: {:?}\n}}",
-            self.listen_addr,
+            "P2PNetworkConfig {{\n  ipv4: {},\n  udpv4: {},\n  tcpv4: {},\n  ipv6: {:?},\n  udpv6: {},\n  tcpv6: {},\n  external_ipv4: {:?},\n  external_ipv6: {:?},\n  boot_nodes: {:?},\n  signed_messages: {},\n  gossip: {},\n  chain_id: {},\n  peer_db_path: {:?},\n  max_messages_per_sec: {}\n}}",
             self.ipv4,
             self.udpv4,
             self.tcpv4,
-            self.boot_nodes
+            self.ipv6,
+            self.udpv6,
+            self.tcpv6,
+            self.external_ipv4,
+            self.external_ipv6,
+            self.boot_nodes,
+            self.signed_messages,
+            self.gossip,
+            self.chain_id,
+            self.peer_db_path,
+            self.max_messages_per_sec
         )
     }
 }
 
+impl P2PNetworkConfig {
+    /// IPv4 address put in the ENR, none until discv5 learns it from the peers.
+    pub fn advertised_ipv4(&self) -> Option<Ipv4Addr> {
+        self.external_ipv4
+            .or((!self.ipv4.is_unspecified()).then_some(self.ipv4))
+    }
+
+    /// IPv6 address put in the ENR, none until discv5 learns it from the peers.
+    pub fn advertised_ipv6(&self) -> Option<Ipv6Addr> {
+        let ipv6 = self.ipv6?;
+        self.external_ipv6
+            .or((!ipv6.is_unspecified()).then_some(ipv6))
+    }
+
+    fn listen_addrs(&self) -> Vec<Multiaddr> {
+        let mut addrs = vec![tcp_multiaddr(self.ipv4.into(), self.tcpv4)];
+        if let Some(ipv6) = self.ipv6 {
+            addrs.push(tcp_multiaddr(ipv6.into(), self.tcpv6));
+        }
+        addrs
+    }
+
+    fn external_addrs(&self) -> Vec<Multiaddr> {
+        let mut addrs = Vec::new();
+        if let Some(ipv4) = self.external_ipv4 {
+            addrs.push(tcp_multiaddr(ipv4.into(), self.tcpv4));
+        }
+        if let Some(ipv6) = self.external_ipv6 {
+            addrs.push(tcp_multiaddr(ipv6.into(), self.tcpv6));
+        }
+        addrs
+    }
+}
+
+/// e.g. `/ip4/1.2.3.4/tcp/9000`
+pub fn tcp_multiaddr(ip: IpAddr, port: u16) -> Multiaddr {
+    Multiaddr::from(ip).with(Protocol::Tcp(port))
+}
+
 impl P2PNetwork {
     pub async fn new(
         config: &P2PNetworkConfig,
-        node_tx: Sender<Vec<u8>>,
-        node_to_p2p_rx: Receiver<Vec<u8>>,
+        node_tx: Sender<GossipMessage>,
+        node_to_p2p_rx: Receiver<(Topic, Vec<u8>)>,
+        validation_rx: Receiver<ValidationResult>,
+        peer_report_rx: Receiver<PeerReport>,
+        sync_channels: SyncChannels,
     ) -> Self {
         // Create a random PeerId
         let local_peer_id = PeerId::from(config.local_key.public());
@@ -65,37 +209,52 @@ impl P2PNetwork {
         let discovery = Discovery::new(config, &config.local_key).await;
 
         let target_num_peers = 16;
-        let peer_manager = PeerManager::new(target_num_peers);
-        let message_id_fn = |message: &gossipsub::Message| {
-            let mut s = DefaultHasher::new();
-            message.data.hash(&mut s);
-            gossipsub::MessageId::from(s.finish().to_string())
+        let peer_db = config.peer_db_path.clone().map(PeerDb::new);
+        let peer_manager = PeerManager::new(target_num_peers, config.max_messages_per_sec, peer_db);
+        let (authenticity, validation_mode) = if config.signed_messages {
+            (
+                MessageAuthenticity::Signed(config.local_key.clone()),
+                ValidationMode::Strict,
+            )
+        } else {
+            (MessageAuthenticity::Anonymous, ValidationMode::Anonymous)
         };
 
         // Set a custom gossipsub configuration
         let gossipsub_config = gossipsub::ConfigBuilder::default()
             .max_transmit_size(10 * 1_048_576)
             .fanout_ttl(Duration::from_secs(60))
-            .heartbeat_interval(Duration::from_millis(10_000))
-            .validation_mode(ValidationMode::Anonymous)
-            .fanout_ttl(Duration::from_secs(60))
+            .heartbeat_interval(config.gossip.heartbeat_interval)
+            .validation_mode(validation_mode)
             .history_length(12)
             .max_messages_per_rpc(Some(500))
-            .message_id_fn(message_id_fn)
+            .message_id_fn(gossip::message_id)
+            .validate_messages()
             .build()
             .expect("Valid config");
 
         // build a gossipsub network behaviour
-        let mut gossipsub =
-            gossipsub::Behaviour::new(MessageAuthenticity::Anonymous, gossipsub_config)
-                .expect("Correct configuration");
+        let mut gossipsub = gossipsub::Behaviour::new(authenticity, gossipsub_config)
+            .expect("Correct configuration");
 
-        // Create a Gossipsub topic
-        let topic_name = "taiko-avs".to_string();
-        let topic = gossipsub::IdentTopic::new(topic_name.clone());
+        // Create the Gossipsub topics
+        let topics: HashMap<TopicHash, Topic> = Topic::ALL
+            .into_iter()
+            .map(|topic| (topic.ident(config.chain_id).hash(), topic))
+            .collect();
+        let topic_hashes: Vec<TopicHash> = topics.keys().cloned().collect();
 
-        // subscribes to our topic
-        gossipsub.subscribe(&topic).unwrap();
+        gossipsub
+            .with_peer_score(
+                config.gossip.peer_score_params(&topic_hashes),
+                config.gossip.peer_score_thresholds(),
+            )
+            .expect("Valid peer score params");
+
+        // subscribes to our topics
+        for topic in Topic::ALL {
+            gossipsub.subscribe(&topic.ident(config.chain_id)).unwrap();
+        }
 
         // Set a custom identify configuration
         let identify = identify::Behaviour::new(
@@ -110,6 +269,7 @@ impl P2PNetwork {
                 discovery,
                 identify,
                 peer_manager,
+                sync: sync::behaviour(config.chain_id),
             }
         };
 
@@ -133,8 +293,16 @@ impl P2PNetwork {
         P2PNetwork {
             node_tx,
             node_to_p2p_rx,
+            validation_rx,
+            peer_report_rx,
             swarm,
-            topic_name,
+            chain_id: config.chain_id,
+            topics,
+            connected_peers: Arc::new(AtomicUsize::new(0)),
+            sync_channels,
+            sync_requests: HashMap::new(),
+            sync_responses: FuturesUnordered::new(),
+            next_sync_peer: 0,
         }
     }
 
@@ -142,15 +310,26 @@ impl P2PNetwork {
         self.swarm.behaviour().discovery.get_local_enr()
     }
 
+    /// Number of gossipsub peers, refreshed every second while the network runs.
+    pub fn connected_peers(&self) -> Arc<AtomicUsize> {
+        self.connected_peers.clone()
+    }
+
     pub async fn run(&mut self, config: &P2PNetworkConfig) {
         info!("Starting P2P network");
-        self.swarm.listen_on(config.listen_addr.clone()).unwrap();
+        for addr in config.listen_addrs() {
+            self.swarm.listen_on(addr).unwrap();
+        }
+        for addr in config.external_addrs() {
+            info!("External address: {addr}");
+            self.swarm.add_external_address(addr);
+        }
         //loop
         loop {
             tokio::select! {
-                Some(message) = self.node_to_p2p_rx.recv() => {
-                    debug!("Sent message to p2p with size: {}", message.len());
-                    let topic = gossipsub::IdentTopic::new(self.topic_name.clone());
+                Some((topic, message)) = self.node_to_p2p_rx.recv() => {
+                    debug!("Sent message to p2p topic {} with size: {}", topic.name(), message.len());
+                    let topic = topic.ident(self.chain_id);
                     //encode message
                     if let Err(e) = self.swarm
                         .behaviour_mut().gossipsub
@@ -158,6 +337,31 @@ impl P2PNetwork {
                         warn!("Publish error: {e:?}");
                     }
                 }
+                Some(result) = self.validation_rx.recv() => {
+                    debug!("Message {} from {} validated: {:?}", result.id, result.source, result.acceptance);
+                    let rejected = matches!(result.acceptance, MessageAcceptance::Reject);
+                    if let Err(e) = self.swarm
+                        .behaviour_mut().gossipsub
+                        .report_message_validation_result(&result.id, &result.source, result.acceptance) {
+                        warn!("Failed to report message validation: {e:?}");
+                    }
+                    if rejected {
+                        self.swarm.behaviour_mut().peer_manager.report_peer(result.source, PeerOffence::InvalidMessage);
+                    }
+                }
+                Some(report) = self.peer_report_rx.recv() => {
+                    self.swarm.behaviour_mut().peer_manager.report_peer(report.peer, report.offence);
+                }
+                Some(request) = self.sync_channels.requests_rx.recv() => {
+                    self.send_sync_request(request).await;
+                }
+                Some((channel, response)) = self.sync_responses.next() => {
+                    // an empty response if the node didn't answer
+                    let response = response.unwrap_or_default();
+                    if self.swarm.behaviour_mut().sync.send_response(channel, response).is_err() {
+                        debug!("Sync response not sent, the peer is gone");
+                    }
+                }
                 event = self.swarm.select_next_some() => match event {
                     SwarmEvent::Behaviour(behaviour_event) => match behaviour_event {
                         SwarmBehaviourEvent::Gossipsub(gs) =>
@@ -166,13 +370,28 @@ impl P2PNetwork {
                             message_id: id,
                             message, } = gs {
                                 debug!("Got message: with id: {id} from peer: {peer_id}");
-                                // decode message
-                                if let Err(e) = self.node_tx
-                                    .send(message.data)
-                                    .await {
-                                        warn!("Can't send message to node from network: {e:?}");
-                                    }
+                                // only the subscribed topics are delivered
+                                let Some(&topic) = self.topics.get(&message.topic) else {
+                                    warn!("Message {id} on unknown topic {}", message.topic);
+                                    continue;
+                                };
+                                // dropped without forwarding, the peer is penalized by the peer manager
+                                if !self.swarm.behaviour_mut().peer_manager.allow_message(peer_id) {
+                                    let _ = self.swarm.behaviour_mut().gossipsub.report_message_validation_result(&id, &peer_id, MessageAcceptance::Ignore);
+                                    continue;
+                                }
+                                let message = GossipMessage {
+                                    id,
+                                    source: peer_id,
+                                    topic,
+                                    author: message.source,
+                                    data: message.data,
+                                };
+                                if let Err(e) = self.node_tx.send(message).await {
+                                    warn!("Can't send message to node from network: {e:?}");
+                                }
                         },
+                        SwarmBehaviourEvent::Sync(ev) => self.handle_sync_event(ev).await,
                         SwarmBehaviourEvent::Discovery(discovered) => {
                             debug!("Discovery Event: {:#?}", &discovered);
                             self.swarm.behaviour_mut().peer_manager.add_peers(discovered.peers);
@@ -214,9 +433,108 @@ impl P2PNetwork {
                     _ => debug!("Swarm: {event:?}"),
                 },
                 _ = tokio::time::sleep(Duration::from_secs(1)) => {
-                    debug!("Connected peers: {}", self.swarm.behaviour_mut().gossipsub.all_peers().collect::<Vec<_>>().len());
+                    let connected_peers = self.swarm.behaviour_mut().gossipsub.all_peers().count();
+                    self.connected_peers.store(connected_peers, Ordering::Relaxed);
+                    debug!("Connected peers: {}", connected_peers);
+                }
+            }
+        }
+    }
+
+    // Each request goes to the next connected peer in turn
+    async fn send_sync_request(&mut self, request: SyncRequest) {
+        let peers: Vec<PeerId> = self.swarm.connected_peers().copied().collect();
+        if peers.is_empty() {
+            let result = SyncResult {
+                peer: None,
+                request,
+                response: Err("no connected peers".to_string()),
+            };
+            if let Err(e) = self.sync_channels.results_tx.send(result).await {
+                warn!("Can't send sync result to node: {e:?}");
+            }
+            return;
+        }
+        let peer = peers[self.next_sync_peer % peers.len()];
+        self.next_sync_peer = self.next_sync_peer.wrapping_add(1);
+        debug!(
+            "Requesting blocks {}..={} from {peer}",
+            request.from_block, request.to_block
+        );
+        let request_id = self.swarm.behaviour_mut().sync.send_request(&peer, request);
+        self.sync_requests.insert(request_id, request);
+    }
+
+    async fn handle_sync_event(
+        &mut self,
+        event: request_response::Event<SyncRequest, SyncResponse>,
+    ) {
+        let result = match event {
+            request_response::Event::Message { peer, message } => match message {
+                request_response::Message::Request {
+                    request, channel, ..
+                } => {
+                    self.handle_inbound_sync_request(peer, request, channel);
+                    return;
                 }
+                request_response::Message::Response {
+                    request_id,
+                    response,
+                } => self
+                    .sync_requests
+                    .remove(&request_id)
+                    .map(|request| SyncResult {
+                        peer: Some(peer),
+                        request,
+                        response: Ok(response),
+                    }),
+            },
+            request_response::Event::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => self
+                .sync_requests
+                .remove(&request_id)
+                .map(|request| SyncResult {
+                    peer: Some(peer),
+                    request,
+                    response: Err(error.to_string()),
+                }),
+            ev => {
+                debug!("Sync: {ev:?}");
+                None
+            }
+        };
+        if let Some(result) = result {
+            if let Err(e) = self.sync_channels.results_tx.send(result).await {
+                warn!("Can't send sync result to node: {e:?}");
             }
         }
     }
+
+    fn handle_inbound_sync_request(
+        &mut self,
+        peer: PeerId,
+        request: SyncRequest,
+        channel: ResponseChannel<SyncResponse>,
+    ) {
+        let (response_tx, response_rx) = oneshot::channel();
+        let valid = request.from_block <= request.to_block
+            && request.to_block - request.from_block < MAX_BLOCKS_PER_REQUEST
+            && self.swarm.behaviour_mut().peer_manager.allow_message(peer);
+        let inbound = InboundSyncRequest {
+            peer,
+            request,
+            response: response_tx,
+        };
+        if !valid {
+            debug!("Invalid sync request {request:?} from {peer}");
+        } else if let Err(e) = self.sync_channels.inbound_tx.try_send(inbound) {
+            warn!("Can't send sync request to node: {e:?}");
+        }
+        // a dropped response sender answers with an empty response
+        self.sync_responses
+            .push(Box::pin(async move { (channel, response_rx.await) }));
+    }
 }
@@ -0,0 +1,453 @@
+use crate::peer_manager::{ConnectionData, ConnectionStatus, PeerData};
+use libp2p::identify::Info;
+use libp2p::identity::PublicKey;
+use libp2p::{Multiaddr, PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+use tracing::{debug, info, warn};
+
+// Only the latest connections of a peer are kept, the average duration sums up the older ones
+const MAX_SAVED_CONNECTIONS: usize = 16;
+
+/// Peer table saved on disk, so that a restarted node redials its known peers right away
+/// instead of waiting for discovery. The timestamps are saved as system time since the
+/// `Instant`s of the previous run are meaningless after a restart.
+#[derive(Clone)]
+pub struct PeerDb {
+    path: PathBuf,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct SavedPeers {
+    peers: HashMap<String, SavedPeer>,
+    #[serde(default)]
+    bans: HashMap<String, SavedBan>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum SavedBan {
+    Until(#[serde(with = "serde_millis")] SystemTime),
+    Permanent,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedPeer {
+    multiaddr: Option<Multiaddr>,
+    average_connection_duration: Option<usize>,
+    connection_history: Vec<SavedConnection>,
+    identity: Option<SavedIdentity>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedConnection {
+    #[serde(with = "serde_millis")]
+    established: Option<SystemTime>,
+    #[serde(with = "serde_millis")]
+    failure: Option<SystemTime>,
+    #[serde(with = "serde_millis")]
+    disconnect: Option<SystemTime>,
+    #[serde(with = "serde_millis")]
+    dial: SystemTime,
+    status: ConnectionStatus,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedIdentity {
+    public_key: Vec<u8>,
+    protocol_version: String,
+    agent_version: String,
+    listen_addrs: Vec<Multiaddr>,
+    protocols: Vec<String>,
+    observed_addr: Multiaddr,
+}
+
+/// Peers read from the database.
+#[derive(Default)]
+pub struct LoadedPeers {
+    pub peer_data: HashMap<PeerId, PeerData>,
+    pub peer_identities: HashMap<PeerId, Info>,
+    /// Bans still running, with their end or none for a permanent one.
+    pub banned_peers: HashMap<PeerId, Option<Instant>>,
+}
+
+impl PeerDb {
+    pub fn new(path: PathBuf) -> Self {
+        PeerDb { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the saved peers, none if the database doesn't exist yet. The peers which were
+    /// connected when the node stopped are loaded as disconnected at the time of the save.
+    pub fn load(&self) -> io::Result<LoadedPeers> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                info!("No peer database at {}", self.path.display());
+                return Ok(LoadedPeers::default());
+            }
+            Err(e) => return Err(e),
+        };
+        let saved: SavedPeers = serde_json::from_slice(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let saved_at = fs::metadata(&self.path)?
+            .modified()
+            .unwrap_or_else(|_| SystemTime::now());
+
+        let mut loaded = LoadedPeers::default();
+        for (peer_id, peer) in saved.peers {
+            let Ok(peer_id) = peer_id.parse::<PeerId>() else {
+                warn!("Skipping saved peer with invalid id {}", peer_id);
+                continue;
+            };
+            if let Some(identity) = peer.identity {
+                match identity.into_info() {
+                    Some(info) => {
+                        loaded.peer_identities.insert(peer_id, info);
+                    }
+                    None => warn!("Skipping invalid saved identity of peer {}", peer_id),
+                }
+            }
+            let connection_history = peer
+                .connection_history
+                .into_iter()
+                .map(|connection| connection.into_connection_data(saved_at))
+                .collect();
+            loaded.peer_data.insert(
+                peer_id,
+                PeerData {
+                    connection_history,
+                    average_connection_duration: peer.average_connection_duration,
+                    multiaddr: peer.multiaddr,
+                },
+            );
+        }
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        for (peer_id, ban) in saved.bans {
+            let Ok(peer_id) = peer_id.parse::<PeerId>() else {
+                warn!("Skipping ban of invalid peer id {}", peer_id);
+                continue;
+            };
+            match ban {
+                SavedBan::Until(until) => {
+                    if let Ok(remaining) = until.duration_since(system_now) {
+                        loaded.banned_peers.insert(peer_id, Some(now + remaining));
+                    }
+                }
+                SavedBan::Permanent => {
+                    loaded.banned_peers.insert(peer_id, None);
+                }
+            }
+        }
+        info!(
+            "Loaded {} peers and {} bans from {}",
+            loaded.peer_data.len(),
+            loaded.banned_peers.len(),
+            self.path.display()
+        );
+        Ok(loaded)
+    }
+
+    /// Replaces the database with the given peers.
+    pub fn save(
+        &self,
+        peer_data: &HashMap<PeerId, PeerData>,
+        peer_identities: &HashMap<PeerId, Info>,
+        banned_peers: &HashMap<PeerId, Option<Instant>>,
+    ) -> io::Result<()> {
+        let bytes = Self::encode(peer_data, peer_identities, banned_peers)?;
+        self.write(&bytes)
+    }
+
+    /// Serializes the given peers, to be written by [`PeerDb::write`].
+    pub fn encode(
+        peer_data: &HashMap<PeerId, PeerData>,
+        peer_identities: &HashMap<PeerId, Info>,
+        banned_peers: &HashMap<PeerId, Option<Instant>>,
+    ) -> io::Result<Vec<u8>> {
+        let peers = peer_data
+            .iter()
+            .map(|(peer_id, data)| {
+                let skip = data
+                    .connection_history
+                    .len()
+                    .saturating_sub(MAX_SAVED_CONNECTIONS);
+                let peer = SavedPeer {
+                    multiaddr: data.multiaddr.clone(),
+                    average_connection_duration: data.average_connection_duration,
+                    connection_history: data.connection_history[skip..]
+                        .iter()
+                        .map(SavedConnection::from)
+                        .collect(),
+                    identity: peer_identities.get(peer_id).map(SavedIdentity::from),
+                };
+                (peer_id.to_string(), peer)
+            })
+            .collect();
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let bans = banned_peers
+            .iter()
+            .map(|(peer_id, until)| {
+                let ban = match until {
+                    Some(until) => {
+                        SavedBan::Until(system_now + until.saturating_duration_since(now))
+                    }
+                    None => SavedBan::Permanent,
+                };
+                (peer_id.to_string(), ban)
+            })
+            .collect();
+        serde_json::to_vec(&SavedPeers { peers, bans })
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Replaces the database with the encoded peers, through a temporary file so that a crash
+    /// while saving keeps the previous one.
+    pub fn write(&self, bytes: &[u8]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, bytes)?;
+        fs::rename(&tmp_path, &self.path)?;
+        debug!("Saved the peers to {}", self.path.display());
+        Ok(())
+    }
+}
+
+fn to_system_time(instant: Instant, now: Instant, system_now: SystemTime) -> SystemTime {
+    system_now - now.saturating_duration_since(instant)
+}
+
+// Instants before the start of the process can't be represented on some platforms, those fall
+// back to now
+fn to_instant(time: SystemTime, now: Instant, system_now: SystemTime) -> Instant {
+    system_now
+        .duration_since(time)
+        .ok()
+        .and_then(|elapsed| now.checked_sub(elapsed))
+        .unwrap_or(now)
+}
+
+impl From<&ConnectionData> for SavedConnection {
+    fn from(connection: &ConnectionData) -> Self {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let convert = |instant| to_system_time(instant, now, system_now);
+        SavedConnection {
+            established: connection.established_timestamp.map(convert),
+            failure: connection.failure_timestamp.map(convert),
+            disconnect: connection.disconnect_timestamp.map(convert),
+            dial: convert(connection.dial_timestamp),
+            status: connection.connection_status,
+        }
+    }
+}
+
+impl SavedConnection {
+    fn into_connection_data(self, saved_at: SystemTime) -> ConnectionData {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let convert = |time| to_instant(time, now, system_now);
+        let mut connection = ConnectionData {
+            established_timestamp: self.established.map(convert),
+            failure_timestamp: self.failure.map(convert),
+            disconnect_timestamp: self.disconnect.map(convert),
+            dial_timestamp: convert(self.dial),
+            connection_status: self.status,
+        };
+        match connection.connection_status {
+            ConnectionStatus::Connected => {
+                connection.connection_status = ConnectionStatus::Disconnected;
+                connection.disconnect_timestamp = Some(convert(saved_at));
+            }
+            ConnectionStatus::Connecting => {
+                connection.connection_status = ConnectionStatus::Timeout;
+                connection.failure_timestamp = Some(convert(saved_at));
+            }
+            _ => {}
+        }
+        connection
+    }
+}
+
+impl From<&Info> for SavedIdentity {
+    fn from(info: &Info) -> Self {
+        SavedIdentity {
+            public_key: info.public_key.encode_protobuf(),
+            protocol_version: info.protocol_version.clone(),
+            agent_version: info.agent_version.clone(),
+            listen_addrs: info.listen_addrs.clone(),
+            protocols: info.protocols.iter().map(|p| p.to_string()).collect(),
+            observed_addr: info.observed_addr.clone(),
+        }
+    }
+}
+
+impl SavedIdentity {
+    fn into_info(self) -> Option<Info> {
+        Some(Info {
+            public_key: PublicKey::try_decode_protobuf(&self.public_key).ok()?,
+            protocol_version: self.protocol_version,
+            agent_version: self.agent_version,
+            listen_addrs: self.listen_addrs,
+            protocols: self
+                .protocols
+                .into_iter()
+                .filter_map(|p| StreamProtocol::try_from_owned(p).ok())
+                .collect(),
+            observed_addr: self.observed_addr,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::identity::Keypair;
+    use std::time::Duration;
+
+    fn connection(status: ConnectionStatus, dial_timestamp: Instant) -> ConnectionData {
+        ConnectionData {
+            established_timestamp: Some(dial_timestamp),
+            failure_timestamp: None,
+            disconnect_timestamp: None,
+            dial_timestamp,
+            connection_status: status,
+        }
+    }
+
+    fn identity(keypair: &Keypair) -> Info {
+        Info {
+            public_key: keypair.public(),
+            protocol_version: "/avs/1".to_string(),
+            agent_version: "avs-node".to_string(),
+            listen_addrs: vec!["/ip4/10.0.0.1/tcp/9000".parse().unwrap()],
+            protocols: vec![StreamProtocol::new("/ipfs/id/1.0.0")],
+            observed_addr: "/ip4/10.0.0.2/tcp/9000".parse().unwrap(),
+        }
+    }
+
+    fn is_close(a: Instant, b: Instant) -> bool {
+        a.max(b).duration_since(a.min(b)) < Duration::from_millis(100)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let peer_db = PeerDb::new(dir.path().join("peers.json"));
+        let now = Instant::now();
+        let keypair = Keypair::generate_ed25519();
+        let peer = keypair.public().to_peer_id();
+        let mut history: Vec<ConnectionData> = (0..MAX_SAVED_CONNECTIONS + 2)
+            .map(|_| connection(ConnectionStatus::Disconnected, now))
+            .collect();
+        history[MAX_SAVED_CONNECTIONS + 1].connection_status = ConnectionStatus::Failed;
+        let peer_data = HashMap::from([(
+            peer,
+            PeerData {
+                connection_history: history,
+                average_connection_duration: Some(5000),
+                multiaddr: Some("/ip4/10.0.0.1/tcp/9000".parse().unwrap()),
+            },
+        )]);
+        let peer_identities = HashMap::from([(peer, identity(&keypair))]);
+        let (temporary, permanent, expired) =
+            (PeerId::random(), PeerId::random(), PeerId::random());
+        let banned_peers = HashMap::from([
+            (temporary, Some(now + Duration::from_secs(3600))),
+            (permanent, None),
+            (expired, Some(now)),
+        ]);
+
+        peer_db
+            .save(&peer_data, &peer_identities, &banned_peers)
+            .unwrap();
+        let loaded = peer_db.load().unwrap();
+
+        let loaded_peer = &loaded.peer_data[&peer];
+        assert_eq!(loaded_peer.multiaddr, peer_data[&peer].multiaddr);
+        assert_eq!(loaded_peer.average_connection_duration, Some(5000));
+        // only the latest connections are kept
+        assert_eq!(loaded_peer.connection_history.len(), MAX_SAVED_CONNECTIONS);
+        assert_eq!(
+            loaded_peer
+                .connection_history
+                .last()
+                .unwrap()
+                .connection_status,
+            ConnectionStatus::Failed
+        );
+        assert!(is_close(
+            loaded_peer.connection_history[0].dial_timestamp,
+            now
+        ));
+        assert_eq!(loaded.peer_identities[&peer].public_key, keypair.public());
+        assert_eq!(
+            loaded.peer_identities[&peer].protocols,
+            peer_identities[&peer].protocols
+        );
+        assert!(is_close(
+            loaded.banned_peers[&temporary].unwrap(),
+            now + Duration::from_secs(3600)
+        ));
+        assert_eq!(loaded.banned_peers[&permanent], None);
+        assert!(!loaded.banned_peers.contains_key(&expired));
+    }
+
+    #[test]
+    fn test_missing_database_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded = PeerDb::new(dir.path().join("peers.json")).load().unwrap();
+        assert!(loaded.peer_data.is_empty());
+        assert!(loaded.banned_peers.is_empty());
+    }
+
+    #[test]
+    fn test_time_conversion() {
+        let now = Instant::now();
+        let system_now = SystemTime::now();
+        let ago = Duration::from_secs(60);
+
+        let instant = now.checked_sub(ago).unwrap();
+        let time = to_system_time(instant, now, system_now);
+        assert_eq!(time, system_now - ago);
+        assert_eq!(to_instant(time, now, system_now), instant);
+        // future times are clamped to now
+        assert_eq!(to_instant(system_now + ago, now, system_now), now);
+        assert_eq!(to_system_time(now + ago, now, system_now), system_now);
+    }
+
+    #[test]
+    fn test_open_connections_are_closed_on_load() {
+        let saved_at = SystemTime::now() - Duration::from_secs(10);
+        let saved = |status| {
+            SavedConnection::from(&connection(status, Instant::now()))
+                .into_connection_data(saved_at)
+        };
+
+        let connected = saved(ConnectionStatus::Connected);
+        assert_eq!(connected.connection_status, ConnectionStatus::Disconnected);
+        let disconnect = connected.disconnect_timestamp.unwrap();
+        assert!(is_close(
+            disconnect + Duration::from_secs(10),
+            Instant::now()
+        ));
+
+        let connecting = saved(ConnectionStatus::Connecting);
+        assert_eq!(connecting.connection_status, ConnectionStatus::Timeout);
+        assert!(connecting.failure_timestamp.is_some());
+
+        let failed = saved(ConnectionStatus::Failed);
+        assert_eq!(failed.connection_status, ConnectionStatus::Failed);
+        assert!(failed.disconnect_timestamp.is_none());
+    }
+}
@@ -1,14 +1,17 @@
 use libp2p::swarm::behaviour::{ConnectionClosed, ConnectionEstablished, DialFailure, FromSwarm};
 use libp2p::swarm::dummy::ConnectionHandler;
-use libp2p::swarm::{ConnectionDenied, ConnectionId, NetworkBehaviour, ToSwarm};
+use libp2p::swarm::{CloseConnection, ConnectionDenied, ConnectionId, NetworkBehaviour, ToSwarm};
 use libp2p::{identify::Info, Multiaddr, PeerId};
 use serde::{Deserialize, Serialize};
 
+use crate::peer_db::PeerDb;
+
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 pub struct PeerManager {
     connected_peers: HashSet<PeerId>,
@@ -20,6 +23,17 @@ pub struct PeerManager {
     peers_to_discover: u32,
     heartbeat: tokio::time::Interval,
     waiting_for_peer_discovery: bool,
+    peer_db: Option<PeerDb>,
+    heartbeats_since_save: u32,
+    bans_changed: bool,
+    save_task: Option<tokio::task::JoinHandle<()>>,
+    // banned peers and the end of their ban, none for a permanent one
+    banned_peers: HashMap<PeerId, Option<Instant>>,
+    penalties: HashMap<PeerId, u32>,
+    ban_counts: HashMap<PeerId, u32>,
+    rate_limiters: HashMap<PeerId, RateLimiter>,
+    max_messages_per_sec: u32,
+    peers_to_disconnect: Vec<PeerId>,
 }
 
 #[derive(Debug)]
@@ -62,7 +76,7 @@ pub struct ConnectionData {
     pub connection_status: ConnectionStatus,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ConnectionStatus {
     Connecting,
     Connected,
@@ -71,16 +85,92 @@ pub enum ConnectionStatus {
     Timeout,
 }
 
+/// Misbehaviour of a peer, reported by the network or the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeerOffence {
+    /// Gossip message rejected by the node, or invalid sync response.
+    InvalidMessage,
+    /// Message dropped for exceeding the rate limit of the peer.
+    RateLimited,
+}
+
+impl PeerOffence {
+    fn penalty(&self) -> u32 {
+        match self {
+            PeerOffence::InvalidMessage => 10,
+            PeerOffence::RateLimited => 1,
+        }
+    }
+}
+
+/// Default of the messages per second accepted from each peer.
+pub const DEFAULT_MAX_MESSAGES_PER_SEC: u32 = 20;
+
+// Peers are banned when their penalty reaches this, it is halved at every heartbeat
+const BAN_PENALTY: u32 = 30;
+// Length of the first ban of a peer, doubled at each of the following ones
+const BAN_DURATION: Duration = Duration::from_secs(3600);
+// Peers banned again after this many bans are banned permanently
+const MAX_TEMPORARY_BANS: u32 = 3;
+
+// Token bucket allowing bursts of twice the rate
+struct RateLimiter {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    fn new(max_messages_per_sec: u32) -> Self {
+        RateLimiter {
+            tokens: 2.0 * max_messages_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn allow(&mut self, max_messages_per_sec: u32, now: Instant) -> bool {
+        let rate = max_messages_per_sec as f64;
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(2.0 * rate);
+        self.last_refill = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[derive(Debug)]
+struct BannedPeer(PeerId);
+
+impl fmt::Display for BannedPeer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "peer {} is banned", self.0)
+    }
+}
+
+impl std::error::Error for BannedPeer {}
+
 // Interval for which to check if we need to dial new peers
 const HEARTBEAT_INTERVAL: u64 = 30000;
 // Consider connection attempt timed out if it takes longer than this duration (in ms)
 const DIAL_TIMEOUT: u64 = 120000;
+// The peers are saved every this many heartbeats, and at the next one after a ban changed
+const SAVE_PEERS_HEARTBEATS: u32 = 10;
+// Above this many known peers the ones disconnected for the longest are forgotten
+const MAX_KNOWN_PEERS: usize = 1000;
 
 impl NetworkBehaviour for PeerManager {
     type ConnectionHandler = ConnectionHandler;
     type ToSwarm = PeerManagerEvent;
 
     fn poll(&mut self, cx: &mut Context<'_>) -> Poll<ToSwarm<Self::ToSwarm, void::Void>> {
+        if let Some(peer_id) = self.peers_to_disconnect.pop() {
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id,
+                connection: CloseConnection::All,
+            });
+        }
         // perform the heartbeat when necessary
         if !self.waiting_for_peer_discovery && self.peers_to_discover > 0 {
             let ev = Poll::Ready(ToSwarm::GenerateEvent(PeerManagerEvent::DiscoverPeers(
@@ -92,6 +182,8 @@ impl NetworkBehaviour for PeerManager {
         }
         while self.heartbeat.poll_tick(cx).is_ready() {
             self.timeout_dialling_peers();
+            self.decay_penalties();
+            self.save_peers();
             let missing_peers =
                 self.target_peer_number - self.connected_and_dialling_peers().len() as u32;
             if missing_peers > self.peers_to_discover / 4 {
@@ -146,6 +238,7 @@ impl NetworkBehaviour for PeerManager {
         remote_addr: &libp2p::Multiaddr,
     ) -> Result<libp2p::swarm::THandler<Self>, ConnectionDenied> {
         debug!("Inbound connection: {:?} -> {:?}", peer_id, remote_addr);
+        self.deny_banned(peer_id)?;
         Ok(ConnectionHandler)
     }
 
@@ -158,25 +251,200 @@ impl NetworkBehaviour for PeerManager {
         _port_use: libp2p::core::transport::PortUse,
     ) -> Result<libp2p::swarm::THandler<Self>, libp2p::swarm::ConnectionDenied> {
         debug!("Outbound connection: {:?} -> {:?}", peer_id, addr);
+        self.deny_banned(peer_id)?;
         Ok(ConnectionHandler)
     }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: libp2p::core::Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        if let Some(peer_id) = maybe_peer {
+            self.deny_banned(peer_id)?;
+        }
+        Ok(vec![])
+    }
 }
 impl PeerManager {
-    pub fn new(target_peer_number: u32) -> Self {
+    /// The peers saved in `peer_db` are loaded, the first heartbeat redials the best of them.
+    pub fn new(
+        target_peer_number: u32,
+        max_messages_per_sec: u32,
+        peer_db: Option<PeerDb>,
+    ) -> Self {
         // Set up the peer manager heartbeat interval
         let heartbeat =
             tokio::time::interval(tokio::time::Duration::from_millis(HEARTBEAT_INTERVAL));
+        let loaded = match peer_db.as_ref().map(PeerDb::load) {
+            Some(Ok(loaded)) => loaded,
+            Some(Err(e)) => {
+                warn!("Failed to load the peer database: {}", e);
+                Default::default()
+            }
+            None => Default::default(),
+        };
+        // peers never dialled are dialled again as new ones
+        let new_peers = loaded
+            .peer_data
+            .iter()
+            .filter(|(_, peer_data)| peer_data.connection_history.is_empty())
+            .map(|(peer_id, _)| *peer_id)
+            .collect();
         Self {
-            new_peers: HashSet::new(),
+            new_peers,
             connected_peers: HashSet::new(),
             dialling_peers: HashSet::new(),
-            peer_data: HashMap::new(),
-            peer_identities: HashMap::new(),
+            peer_data: loaded.peer_data,
+            peer_identities: loaded.peer_identities,
             peers_to_discover: 0,
             target_peer_number,
             heartbeat,
             waiting_for_peer_discovery: false,
+            peer_db,
+            heartbeats_since_save: 0,
+            bans_changed: false,
+            save_task: None,
+            banned_peers: loaded.banned_peers,
+            penalties: HashMap::new(),
+            ban_counts: HashMap::new(),
+            rate_limiters: HashMap::new(),
+            max_messages_per_sec,
+            peers_to_disconnect: Vec::new(),
+        }
+    }
+
+    // The file is written on the blocking thread pool, to keep the IO out of the swarm polling
+    fn save_peers(&mut self) {
+        self.heartbeats_since_save += 1;
+        if self.heartbeats_since_save < SAVE_PEERS_HEARTBEATS && !self.bans_changed {
+            return;
+        }
+        let Some(peer_db) = &self.peer_db else {
+            return;
+        };
+        // the previous save is still running, retry at the next heartbeat
+        if self
+            .save_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            return;
         }
+        self.heartbeats_since_save = 0;
+        self.bans_changed = false;
+        let bytes = match PeerDb::encode(&self.peer_data, &self.peer_identities, &self.banned_peers)
+        {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!("Failed to encode the peers: {}", e);
+                return;
+            }
+        };
+        let peer_db = peer_db.clone();
+        self.save_task = Some(tokio::task::spawn_blocking(move || {
+            if let Err(e) = peer_db.write(&bytes) {
+                warn!(
+                    "Failed to save the peers to {}: {}",
+                    peer_db.path().display(),
+                    e
+                );
+            }
+        }));
+    }
+
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        match self.banned_peers.get(peer_id) {
+            Some(Some(until)) => *until > Instant::now(),
+            Some(None) => true,
+            None => false,
+        }
+    }
+
+    fn deny_banned(&self, peer_id: PeerId) -> Result<(), ConnectionDenied> {
+        if self.is_banned(&peer_id) {
+            debug!("Denied connection with banned peer {}", peer_id);
+            return Err(ConnectionDenied::new(BannedPeer(peer_id)));
+        }
+        Ok(())
+    }
+
+    /// Bans the peer for `duration`, or permanently, and disconnects it.
+    pub fn ban_peer(&mut self, peer_id: PeerId, duration: Option<Duration>) {
+        match duration {
+            Some(duration) => info!("Banning peer {} for {:?}", peer_id, duration),
+            None => info!("Banning peer {} permanently", peer_id),
+        }
+        self.banned_peers
+            .insert(peer_id, duration.map(|duration| Instant::now() + duration));
+        self.bans_changed = true;
+        self.new_peers.remove(&peer_id);
+        self.dialling_peers.remove(&peer_id);
+        if self.connected_peers.contains(&peer_id) {
+            self.peers_to_disconnect.push(peer_id);
+        }
+    }
+
+    pub fn unban_peer(&mut self, peer_id: &PeerId) {
+        if self.banned_peers.remove(peer_id).is_some() {
+            info!("Unbanned peer {}", peer_id);
+            self.bans_changed = true;
+        }
+    }
+
+    /// Adds the penalty of the offence to the peer, which is banned once it reaches the limit.
+    /// Each ban lasts twice longer than the previous one, until the peer is banned for good.
+    pub fn report_peer(&mut self, peer_id: PeerId, offence: PeerOffence) {
+        if self.is_banned(&peer_id) {
+            return;
+        }
+        let penalty = self.penalties.entry(peer_id).or_default();
+        *penalty += offence.penalty();
+        debug!(
+            "Peer {} reported for {:?}, penalty {}",
+            peer_id, offence, penalty
+        );
+        if *penalty < BAN_PENALTY {
+            return;
+        }
+        self.penalties.remove(&peer_id);
+        let ban_count = self.ban_counts.entry(peer_id).or_default();
+        *ban_count += 1;
+        let duration =
+            (*ban_count <= MAX_TEMPORARY_BANS).then(|| BAN_DURATION * (1 << (*ban_count - 1)));
+        self.ban_peer(peer_id, duration);
+    }
+
+    /// Whether a message of the peer is within its rate limit, the ones above it are reported.
+    pub fn allow_message(&mut self, peer_id: PeerId) -> bool {
+        let max_messages_per_sec = self.max_messages_per_sec;
+        let allowed = self
+            .rate_limiters
+            .entry(peer_id)
+            .or_insert_with(|| RateLimiter::new(max_messages_per_sec))
+            .allow(max_messages_per_sec, Instant::now());
+        if !allowed {
+            debug!("Peer {} exceeded its rate limit", peer_id);
+            self.report_peer(peer_id, PeerOffence::RateLimited);
+        }
+        allowed
+    }
+
+    fn decay_penalties(&mut self) {
+        self.penalties.retain(|_, penalty| {
+            *penalty /= 2;
+            *penalty > 0
+        });
+        let now = Instant::now();
+        self.banned_peers.retain(|peer_id, until| match until {
+            Some(until) if *until <= now => {
+                info!("Ban of peer {} expired", peer_id);
+                false
+            }
+            _ => true,
+        });
     }
 
     fn timeout_dialling_peers(&mut self) {
@@ -230,6 +498,9 @@ impl PeerManager {
             if peers_to_dial.len() == missing_peers as usize {
                 break;
             }
+            if self.is_banned(peer_id) {
+                continue;
+            }
             peers_to_dial.push(*peer_id);
         }
         peers_to_dial
@@ -237,16 +508,51 @@ impl PeerManager {
 
     pub fn add_peers(&mut self, peer_ids: HashMap<PeerId, Option<Multiaddr>>) {
         for (peer_id, multiaddr) in peer_ids.iter() {
-            if self.peer_data.contains_key(peer_id) {
+            if self.peer_data.contains_key(peer_id) || self.is_banned(peer_id) {
                 continue;
             }
             let multiaddr = multiaddr.as_ref().cloned();
             self.peer_data.insert(*peer_id, PeerData::new(multiaddr));
             self.new_peers.insert(*peer_id);
         }
+        self.forget_old_peers();
         self.waiting_for_peer_discovery = false;
     }
 
+    // Forgets the peers beyond MAX_KNOWN_PEERS, the ones disconnected for the longest first and
+    // the just discovered ones last
+    fn forget_old_peers(&mut self) {
+        let excess = self.peer_data.len().saturating_sub(MAX_KNOWN_PEERS);
+        if excess == 0 {
+            return;
+        }
+        let mut candidates: Vec<(PeerId, Option<Instant>)> = self
+            .peer_data
+            .iter()
+            .filter(|(peer_id, _)| {
+                !self.connected_peers.contains(peer_id) && !self.dialling_peers.contains(peer_id)
+            })
+            .map(|(peer_id, peer_data)| (*peer_id, PeerManager::last_seen(peer_data)))
+            .collect();
+        candidates.sort_by_key(|(_, last_seen)| (last_seen.is_none(), *last_seen));
+        candidates.truncate(excess);
+        for (peer_id, _) in candidates.iter() {
+            self.peer_data.remove(peer_id);
+            self.peer_identities.remove(peer_id);
+            self.new_peers.remove(peer_id);
+        }
+        debug!("Forgot {} old peers", candidates.len());
+    }
+
+    fn last_seen(peer_data: &PeerData) -> Option<Instant> {
+        peer_data.connection_history.last().map(|connection| {
+            connection
+                .disconnect_timestamp
+                .or(connection.failure_timestamp)
+                .unwrap_or(connection.dial_timestamp)
+        })
+    }
+
     fn peer_connecting(&mut self, peer_id: PeerId) {
         let peer_data = self.peer_data.get_mut(&peer_id).unwrap();
         peer_data.connection_history.push(ConnectionData {
@@ -307,6 +613,7 @@ impl PeerManager {
             }
         }
         self.connected_peers.remove(&peer_id);
+        self.rate_limiters.remove(&peer_id);
     }
 
     fn on_dial_failure(&mut self, peer_id: Option<PeerId>) {
@@ -392,6 +699,7 @@ impl PeerManager {
             .filter(|(_, peer_data)| {
                 peer_data.average_connection_duration.unwrap_or(0) > MIN_AVERAGE_CONNECTION_DURATION
             })
+            .filter(|(peer_id, _)| !self.is_banned(peer_id))
             .map(|(peer_id, peer_data)| (*peer_id, peer_data))
             .collect();
         peer_data.sort_by(|(_, a), (_, b)| {
@@ -428,7 +736,189 @@ impl PeerManager {
                 peer_address.push(address.clone());
             }
         }
+        // peers loaded from the database may only be known by the addresses they advertised
+        if peer_address.is_empty() {
+            if let Some(identity) = self.peer_identities.get(peer_id) {
+                peer_address.extend(identity.listen_addrs.iter().cloned());
+            }
+        }
 
         peer_address
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disconnected(peer_manager: &mut PeerManager, peer_id: PeerId, ago: Duration) {
+        let timestamp = Instant::now().checked_sub(ago).unwrap();
+        peer_manager.peer_data.insert(
+            peer_id,
+            PeerData {
+                connection_history: vec![ConnectionData {
+                    established_timestamp: Some(timestamp),
+                    failure_timestamp: None,
+                    disconnect_timestamp: Some(timestamp),
+                    dial_timestamp: timestamp,
+                    connection_status: ConnectionStatus::Disconnected,
+                }],
+                average_connection_duration: None,
+                multiaddr: None,
+            },
+        );
+    }
+
+    fn ban_duration(peer_manager: &PeerManager, peer_id: &PeerId) -> Option<Duration> {
+        peer_manager.banned_peers[peer_id].map(|until| until.duration_since(Instant::now()))
+    }
+
+    fn is_about(duration: Option<Duration>, expected: Duration) -> bool {
+        duration.is_some_and(|duration| {
+            duration <= expected && expected - duration < Duration::from_secs(1)
+        })
+    }
+
+    #[tokio::test]
+    async fn test_ban_escalation() {
+        let mut peer_manager = PeerManager::new(10, DEFAULT_MAX_MESSAGES_PER_SEC, None);
+        let peer_id = PeerId::random();
+        peer_manager.connected_peers.insert(peer_id);
+
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        assert!(!peer_manager.is_banned(&peer_id));
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        assert!(peer_manager.is_banned(&peer_id));
+        assert!(is_about(
+            ban_duration(&peer_manager, &peer_id),
+            BAN_DURATION
+        ));
+        assert_eq!(peer_manager.peers_to_disconnect, vec![peer_id]);
+        assert!(peer_manager.deny_banned(peer_id).is_err());
+
+        // reports during a ban don't add up
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        assert!(!peer_manager.penalties.contains_key(&peer_id));
+
+        for ban in 2..=MAX_TEMPORARY_BANS {
+            peer_manager.unban_peer(&peer_id);
+            for _ in 0..BAN_PENALTY / PeerOffence::InvalidMessage.penalty() {
+                peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+            }
+            assert!(is_about(
+                ban_duration(&peer_manager, &peer_id),
+                BAN_DURATION * (1 << (ban - 1))
+            ));
+        }
+        peer_manager.unban_peer(&peer_id);
+        for _ in 0..BAN_PENALTY / PeerOffence::InvalidMessage.penalty() {
+            peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        }
+        assert_eq!(peer_manager.banned_peers[&peer_id], None);
+        assert!(peer_manager.is_banned(&peer_id));
+    }
+
+    #[tokio::test]
+    async fn test_penalties_decay_and_bans_expire() {
+        let mut peer_manager = PeerManager::new(10, DEFAULT_MAX_MESSAGES_PER_SEC, None);
+        let peer_id = PeerId::random();
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        peer_manager.decay_penalties();
+        assert_eq!(peer_manager.penalties[&peer_id], 10);
+        peer_manager.report_peer(peer_id, PeerOffence::InvalidMessage);
+        assert!(!peer_manager.is_banned(&peer_id));
+
+        let expired = PeerId::random();
+        peer_manager
+            .banned_peers
+            .insert(expired, Some(Instant::now()));
+        assert!(!peer_manager.is_banned(&expired));
+        peer_manager.decay_penalties();
+        assert!(!peer_manager.banned_peers.contains_key(&expired));
+    }
+
+    #[test]
+    fn test_rate_limit_window() {
+        let rate = 10;
+        let mut rate_limiter = RateLimiter::new(rate);
+        let start = rate_limiter.last_refill;
+
+        // bursts of twice the rate
+        for _ in 0..2 * rate {
+            assert!(rate_limiter.allow(rate, start));
+        }
+        assert!(!rate_limiter.allow(rate, start));
+        // one message every 1/rate second
+        assert!(!rate_limiter.allow(rate, start + Duration::from_millis(50)));
+        assert!(rate_limiter.allow(rate, start + Duration::from_millis(100)));
+        assert!(!rate_limiter.allow(rate, start + Duration::from_millis(100)));
+        // the tokens don't pile up beyond the burst
+        let later = start + Duration::from_secs(60);
+        for _ in 0..2 * rate {
+            assert!(rate_limiter.allow(rate, later));
+        }
+        assert!(!rate_limiter.allow(rate, later));
+    }
+
+    #[tokio::test]
+    async fn test_messages_above_the_rate_limit_are_reported() {
+        let mut peer_manager = PeerManager::new(10, 1, None);
+        let peer_id = PeerId::random();
+        assert!(peer_manager.allow_message(peer_id));
+        assert!(peer_manager.allow_message(peer_id));
+        assert!(!peer_manager.allow_message(peer_id));
+        assert_eq!(
+            peer_manager.penalties[&peer_id],
+            PeerOffence::RateLimited.penalty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bans_persist_in_the_peer_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("peers.json");
+        let mut peer_manager = PeerManager::new(
+            10,
+            DEFAULT_MAX_MESSAGES_PER_SEC,
+            Some(PeerDb::new(path.clone())),
+        );
+        let (temporary, permanent) = (PeerId::random(), PeerId::random());
+        peer_manager.ban_peer(temporary, Some(BAN_DURATION));
+        peer_manager.ban_peer(permanent, None);
+
+        // saved at the next heartbeat after a ban
+        peer_manager.save_peers();
+        peer_manager.save_task.take().unwrap().await.unwrap();
+
+        let restarted = PeerManager::new(10, DEFAULT_MAX_MESSAGES_PER_SEC, Some(PeerDb::new(path)));
+        assert!(is_about(ban_duration(&restarted, &temporary), BAN_DURATION));
+        assert!(restarted.deny_banned(temporary).is_err());
+        assert!(restarted.deny_banned(permanent).is_err());
+        assert!(restarted.deny_banned(PeerId::random()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_oldest_disconnected_peers_are_forgotten() {
+        let mut peer_manager = PeerManager::new(10, DEFAULT_MAX_MESSAGES_PER_SEC, None);
+        let oldest = PeerId::random();
+        let connected = PeerId::random();
+        disconnected(&mut peer_manager, oldest, Duration::from_secs(60));
+        disconnected(&mut peer_manager, connected, Duration::from_secs(120));
+        peer_manager.connected_peers.insert(connected);
+        for _ in 0..MAX_KNOWN_PEERS - 3 {
+            disconnected(&mut peer_manager, PeerId::random(), Duration::from_secs(1));
+        }
+
+        let discovered: Vec<PeerId> = (0..2).map(|_| PeerId::random()).collect();
+        peer_manager.add_peers(discovered.iter().map(|peer_id| (*peer_id, None)).collect());
+
+        assert_eq!(peer_manager.peer_data.len(), MAX_KNOWN_PEERS);
+        assert!(!peer_manager.peer_data.contains_key(&oldest));
+        assert!(peer_manager.peer_data.contains_key(&connected));
+        for peer_id in discovered {
+            assert!(peer_manager.new_peers.contains(&peer_id));
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use libp2p::request_response::{self, ProtocolSupport};
+use libp2p::{PeerId, StreamProtocol};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::oneshot;
+
+/// Most blocks a peer is asked for, or serves, in one request.
+pub const MAX_BLOCKS_PER_REQUEST: u64 = 32;
+/// Responses are cut before reaching the 10 MB limit of the codec.
+pub const MAX_RESPONSE_BYTES: usize = 8 * 1_048_576;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Asks a peer for the preconfirmations of the blocks `from_block..=to_block`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncRequest {
+    pub from_block: u64,
+    pub to_block: u64,
+}
+
+impl SyncRequest {
+    /// Range starting at `from_block`, capped to [`MAX_BLOCKS_PER_REQUEST`] blocks.
+    pub fn new(from_block: u64, to_block: u64) -> Self {
+        SyncRequest {
+            from_block,
+            to_block: to_block.min(from_block.saturating_add(MAX_BLOCKS_PER_REQUEST - 1)),
+        }
+    }
+}
+
+/// Messages encoded by the node, ordered by block id. Blocks the peer doesn't know are missing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncResponse {
+    pub messages: Vec<Vec<u8>>,
+}
+
+/// Request of a peer, answered by sending the response to `response`.
+pub struct InboundSyncRequest {
+    pub peer: PeerId,
+    pub request: SyncRequest,
+    pub response: oneshot::Sender<SyncResponse>,
+}
+
+/// Outcome of a request of the node.
+pub struct SyncResult {
+    pub peer: Option<PeerId>,
+    pub request: SyncRequest,
+    pub response: Result<SyncResponse, String>,
+}
+
+/// Network side of the sync channels.
+pub struct SyncChannels {
+    pub requests_rx: Receiver<SyncRequest>,
+    pub results_tx: Sender<SyncResult>,
+    pub inbound_tx: Sender<InboundSyncRequest>,
+}
+
+/// Node side of the sync channels.
+pub struct SyncHandle {
+    pub requests_tx: Sender<SyncRequest>,
+    pub results_rx: Receiver<SyncResult>,
+    pub inbound_rx: Receiver<InboundSyncRequest>,
+}
+
+pub fn channels(capacity: usize) -> (SyncChannels, SyncHandle) {
+    let (requests_tx, requests_rx) = mpsc::channel(capacity);
+    let (results_tx, results_rx) = mpsc::channel(capacity);
+    let (inbound_tx, inbound_rx) = mpsc::channel(capacity);
+    (
+        SyncChannels {
+            requests_rx,
+            results_tx,
+            inbound_tx,
+        },
+        SyncHandle {
+            requests_tx,
+            results_rx,
+            inbound_rx,
+        },
+    )
+}
+
+pub(crate) type Behaviour = request_response::cbor::Behaviour<SyncRequest, SyncResponse>;
+
+/// e.g. `/taiko-avs/167/sync/1`
+pub(crate) fn behaviour(chain_id: u64) -> Behaviour {
+    let protocol = StreamProtocol::try_from_owned(format!("/taiko-avs/{}/sync/1", chain_id))
+        .expect("Valid protocol name");
+    request_response::cbor::Behaviour::new(
+        [(protocol, ProtocolSupport::Full)],
+        request_response::Config::default().with_request_timeout(REQUEST_TIMEOUT),
+    )
+}
@@ -0,0 +1,32 @@
+use libp2p::gossipsub::IdentTopic;
+
+/// Gossip topics of the AVS. Each kind of message has its own topic so that a node only
+/// validates what it understands, and the topics are scoped by the L2 chain id so that the
+/// networks of different chains don't mix even if they share boot nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Preconfirmations,
+    LookaheadAnnouncements,
+    PreconferHandovers,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 3] = [
+        Topic::Preconfirmations,
+        Topic::LookaheadAnnouncements,
+        Topic::PreconferHandovers,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topic::Preconfirmations => "preconfirmations",
+            Topic::LookaheadAnnouncements => "lookahead_announcements",
+            Topic::PreconferHandovers => "preconfer_handovers",
+        }
+    }
+
+    /// e.g. `/taiko-avs/167/preconfirmations/ssz`
+    pub fn ident(&self, chain_id: u64) -> IdentTopic {
+        IdentTopic::new(format!("/taiko-avs/{}/{}/ssz", chain_id, self.name()))
+    }
+}